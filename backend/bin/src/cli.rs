@@ -52,15 +52,24 @@ pub struct Args {
     )]
     settings_file: String,
 
+    /// Sets the directory where the camera snapshots are stored
+    #[arg(long, default_value = "~/.config/radcam-manager/snapshots")]
+    snapshots_path: String,
+
+    /// Sets the maximum disk space used by the camera snapshots, in MiB
+    #[arg(long, value_name = "MIB", default_value = "1024")]
+    snapshots_quota: u64,
+
     /// Deletes settings file before starting.
     #[arg(long)]
     reset: bool,
 
-    /// Sets the mavlink connection string
+    /// Sets the mavlink connection string, like "udpout:127.0.0.1:11001" or "serial:/dev/ttyACM0:115200"
     #[arg(
         long,
         value_name = "<TYPE>:<IP/SERIAL>:<PORT/BAUDRATE>",
-        default_value = "udpout:127.0.0.1:11001"
+        default_value = "udpout:127.0.0.1:11001",
+        value_parser = mavlink_connection_parser
     )]
     mavlink: String,
 
@@ -171,16 +180,96 @@ pub fn settings_file() -> String {
         .to_string()
 }
 
+#[instrument(level = "debug")]
+pub fn snapshots_path() -> String {
+    let snapshots_path = args().snapshots_path.clone();
+
+    shellexpand::full(&snapshots_path)
+        .expect("Failed to expand path")
+        .to_string()
+}
+
+/// The snapshots quota, in bytes
+#[instrument(level = "debug")]
+pub fn snapshots_quota() -> u64 {
+    args().snapshots_quota * 1024 * 1024
+}
+
 #[instrument(level = "debug")]
 pub fn is_reset() -> bool {
     args().reset
 }
 
+/// The kinds of connections understood by `mavlink::connect_async`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MavlinkConnectionKind {
+    /// `udpin`, `udpout`, `tcpin` and `tcpout`, followed by `<HOST>:<PORT>`
+    Network,
+    /// `udpbcast` and `udpcast`, followed by `<HOST>:<PORT>`
+    Broadcast,
+    /// `serial`, followed by `<PORT>:<BAUDRATE>`
+    Serial,
+    /// `file`, followed by `<PATH>`
+    File,
+}
+
+impl MavlinkConnectionKind {
+    fn try_from_kind(kind: &str) -> Option<Self> {
+        match kind {
+            "udpin" | "udpout" | "tcpin" | "tcpout" => Some(Self::Network),
+            "udpbcast" | "udpcast" => Some(Self::Broadcast),
+            "serial" => Some(Self::Serial),
+            "file" => Some(Self::File),
+            _ => None,
+        }
+    }
+
+    /// Whether the address is a host and port, which should be resolved
+    pub fn is_network(&self) -> bool {
+        matches!(self, Self::Network | Self::Broadcast)
+    }
+
+    /// Whether BlueOS can route the vehicle to us through one of its MAVLink endpoints
+    pub fn has_blueos_endpoint(&self) -> bool {
+        matches!(self, Self::Network)
+    }
+}
+
+fn mavlink_connection_parser(value: &str) -> Result<String, String> {
+    let (kind, address) = value
+        .split_once(':')
+        .ok_or_else(|| "Expected <TYPE>:<ADDRESS>".to_string())?;
+
+    if MavlinkConnectionKind::try_from_kind(kind).is_none() {
+        return Err(format!(
+            "Unsupported connection type {kind:?}, expected one of udpin, udpout, tcpin, tcpout, udpbcast, udpcast, serial or file"
+        ));
+    }
+
+    if address.is_empty() {
+        return Err("Missing the connection address".to_string());
+    }
+
+    Ok(value.to_string())
+}
+
+#[instrument(level = "debug")]
+pub fn mavlink_connection_kind() -> MavlinkConnectionKind {
+    let (kind, _address) = args().mavlink.split_once(':').unwrap();
+
+    MavlinkConnectionKind::try_from_kind(kind)
+        .expect("Clap arg \"mavlink\" should always have a valid kind because of its parser.")
+}
+
 #[instrument(level = "debug")]
 pub async fn mavlink_connection_string() -> String {
     let mavlink = args().mavlink.clone();
     let mavlink = shellexpand::full(&mavlink).unwrap();
 
+    if !mavlink_connection_kind().is_network() {
+        return mavlink.to_string();
+    }
+
     let (kind, address) = mavlink.split_once(":").unwrap();
 
     let address = resolve_address(address).await.unwrap();
@@ -224,3 +313,21 @@ async fn resolve_address(address: &str) -> std::io::Result<std::net::SocketAddr>
         )
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mavlink_connection_parser() {
+        assert!(mavlink_connection_parser("udpout:127.0.0.1:11001").is_ok());
+        assert!(mavlink_connection_parser("tcpin:0.0.0.0:5760").is_ok());
+        assert!(mavlink_connection_parser("udpbcast:192.168.2.255:14550").is_ok());
+        assert!(mavlink_connection_parser("serial:/dev/ttyACM0:115200").is_ok());
+        assert!(mavlink_connection_parser("file:/tmp/mavlink.tlog").is_ok());
+
+        assert!(mavlink_connection_parser("127.0.0.1:11001").is_err());
+        assert!(mavlink_connection_parser("serial:").is_err());
+        assert!(mavlink_connection_parser("udpout").is_err());
+    }
+}
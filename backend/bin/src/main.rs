@@ -29,24 +29,40 @@ async fn main() -> Result<()> {
         .await
         .unwrap();
 
+    radcam_commands::camera_backend::init().await.unwrap();
+
+    radcam_commands::snapshot::init(cli::snapshots_path(), cli::snapshots_quota());
+
     let mcm_client_startup_task = tokio::spawn(mcm_client::init(cli::mcm_address().await));
 
+    let time_sync_task = tokio::spawn(radcam_commands::time_sync::time_sync_task(
+        autopilot::autopilot_time,
+    ));
+
     blueos_client::init(cli::blueos_address().await).await;
 
     let autopilot_startup_task = tokio::spawn(async move {
-        loop {
-            if let Err(error) =
-                blueos_client::create_mavlink_endpoint(&cli::mavlink_connection_string().await)
-                    .await
-            {
-                error!("Failed creating MAVLink Endpoint: {error:?}");
-                tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
-                continue;
+        // Direct links (like serial) don't go through BlueOS
+        let mavlink_connection_kind = cli::mavlink_connection_kind();
+        if mavlink_connection_kind.has_blueos_endpoint() {
+            loop {
+                if let Err(error) =
+                    blueos_client::create_mavlink_endpoint(&cli::mavlink_connection_string().await)
+                        .await
+                {
+                    error!("Failed creating MAVLink Endpoint: {error:?}");
+                    tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+                    continue;
+                }
+
+                info!("Successfully created MAVLink endpoint!");
+
+                break;
             }
-
-            info!("Successfully created MAVLink endpoint!");
-
-            break;
+        } else {
+            info!(
+                "Skipping MAVLink endpoint creation for a {mavlink_connection_kind:?} connection"
+            );
         }
 
         loop {
@@ -72,6 +88,7 @@ async fn main() -> Result<()> {
     web::run(cli::web_server().await, cli::default_api_version()).await;
 
     autopilot_startup_task.abort();
+    time_sync_task.abort();
     mcm_client_startup_task.abort();
 
     Ok(())
@@ -4,13 +4,23 @@ use anyhow::Result;
 use autopilot::api;
 use regex::Regex;
 use ts_rs::TS;
+use utils::jobs::{Job, JobState};
 
-use mcm_client::{Camera, Credentials, Stream, mcm_types};
+use mcm_client::{Camera, CameraKind, Credentials, Stream, mcm_types};
 use radcam_commands::{
     Action, CameraControl,
+    batch::{BatchControl, CameraBatchResult, CameraSelector},
+    camera_backend::{
+        BackendAction, BackendControl, Capabilities, EncoderSettings, Encoding, ImageSettings,
+    },
+    camera_config::CameraConfig,
+    firmware::{CameraEntry, CameraVersions, FirmwareUpgradeState, FirmwareUpgradeStatus},
     protocol::display::{
         advanced_display::AdvancedParameterSetting, base_display::BaseParameterSetting,
     },
+    schema::{ActionSchema, EnumOption, FieldDependency, FieldKind, FieldSchema},
+    snapshot::Snapshot,
+    status::{CameraStatus, TimeSource, TimeSyncStatus},
 };
 
 fn main() -> Result<()> {
@@ -59,6 +69,7 @@ fn generate_typescript_bindings_for_mcm_client() -> Result<()> {
         let bindings = [
             Camera::export_to_string()?,
             Credentials::export_to_string()?,
+            CameraKind::export_to_string()?,
             Stream::export_to_string()?,
             mcm_types::VideoEncodeType::export_to_string()?,
             mcm_types::CaptureConfiguration::export_to_string()?,
@@ -144,6 +155,40 @@ fn generate_typescript_bindings_for_autopilot() -> Result<()> {
             api::ScriptFunction::export_to_string()?,
             api::FocusZoomPoints::export_to_string()?,
             api::FocusZoomPoint::export_to_string()?,
+            api::InterpolationMode::export_to_string()?,
+            api::RangefinderOrientation::export_to_string()?,
+            api::DistanceFocusPoints::export_to_string()?,
+            api::DistanceFocusPoint::export_to_string()?,
+            api::Preset::export_to_string()?,
+            api::PresetName::export_to_string()?,
+            api::ImageProfile::export_to_string()?,
+            api::ImageProfiles::export_to_string()?,
+            api::ImageProfileName::export_to_string()?,
+            api::ReapplyPolicy::export_to_string()?,
+            api::ReapplyPolicyConfig::export_to_string()?,
+            api::Rule::export_to_string()?,
+            api::RuleCondition::export_to_string()?,
+            api::Comparison::export_to_string()?,
+            api::RuleAction::export_to_string()?,
+            api::RuleName::export_to_string()?,
+            api::OsdConfig::export_to_string()?,
+            api::OsdField::export_to_string()?,
+            api::OsdItem::export_to_string()?,
+            api::JoystickButton::export_to_string()?,
+            api::ButtonFunction::export_to_string()?,
+            api::JoystickButtonAssignment::export_to_string()?,
+            api::ScriptButton::export_to_string()?,
+            api::ScriptButtonAction::export_to_string()?,
+            api::JoystickMapping::export_to_string()?,
+            api::ActuatorRate::export_to_string()?,
+            api::ActuatorsEvent::export_to_string()?,
+            api::StateChanged::export_to_string()?,
+            api::ParameterChanged::export_to_string()?,
+            api::ConfigProgress::export_to_string()?,
+            api::ConfigStage::export_to_string()?,
+            api::SettingsDrift::export_to_string()?,
+            api::DriftReason::export_to_string()?,
+            api::RuleTriggered::export_to_string()?,
         ]
         .join("\n\n");
 
@@ -192,6 +237,31 @@ fn generate_typescript_bindings_for_radcam() -> Result<()> {
             Action::export_to_string()?,
             BaseParameterSetting::export_to_string()?,
             AdvancedParameterSetting::export_to_string()?,
+            Snapshot::export_to_string()?,
+            CameraStatus::export_to_string()?,
+            TimeSyncStatus::export_to_string()?,
+            TimeSource::export_to_string()?,
+            CameraConfig::export_to_string()?,
+            CameraEntry::export_to_string()?,
+            CameraVersions::export_to_string()?,
+            FirmwareUpgradeStatus::export_to_string()?,
+            FirmwareUpgradeState::export_to_string()?,
+            ActionSchema::export_to_string()?,
+            FieldSchema::export_to_string()?,
+            FieldKind::export_to_string()?,
+            EnumOption::export_to_string()?,
+            FieldDependency::export_to_string()?,
+            BatchControl::export_to_string()?,
+            CameraSelector::export_to_string()?,
+            CameraBatchResult::export_to_string()?,
+            Job::export_to_string()?,
+            JobState::export_to_string()?,
+            BackendControl::export_to_string()?,
+            BackendAction::export_to_string()?,
+            Capabilities::export_to_string()?,
+            ImageSettings::export_to_string()?,
+            EncoderSettings::export_to_string()?,
+            Encoding::export_to_string()?,
         ]
         .join("\n\n");
 
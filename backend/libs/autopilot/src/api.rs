@@ -1,8 +1,13 @@
+use num_enum::TryFromPrimitive;
+use radcam_commands::protocol::{
+    display::{advanced_display::AdvancedParameterSetting, base_display::BaseParameterSetting},
+    video::video_parameters::VideoParameterSettings,
+};
 use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 use uuid::Uuid;
 
-use crate::{CameraActuators, parameters::ActuatorsParameters};
+use crate::{CameraActuators, interpolation, parameters::ActuatorsParameters};
 
 #[derive(Debug, Clone, Serialize, Deserialize, TS)]
 pub struct ActuatorsControl {
@@ -27,6 +32,48 @@ pub enum Action {
     SetActuatorsConfig(ActuatorsConfig),
     #[serde(rename = "resetActuatorsConfig")]
     ResetActuatorsConfig,
+    #[serde(rename = "getPresets")]
+    GetPresets,
+    #[serde(rename = "setPreset")]
+    SetPreset(Preset),
+    #[serde(rename = "deletePreset")]
+    DeletePreset(PresetName),
+    #[serde(rename = "recallPreset")]
+    RecallPreset(PresetName),
+    #[serde(rename = "getJoystickButtons")]
+    GetJoystickButtons,
+    #[serde(rename = "proposeJoystickMapping")]
+    ProposeJoystickMapping,
+    #[serde(rename = "setJoystickMapping")]
+    SetJoystickMapping(JoystickMapping),
+    #[serde(rename = "startZoom")]
+    StartZoom(ActuatorRate),
+    #[serde(rename = "startFocus")]
+    StartFocus(ActuatorRate),
+    #[serde(rename = "keepAlive")]
+    KeepAlive,
+    #[serde(rename = "stop")]
+    Stop,
+    #[serde(rename = "getOsdConfig")]
+    GetOsdConfig,
+    #[serde(rename = "setOsdConfig")]
+    SetOsdConfig(OsdConfig),
+    #[serde(rename = "getProfiles")]
+    GetImageProfiles,
+    #[serde(rename = "setProfile")]
+    SetImageProfile(ImageProfile),
+    #[serde(rename = "deleteProfile")]
+    DeleteImageProfile(ImageProfileName),
+    #[serde(rename = "applyProfile")]
+    ApplyImageProfile(ImageProfileName),
+    #[serde(rename = "setReapplyPolicy")]
+    SetReapplyPolicy(ReapplyPolicyConfig),
+    #[serde(rename = "getRules")]
+    GetRules,
+    #[serde(rename = "setRule")]
+    SetRule(Rule),
+    #[serde(rename = "deleteRule")]
+    DeleteRule(RuleName),
 }
 
 #[derive(Debug, Default, PartialEq, Serialize, Deserialize, Clone, Copy, TS)]
@@ -34,6 +81,318 @@ pub struct ActuatorsState {
     pub focus: Option<f32>,
     pub zoom: Option<f32>,
     pub tilt: Option<f32>,
+    /// The focus the script is driving the lens to, after the focus and zoom correlation. It is
+    /// read-only
+    #[serde(default)]
+    #[ts(optional)]
+    pub focus_output: Option<f32>,
+}
+
+/// The events pushed through the events WebSocket
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[serde(tag = "event", content = "json")]
+pub enum ActuatorsEvent {
+    #[serde(rename = "stateChanged")]
+    StateChanged(StateChanged),
+    #[serde(rename = "parameterChanged")]
+    ParameterChanged(ParameterChanged),
+    #[serde(rename = "configProgress")]
+    ConfigProgress(ConfigProgress),
+    #[serde(rename = "settingsDrift")]
+    SettingsDrift(SettingsDrift),
+    #[serde(rename = "ruleTriggered")]
+    RuleTriggered(RuleTriggered),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, TS)]
+pub struct StateChanged {
+    pub camera_uuid: Uuid,
+    pub state: ActuatorsState,
+}
+
+/// An autopilot parameter owned by RadCam, which was changed in the autopilot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+pub struct ParameterChanged {
+    pub name: String,
+    pub value: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+pub struct ConfigProgress {
+    pub camera_uuid: Uuid,
+    pub stage: ConfigStage,
+    /// Only present when the stage is `Failed`
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+pub enum ConfigStage {
+    Started,
+    Parameters,
+    Calibration,
+    Script,
+    ReloadingScripts,
+    RebootingAutopilot,
+    Finished,
+    Failed,
+}
+
+/// A camera came back with settings different from its active image profile
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+pub struct SettingsDrift {
+    pub camera_uuid: Uuid,
+    pub profile: String,
+    pub reason: DriftReason,
+    /// Each setting that drifted, as `field: live instead of expected`
+    pub differences: Vec<String>,
+    /// Whether the profile was applied again, as the `Auto` policy does
+    pub reapplied: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+pub enum DriftReason {
+    /// The camera was discovered again, or the manager started
+    Appeared,
+    /// The camera uptime went backwards
+    Rebooted,
+}
+
+/// A rule switched, and its action was applied to the camera
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+pub struct RuleTriggered {
+    pub camera_uuid: Uuid,
+    pub rule: String,
+    /// Whether the condition started holding, otherwise it stopped
+    pub active: bool,
+    /// Only present when the action failed
+    pub error: Option<String>,
+}
+
+/// A normalized rate for continuous zoom (negative: wide, positive: narrow) or focus (negative: near,
+/// positive: far), from -1.0 to 1.0. Zero stops the motion. The motion has to be kept alive by
+/// repeating it or with `keepAlive`, otherwise it stops
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Copy, TS)]
+pub struct ActuatorRate {
+    pub rate: f32,
+}
+
+/// A named framing, which can be recalled to drive the actuators and the image adjustments at once
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, TS)]
+pub struct Preset {
+    pub name: String,
+    pub state: ActuatorsState,
+    pub image_adjustment: Option<BaseParameterSetting>,
+    pub image_adjustment_ex: Option<AdvancedParameterSetting>,
+}
+
+/// The vehicle telemetry overlaid on the camera image, so recordings carry it
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, TS)]
+pub struct OsdConfig {
+    pub enabled: bool,
+    /// How often the overlays are updated, in milliseconds
+    pub update_interval_ms: u64,
+    /// Each field takes one of the camera's text overlays, in order
+    pub fields: Vec<OsdField>,
+}
+
+impl Default for OsdConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            update_interval_ms: 1000,
+            fields: vec![
+                OsdField {
+                    item: OsdItem::Time,
+                    x: 2,
+                    y: 2,
+                },
+                OsdField {
+                    item: OsdItem::Depth,
+                    x: 2,
+                    y: 92,
+                },
+                OsdField {
+                    item: OsdItem::Heading,
+                    x: 80,
+                    y: 92,
+                },
+            ],
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Copy, TS)]
+pub struct OsdField {
+    pub item: OsdItem,
+    /// Horizontal position, in percent of the image width
+    pub x: u8,
+    /// Vertical position, in percent of the image height
+    pub y: u8,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy, TS)]
+pub enum OsdItem {
+    Depth,
+    Heading,
+    Time,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, TS)]
+pub struct PresetName {
+    pub name: String,
+}
+
+/// A named set of image and encoder settings for a scene, such as "clear water" or "turbid"
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, TS)]
+pub struct ImageProfile {
+    pub name: String,
+    pub image_adjustment: Option<BaseParameterSetting>,
+    pub image_adjustment_ex: Option<AdvancedParameterSetting>,
+    /// The encoder settings, one for each channel to change
+    pub video_parameters: Vec<VideoParameterSettings>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize, Clone, TS)]
+pub struct ImageProfiles {
+    pub profiles: Vec<ImageProfile>,
+    /// The last profile applied to the camera
+    pub active_profile: Option<String>,
+    pub reapply_policy: ReapplyPolicy,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, TS)]
+pub struct ImageProfileName {
+    pub name: String,
+}
+
+/// What to do when a camera reconnects or reboots with settings different from its active profile
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize, Clone, Copy, TS)]
+pub enum ReapplyPolicy {
+    /// Apply the active profile again
+    Auto,
+    /// Only publish a `settingsDrift` event
+    #[default]
+    Notify,
+    Ignore,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Copy, TS)]
+pub struct ReapplyPolicyConfig {
+    pub policy: ReapplyPolicy,
+}
+
+/// Switches the camera settings from the vehicle telemetry, like a profile for the deep water or
+/// for when the lights are on
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+pub struct Rule {
+    pub name: String,
+    pub enabled: bool,
+    pub condition: RuleCondition,
+    /// Applied when the condition starts holding
+    pub action: RuleAction,
+    /// Applied when the condition stops holding
+    pub exit_action: Option<RuleAction>,
+    /// How long the condition has to hold, or to stop holding, before the rule switches, in
+    /// milliseconds
+    pub dwell_ms: u64,
+}
+
+/// The conditions on a reading hold while it is beyond the threshold, and only stop holding once it
+/// comes back by more than the hysteresis, so a reading around the threshold doesn't keep switching
+/// the rule
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, TS)]
+pub enum RuleCondition {
+    /// The vehicle depth, in meters
+    Depth {
+        comparison: Comparison,
+        threshold: f32,
+        hysteresis: f32,
+    },
+    /// The PWM output of the lights channel, in microseconds
+    LightsPwm {
+        channel: ServoChannel,
+        comparison: Comparison,
+        threshold: u16,
+        hysteresis: u16,
+    },
+    /// The time of day, from the start to the end minute after midnight UTC. It wraps around
+    /// midnight when the end comes before the start
+    TimeOfDay { start_minute: u16, end_minute: u16 },
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy, TS)]
+pub enum Comparison {
+    Greater,
+    Less,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+pub enum RuleAction {
+    /// Applies one of the camera image profiles
+    ApplyProfile(String),
+    /// Sends a control to the camera
+    CameraAction(radcam_commands::Action),
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, TS)]
+pub struct RuleName {
+    pub name: String,
+}
+
+/// The current functions of an ArduSub joystick button, as the raw `BTNn_FUNCTION` and `BTNn_SFUNCTION` values
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Copy, TS)]
+pub struct JoystickButton {
+    pub button: u8,
+    pub function: u8,
+    pub shift_function: u8,
+}
+
+/// ArduSub joystick button functions used by the RadCam mapping
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS, TryFromPrimitive)]
+#[repr(u8)]
+pub enum ButtonFunction {
+    Disabled = 0,
+    Shift = 1,
+    MountCenter = 21,
+    MountTiltUp = 22,
+    MountTiltDown = 23,
+    Script1 = 108,
+    Script2 = 109,
+    Script3 = 110,
+    Script4 = 111,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Copy, TS)]
+pub struct JoystickButtonAssignment {
+    pub button: u8,
+    /// When true, assigns `BTNn_SFUNCTION` (used while the shift button is held) instead of `BTNn_FUNCTION`
+    pub shift: bool,
+    pub function: ButtonFunction,
+}
+
+/// What the RadCam does when an ArduSub script button (1 to 4) is pressed
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, TS)]
+pub struct ScriptButton {
+    pub button: u8,
+    pub action: ScriptButtonAction,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, TS)]
+pub enum ScriptButtonAction {
+    ZoomIn,
+    ZoomOut,
+    FocusNear,
+    FocusFar,
+    RecallPreset(String),
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize, Clone, TS)]
+pub struct JoystickMapping {
+    pub assignments: Vec<JoystickButtonAssignment>,
+    pub script_buttons: Vec<ScriptButton>,
+    /// Buttons that were (or would be) remapped from another function
+    #[serde(default)]
+    pub warnings: Vec<String>,
 }
 
 #[derive(Debug, Default, PartialEq, Serialize, Deserialize, Clone, TS)]
@@ -41,6 +400,7 @@ pub struct ActuatorsConfig {
     pub parameters: Option<ActuatorsParametersConfig>,
     pub closest_points: Option<FocusZoomPoints>,
     pub furthest_points: Option<FocusZoomPoints>,
+    pub distance_points: Option<DistanceFocusPoints>,
 }
 
 impl From<&CameraActuators> for ActuatorsConfig {
@@ -49,6 +409,7 @@ impl From<&CameraActuators> for ActuatorsConfig {
             parameters: Some((&value.parameters).into()),
             closest_points: Some(value.closest_points.clone()),
             furthest_points: Some(value.furthest_points.clone()),
+            distance_points: Some(value.distance_points.clone()),
         }
     }
 }
@@ -72,6 +433,9 @@ pub struct ActuatorsParametersConfig {
     pub script_channel_trim: Option<u16>,
     pub script_channel_max: Option<u16>,
     pub enable_focus_and_zoom_correlation: Option<bool>,
+    pub focus_interpolation: Option<InterpolationMode>,
+    pub enable_rangefinder_focus: Option<bool>,
+    pub rangefinder_orientation: Option<RangefinderOrientation>,
 
     // Zoom channel parameters
     pub zoom_channel: Option<ServoChannel>,
@@ -107,6 +471,9 @@ impl From<&ActuatorsParameters> for ActuatorsParametersConfig {
             script_channel_trim: Some(value.script_channel_trim),
             script_channel_max: Some(value.script_channel_max),
             enable_focus_and_zoom_correlation: Some(value.enable_focus_and_zoom_correlation),
+            focus_interpolation: Some(value.focus_interpolation),
+            enable_rangefinder_focus: Some(value.enable_rangefinder_focus),
+            rangefinder_orientation: Some(value.rangefinder_orientation),
             zoom_channel: Some(value.zoom_channel),
             zoom_channel_min: Some(value.zoom_channel_min),
             zoom_channel_trim: Some(value.zoom_channel_trim),
@@ -207,14 +574,30 @@ pub enum ScriptFunction {
     SCRIPT16 = 109,
 }
 
+/// How the focus curves are interpolated between the calibration points
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+pub enum InterpolationMode {
+    /// Piecewise-linear
+    #[default]
+    Linear,
+    /// Fritsch–Carlson monotone cubic Hermite spline, which never overshoots the points
+    MonotoneCubic,
+    /// Catmull-Rom cubic Hermite spline
+    CatmullRom,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, PartialOrd, TS)]
 pub struct FocusZoomPoints(pub Vec<FocusZoomPoint>);
 impl FocusZoomPoints {
-    pub fn to_lua(&self) -> String {
-        let entries: Vec<String> = self
-            .0
+    pub fn to_lua(&self, mode: InterpolationMode) -> String {
+        let entries: Vec<String> = interpolation::segments(&self.0, mode)
             .iter()
-            .map(|point| format!("    {{zoom = {}, focus = {}}}", point.zoom, point.focus))
+            .map(|segment| {
+                format!(
+                    "    {{zoom = {}, focus = {}, b = {}, c = {}, d = {}}}",
+                    segment.zoom, segment.focus, segment.b, segment.c, segment.d
+                )
+            })
             .collect();
 
         format!("{{\n{}\n}}", entries.join(",\n"))
@@ -226,3 +609,46 @@ pub struct FocusZoomPoint {
     pub zoom: u32,
     pub focus: u32,
 }
+
+/// The orientation of the rangefinder used for the automatic focus, following ArduPilot's `RNGFNDx_ORIENT`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[repr(u8)]
+pub enum RangefinderOrientation {
+    #[default]
+    Forward = 0,
+    Right = 2,
+    Back = 4,
+    Left = 6,
+    Up = 24,
+    Down = 25,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, PartialOrd, TS)]
+pub struct DistanceFocusPoints(pub Vec<DistanceFocusPoint>);
+impl DistanceFocusPoints {
+    pub fn to_lua(&self) -> String {
+        let mut points = self.0.clone();
+        points.sort_by_key(|point| point.distance);
+
+        let entries: Vec<String> = points
+            .iter()
+            .map(|point| {
+                format!(
+                    "    {{distance = {}, position = {}}}",
+                    point.distance, point.position
+                )
+            })
+            .collect();
+
+        format!("{{\n{}\n}}", entries.join(",\n"))
+    }
+}
+
+/// Maps a rangefinder distance to a focus position between the closest and furthest curves
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, PartialOrd, TS)]
+pub struct DistanceFocusPoint {
+    /// Distance measured by the rangefinder, in centimeters
+    pub distance: u32,
+    /// Focus position, from 0.0 (closest curve) to 1.0 (furthest curve)
+    pub position: f32,
+}
@@ -0,0 +1,55 @@
+use once_cell::sync::Lazy;
+use tokio::sync::broadcast;
+
+use crate::api;
+
+const CHANNEL_CAPACITY: usize = 256;
+
+static EVENTS: Lazy<broadcast::Sender<api::ActuatorsEvent>> =
+    Lazy::new(|| broadcast::channel(CHANNEL_CAPACITY).0);
+
+/// Publishes an event to all the subscribers
+pub(crate) fn publish(event: api::ActuatorsEvent) {
+    utils::broadcast(&EVENTS, event);
+}
+
+pub fn subscribe() -> broadcast::Receiver<api::ActuatorsEvent> {
+    EVENTS.subscribe()
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+
+    #[test]
+    fn test_publish_to_subscribers() {
+        let mut receiver = subscribe();
+
+        let event = api::ActuatorsEvent::ConfigProgress(api::ConfigProgress {
+            camera_uuid: Uuid::nil(),
+            stage: api::ConfigStage::Started,
+            error: None,
+        });
+        publish(event.clone());
+
+        assert_eq!(receiver.try_recv().unwrap(), event);
+    }
+
+    #[test]
+    fn test_event_serialization() {
+        let event = api::ActuatorsEvent::ParameterChanged(api::ParameterChanged {
+            name: "RCAM1_ENABLE".to_string(),
+            value: 1.,
+        });
+
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            serde_json::json!({
+                "event": "parameterChanged",
+                "json": { "name": "RCAM1_ENABLE", "value": 1.0 },
+            })
+        );
+    }
+}
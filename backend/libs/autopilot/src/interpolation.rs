@@ -0,0 +1,218 @@
+use crate::api::{FocusZoomPoint, InterpolationMode};
+
+/// A cubic polynomial segment starting at `zoom`, valid until the next segment's `zoom`:
+/// `focus(x) = focus + b * dx + c * dx^2 + d * dx^3`, with `dx = x - zoom`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Segment {
+    pub zoom: f64,
+    pub focus: f64,
+    pub b: f64,
+    pub c: f64,
+    pub d: f64,
+}
+
+impl Segment {
+    pub fn evaluate(&self, zoom: f64) -> f64 {
+        let dx = zoom - self.zoom;
+        self.focus + dx * (self.b + dx * (self.c + dx * self.d))
+    }
+}
+
+/// Precomputes the coefficients of each segment of the curve, so the Lua script only needs to
+/// evaluate a polynomial in its update loop. The returned segments are sorted by zoom, and the
+/// last one has no slope, as it is only used to clamp the curve's end.
+pub fn segments(points: &[FocusZoomPoint], mode: InterpolationMode) -> Vec<Segment> {
+    let mut points = points.to_vec();
+    points.sort_by_key(|point| point.zoom);
+    points.dedup_by_key(|point| point.zoom);
+
+    let xs: Vec<f64> = points.iter().map(|point| point.zoom as f64).collect();
+    let ys: Vec<f64> = points.iter().map(|point| point.focus as f64).collect();
+    let n = points.len();
+
+    if n < 2 {
+        return xs
+            .iter()
+            .zip(&ys)
+            .map(|(&zoom, &focus)| Segment {
+                zoom,
+                focus,
+                b: 0.,
+                c: 0.,
+                d: 0.,
+            })
+            .collect();
+    }
+
+    let h: Vec<f64> = xs.windows(2).map(|x| x[1] - x[0]).collect();
+    let deltas: Vec<f64> = (0..n - 1).map(|i| (ys[i + 1] - ys[i]) / h[i]).collect();
+
+    let tangents = match mode {
+        InterpolationMode::Linear => None,
+        InterpolationMode::MonotoneCubic => Some(fritsch_carlson_tangents(&deltas)),
+        InterpolationMode::CatmullRom => Some(catmull_rom_tangents(&xs, &ys, &deltas)),
+    };
+
+    let mut segments = Vec::with_capacity(n);
+    for i in 0..n - 1 {
+        let (b, c, d) = match &tangents {
+            None => (deltas[i], 0., 0.),
+            Some(m) => (
+                m[i],
+                (3. * deltas[i] - 2. * m[i] - m[i + 1]) / h[i],
+                (m[i] + m[i + 1] - 2. * deltas[i]) / (h[i] * h[i]),
+            ),
+        };
+
+        segments.push(Segment {
+            zoom: xs[i],
+            focus: ys[i],
+            b,
+            c,
+            d,
+        });
+    }
+    segments.push(Segment {
+        zoom: xs[n - 1],
+        focus: ys[n - 1],
+        b: 0.,
+        c: 0.,
+        d: 0.,
+    });
+
+    segments
+}
+
+/// Tangents from the Fritsch–Carlson method, which keeps the curve monotone between points
+fn fritsch_carlson_tangents(deltas: &[f64]) -> Vec<f64> {
+    let n = deltas.len() + 1;
+
+    let mut m = vec![0.; n];
+    m[0] = deltas[0];
+    m[n - 1] = deltas[n - 2];
+    for i in 1..n - 1 {
+        if deltas[i - 1] * deltas[i] > 0. {
+            m[i] = (deltas[i - 1] + deltas[i]) / 2.;
+        }
+    }
+
+    for i in 0..n - 1 {
+        if deltas[i] == 0. {
+            m[i] = 0.;
+            m[i + 1] = 0.;
+            continue;
+        }
+
+        let alpha = m[i] / deltas[i];
+        let beta = m[i + 1] / deltas[i];
+        if alpha < 0. {
+            m[i] = 0.;
+        }
+        if beta < 0. {
+            m[i + 1] = 0.;
+        }
+
+        let magnitude = alpha.powi(2) + beta.powi(2);
+        if magnitude > 9. {
+            let tau = 3. / magnitude.sqrt();
+            m[i] = tau * alpha * deltas[i];
+            m[i + 1] = tau * beta * deltas[i];
+        }
+    }
+
+    m
+}
+
+/// Tangents from the (non-uniform) Catmull-Rom spline, using one-sided slopes at the ends
+fn catmull_rom_tangents(xs: &[f64], ys: &[f64], deltas: &[f64]) -> Vec<f64> {
+    let n = xs.len();
+
+    let mut m = vec![0.; n];
+    m[0] = deltas[0];
+    m[n - 1] = deltas[n - 2];
+    for i in 1..n - 1 {
+        m[i] = (ys[i + 1] - ys[i - 1]) / (xs[i + 1] - xs[i - 1]);
+    }
+
+    m
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parameters::CLOSEST_POINTS;
+
+    const MODES: [InterpolationMode; 3] = [
+        InterpolationMode::Linear,
+        InterpolationMode::MonotoneCubic,
+        InterpolationMode::CatmullRom,
+    ];
+
+    fn evaluate(segments: &[Segment], zoom: f64) -> f64 {
+        let segment = segments
+            .windows(2)
+            .find(|pair| zoom >= pair[0].zoom && zoom < pair[1].zoom)
+            .map(|pair| pair[0])
+            .unwrap_or(*segments.last().unwrap());
+
+        segment.evaluate(zoom)
+    }
+
+    #[test]
+    fn curves_pass_through_all_points() {
+        for mode in MODES {
+            let segments = segments(CLOSEST_POINTS, mode);
+
+            for pair in segments.windows(2) {
+                let (start, end) = (pair[0], pair[1]);
+                assert!((start.evaluate(start.zoom) - start.focus).abs() < 1e-6);
+                assert!(
+                    (start.evaluate(end.zoom) - end.focus).abs() < 1e-6,
+                    "{mode:?} segment at {} doesn't reach the next point",
+                    start.zoom
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn monotone_cubic_is_monotone() {
+        let segments = segments(CLOSEST_POINTS, InterpolationMode::MonotoneCubic);
+
+        let first = CLOSEST_POINTS.first().unwrap().zoom;
+        let last = CLOSEST_POINTS.last().unwrap().zoom;
+
+        let mut previous = evaluate(&segments, first as f64);
+        for zoom in first..=last {
+            let focus = evaluate(&segments, zoom as f64);
+            assert!(focus >= previous - 1e-9, "Not monotone at zoom {zoom}");
+            previous = focus;
+        }
+    }
+
+    #[test]
+    fn unsorted_and_duplicated_points() {
+        let points = [
+            FocusZoomPoint {
+                zoom: 1200,
+                focus: 1100,
+            },
+            FocusZoomPoint {
+                zoom: 900,
+                focus: 950,
+            },
+            FocusZoomPoint {
+                zoom: 900,
+                focus: 950,
+            },
+        ];
+
+        for mode in MODES {
+            let segments = segments(&points, mode);
+
+            assert_eq!(segments.len(), 2);
+            assert_eq!(segments[0].zoom, 900.);
+            assert!((evaluate(&segments, 1050.) - 1025.).abs() < 1e-6);
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use tracing::*;
 use uuid::Uuid;
 
@@ -33,4 +33,42 @@ impl Manager {
 
         Ok(should_update_script)
     }
+
+    /// The distance points are embedded in the script, so it only needs to be updated
+    #[instrument(level = "debug", skip(self, points))]
+    pub async fn update_distance_points(
+        &mut self,
+        camera_uuid: &Uuid,
+        points: &api::DistanceFocusPoints,
+        overwrite: bool,
+    ) -> Result<bool> {
+        if points.0.len() < 2 {
+            return Err(anyhow!("distance_points requires at least 2 points"));
+        }
+
+        if let Some(point) = points
+            .0
+            .iter()
+            .find(|point| !(0.0..=1.0).contains(&point.position))
+        {
+            return Err(anyhow!(
+                "distance_points position should be between 0 and 1, got {point:?}"
+            ));
+        }
+
+        let current_points = &mut self
+            .settings
+            .actuators
+            .entry(*camera_uuid)
+            .or_default()
+            .distance_points;
+
+        let should_update_script = overwrite || current_points != points;
+        if current_points != points {
+            info!("distance_points changed from {current_points:?} to {points:?}");
+            *current_points = points.clone();
+        }
+
+        Ok(should_update_script)
+    }
 }
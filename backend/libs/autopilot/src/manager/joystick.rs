@@ -0,0 +1,354 @@
+use ::mavlink::ardupilotmega::MavMessage;
+use anyhow::{Context, Result, anyhow};
+use tokio::sync::broadcast;
+use tracing::*;
+use uuid::Uuid;
+
+use crate::{
+    api::{self, ButtonFunction, ScriptButtonAction},
+    manager::{
+        MANAGER, Manager,
+        script::{PARAM_PREFIX, SCRIPT_BUTTON_SUFFIX},
+        tracker,
+    },
+    mavlink::Message,
+    parameters::ParamType,
+};
+
+/// ArduSub supports up to 32 joystick buttons
+const MAX_BUTTONS: u8 = 32;
+/// How much each zoom or focus script button press moves the actuator, in percent
+const SCRIPT_BUTTON_STEP: f32 = 5.0;
+
+impl Manager {
+    /// Reads the joystick buttons from the parameters cache
+    #[instrument(level = "debug", skip(self))]
+    pub async fn get_joystick_buttons(&self) -> Vec<api::JoystickButton> {
+        let mut buttons = Vec::new();
+
+        for button in 0..MAX_BUTTONS {
+            let function = self
+                .mavlink
+                .get_cached_param(&format!("BTN{button}_FUNCTION"))
+                .await;
+            let shift_function = self
+                .mavlink
+                .get_cached_param(&format!("BTN{button}_SFUNCTION"))
+                .await;
+
+            let (Some(function), Some(shift_function)) = (function, shift_function) else {
+                continue;
+            };
+
+            buttons.push(api::JoystickButton {
+                button,
+                function: function.value.as_f64() as u8,
+                shift_function: shift_function.value.as_f64() as u8,
+            });
+        }
+
+        buttons
+    }
+
+    /// Proposes assigning each configured script button to a free joystick button, keeping the
+    /// script buttons that are already assigned
+    #[instrument(level = "debug", skip(self))]
+    pub async fn propose_joystick_mapping(
+        &self,
+        camera_uuid: &Uuid,
+    ) -> Result<api::JoystickMapping> {
+        let script_buttons = self
+            .settings
+            .actuators
+            .get(camera_uuid)
+            .context("Camera not configured")?
+            .script_buttons
+            .clone();
+
+        let buttons = self.get_joystick_buttons().await;
+        if buttons.is_empty() {
+            return Err(anyhow!(
+                "No joystick buttons found in the autopilot parameters, is it running ArduSub?"
+            ));
+        }
+
+        let mut assignments: Vec<api::JoystickButtonAssignment> = Vec::new();
+        let mut warnings = Vec::new();
+
+        for script_button in &script_buttons {
+            let function = script_button_function(script_button.button)?;
+
+            if buttons.iter().any(|button| {
+                button.function == function as u8 || button.shift_function == function as u8
+            }) {
+                continue;
+            }
+
+            let is_free = |button: u8, shift: bool| {
+                !assignments
+                    .iter()
+                    .any(|assignment| assignment.button == button && assignment.shift == shift)
+            };
+
+            // Prefers the shifted functions, as they are usually the least used ones
+            let free_button = buttons
+                .iter()
+                .find(|button| {
+                    button.function != ButtonFunction::Shift as u8
+                        && button.shift_function == ButtonFunction::Disabled as u8
+                        && is_free(button.button, true)
+                })
+                .map(|button| (button.button, true))
+                .or_else(|| {
+                    buttons
+                        .iter()
+                        .find(|button| {
+                            button.function == ButtonFunction::Disabled as u8
+                                && is_free(button.button, false)
+                        })
+                        .map(|button| (button.button, false))
+                });
+
+            match free_button {
+                Some((button, shift)) => assignments.push(api::JoystickButtonAssignment {
+                    button,
+                    shift,
+                    function,
+                }),
+                None => warnings.push(format!(
+                    "No free joystick button for {:?}",
+                    script_button.action
+                )),
+            }
+        }
+
+        Ok(api::JoystickMapping {
+            assignments,
+            script_buttons,
+            warnings,
+        })
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    pub async fn update_joystick_mapping(
+        &mut self,
+        camera_uuid: &Uuid,
+        mapping: &api::JoystickMapping,
+        force_apply: bool,
+    ) -> Result<api::JoystickMapping> {
+        for script_button in &mapping.script_buttons {
+            script_button_function(script_button.button)?;
+        }
+
+        let encoding = self.mavlink.encoding().await;
+        let mut warnings = Vec::new();
+
+        for assignment in &mapping.assignments {
+            let suffix = if assignment.shift {
+                "SFUNCTION"
+            } else {
+                "FUNCTION"
+            };
+            let param_name = format!("BTN{}_{suffix}", assignment.button);
+
+            let new_value = assignment.function as u8;
+
+            let mut param = self.mavlink.get_param(&param_name, false).await?;
+            let old_value = param.value.as_f64() as u8;
+            param
+                .value
+                .set_value(ParamType::INT8(new_value as i8), encoding)?;
+
+            if (old_value != new_value) || force_apply {
+                if old_value != new_value && old_value != ButtonFunction::Disabled as u8 {
+                    let warning = format!(
+                        "{param_name} was mapped to {}, now it is mapped to {:?}",
+                        button_function_name(old_value),
+                        assignment.function
+                    );
+                    warn!("{warning}");
+                    warnings.push(warning);
+                }
+
+                match self.mavlink.set_param(param).await {
+                    Ok(_) => {
+                        if old_value != new_value {
+                            info!(
+                                "{param_name} changed from {} to {:?}",
+                                button_function_name(old_value),
+                                assignment.function
+                            );
+                        }
+                    }
+                    Err(error) => {
+                        warn!("Failed setting parameter: {error:?}");
+                        warnings.push(format!("Failed setting {param_name}: {error}"));
+                    }
+                }
+            } else {
+                trace!("Parameter {param_name:?} skipped");
+            }
+        }
+
+        let actuators = self
+            .settings
+            .actuators
+            .get_mut(camera_uuid)
+            .context("Camera not configured")?;
+
+        if actuators.script_buttons != mapping.script_buttons {
+            info!(
+                "script_buttons changed from {:?} to {:?}",
+                actuators.script_buttons, mapping.script_buttons
+            );
+            actuators.script_buttons = mapping.script_buttons.clone();
+        }
+
+        self.settings.save().await?;
+
+        Ok(api::JoystickMapping {
+            assignments: mapping.assignments.clone(),
+            script_buttons: mapping.script_buttons.clone(),
+            warnings,
+        })
+    }
+}
+
+/// Handles the ArduSub script buttons, which the Lua script forwards as `NAMED_VALUE_FLOAT`s
+#[instrument(level = "debug", skip(receiver))]
+pub(crate) async fn script_buttons_task(mut receiver: broadcast::Receiver<Message>) {
+    loop {
+        use broadcast::error::RecvError;
+
+        let data = match receiver.recv().await {
+            Ok(Message::Received((_, MavMessage::NAMED_VALUE_FLOAT(data)))) => data,
+            Ok(_) => continue,
+            Err(RecvError::Closed) => {
+                error!("Receiver channel closed");
+                break;
+            }
+            Err(RecvError::Lagged(n)) => {
+                warn!("Receiver lagged by {n} messages");
+                continue;
+            }
+        };
+
+        let name = named_value_name(&data.name);
+        let Some(camera_id) = name
+            .strip_prefix(PARAM_PREFIX)
+            .and_then(|name| name.strip_suffix(SCRIPT_BUTTON_SUFFIX))
+        else {
+            continue;
+        };
+
+        let button = data.value as u8;
+        if let Err(error) = handle_script_button(camera_id, button).await {
+            warn!("Failed handling script button {button} from {name:?}: {error:?}");
+        }
+    }
+}
+
+#[instrument(level = "debug")]
+async fn handle_script_button(camera_id: &str, button: u8) -> Result<()> {
+    let mut manager = MANAGER.get().context("Not available")?.write().await;
+
+    let Some((camera_uuid, actuators)) = manager
+        .settings
+        .actuators
+        .iter()
+        .find(|(_, actuators)| (actuators.parameters.camera_id as u8).to_string() == camera_id)
+    else {
+        trace!("No camera configured as {camera_id:?}");
+        return Ok(());
+    };
+
+    let camera_uuid = *camera_uuid;
+    let state = tracker::state(&camera_uuid)
+        .await
+        .unwrap_or(actuators.state);
+    let Some(action) = actuators
+        .script_buttons
+        .iter()
+        .find(|script_button| script_button.button == button)
+        .map(|script_button| script_button.action.clone())
+    else {
+        trace!("Script button {button} not assigned");
+        return Ok(());
+    };
+
+    debug!("Script button {button} pressed: {action:?}");
+
+    let step = |value: Option<f32>, step: f32| Some((value.unwrap_or(50.) + step).clamp(0., 100.));
+
+    let new_state = match action {
+        ScriptButtonAction::ZoomIn => api::ActuatorsState {
+            zoom: step(state.zoom, SCRIPT_BUTTON_STEP),
+            ..Default::default()
+        },
+        ScriptButtonAction::ZoomOut => api::ActuatorsState {
+            zoom: step(state.zoom, -SCRIPT_BUTTON_STEP),
+            ..Default::default()
+        },
+        ScriptButtonAction::FocusNear => api::ActuatorsState {
+            focus: step(state.focus, -SCRIPT_BUTTON_STEP),
+            ..Default::default()
+        },
+        ScriptButtonAction::FocusFar => api::ActuatorsState {
+            focus: step(state.focus, SCRIPT_BUTTON_STEP),
+            ..Default::default()
+        },
+        ScriptButtonAction::RecallPreset(name) => {
+            let preset = manager.recall_preset(&camera_uuid, &name).await?;
+            drop(manager);
+
+            return crate::recall_image_adjustments(&camera_uuid, &preset).await;
+        }
+    };
+
+    manager.update_state(&camera_uuid, &new_state).await?;
+
+    Ok(())
+}
+
+/// ArduSub only has 4 script buttons (`script_1` to `script_4`)
+fn script_button_function(button: u8) -> Result<ButtonFunction> {
+    match button {
+        1 => Ok(ButtonFunction::Script1),
+        2 => Ok(ButtonFunction::Script2),
+        3 => Ok(ButtonFunction::Script3),
+        4 => Ok(ButtonFunction::Script4),
+        _ => Err(anyhow!(
+            "Invalid script button {button}, it should be between 1 and 4"
+        )),
+    }
+}
+
+fn button_function_name(value: u8) -> String {
+    ButtonFunction::try_from(value)
+        .map(|function| format!("{function:?}"))
+        .unwrap_or_else(|_| format!("function {value}"))
+}
+
+fn named_value_name(name: &[u8]) -> String {
+    let len = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+    String::from_utf8_lossy(&name[..len]).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_named_value_name() {
+        assert_eq!(named_value_name(b"RCAM1_BTN\0"), "RCAM1_BTN");
+        assert_eq!(named_value_name(b"RCAM12_BTN"), "RCAM12_BTN");
+    }
+
+    #[test]
+    fn test_script_button_function() {
+        assert_eq!(script_button_function(1).unwrap(), ButtonFunction::Script1);
+        assert_eq!(script_button_function(4).unwrap(), ButtonFunction::Script4);
+        assert!(script_button_function(0).is_err());
+        assert!(script_button_function(5).is_err());
+    }
+}
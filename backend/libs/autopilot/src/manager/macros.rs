@@ -1,12 +1,17 @@
+/// Parameters created by the script (`created_by_script`) may not exist yet, when the script that
+/// creates them was not loaded. Their new value is then only stored, so the script is exported
+/// creating them with it
 #[macro_export]
 macro_rules! generate_update_channel_param_function {
     (
+        @function
         $fn_name:ident,
         $field_name:ident,
         $param_prefix:expr,
         $param_suffix:expr,
-        $ty:ident,
-        $channel_field:ident
+        $ty:ident $(as $param_ty:ty)?,
+        $channel_field:ident,
+        $created_by_script:literal
     ) => {
         #[instrument(level = "debug", skip(self, parameters))]
         async fn $fn_name(
@@ -34,9 +39,25 @@ macro_rules! generate_update_channel_param_function {
                 (None, false) => return Ok(()),
             };
 
-            let mut param = self.mavlink.get_param(&param_name, false).await?;
+            let mut param = match self.mavlink.get_param(&param_name, false).await {
+                Ok(param) => param,
+                Err(error) => {
+                    if !$created_by_script {
+                        return Err(error);
+                    }
+
+                    warn!(
+                        "Parameter {param_name:?} not found, the script creates it with {new_value:?} once reloaded: {error:?}"
+                    );
+                    current_parameters.$field_name = new_value;
+
+                    return Ok(());
+                }
+            };
             let old_value = current_parameters.$field_name;
-            param.value.set_value(ParamType::$ty(new_value), encoding)?;
+            param
+                .value
+                .set_value(ParamType::$ty(new_value $(as $param_ty)?), encoding)?;
 
             if (old_value != new_value) || force_apply {
                 match self.mavlink.set_param(param).await {
@@ -62,6 +83,45 @@ macro_rules! generate_update_channel_param_function {
             Ok(())
         }
     };
+    (
+        $fn_name:ident,
+        $field_name:ident,
+        $param_prefix:expr,
+        $param_suffix:expr,
+        $ty:ident $(as $param_ty:ty)?,
+        $channel_field:ident
+    ) => {
+        $crate::generate_update_channel_param_function!(
+            @function
+            $fn_name,
+            $field_name,
+            $param_prefix,
+            $param_suffix,
+            $ty $(as $param_ty)?,
+            $channel_field,
+            false
+        );
+    };
+    (
+        $fn_name:ident,
+        $field_name:ident,
+        $param_prefix:expr,
+        $param_suffix:expr,
+        $ty:ident $(as $param_ty:ty)?,
+        $channel_field:ident,
+        created_by_script
+    ) => {
+        $crate::generate_update_channel_param_function!(
+            @function
+            $fn_name,
+            $field_name,
+            $param_prefix,
+            $param_suffix,
+            $ty $(as $param_ty)?,
+            $channel_field,
+            true
+        );
+    };
 }
 
 #[macro_export]
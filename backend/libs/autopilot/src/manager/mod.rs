@@ -1,11 +1,22 @@
 mod calibration;
 mod camera;
 mod focus;
+mod joystick;
 mod macros;
+mod motion;
+mod osd;
+mod presets;
+mod profiles;
+mod reapply;
+mod rules;
 mod script;
 mod tilt;
+mod tracker;
 mod zoom;
 
+pub(crate) use motion::keep_alive;
+pub(crate) use osd::autopilot_time;
+
 use anyhow::{Context, Result};
 use indexmap::IndexMap;
 use once_cell::sync::OnceCell;
@@ -15,7 +26,10 @@ use uuid::Uuid;
 
 use settings::MANAGER as SETTINGS_MANAGER;
 
-use crate::{CameraActuators, api, mavlink::MavlinkComponent};
+use crate::{
+    CameraActuators, CameraImageProfiles, api, events, mavlink::MavlinkComponent,
+    parameters::ActuatorsParameters,
+};
 
 pub static MANAGER: OnceCell<RwLock<Manager>> = OnceCell::new();
 
@@ -24,11 +38,17 @@ pub struct Manager {
     pub mavlink: MavlinkComponent,
     pub autopilot_scripts_file: String,
     pub settings: State,
+    pub motions: IndexMap<Uuid, motion::Motion>,
+    pub osd: osd::Osd,
 }
 
 #[derive(Debug)]
 pub struct State {
     pub actuators: IndexMap<Uuid, CameraActuators>,
+    pub presets: IndexMap<Uuid, IndexMap<String, api::Preset>>,
+    pub osd: IndexMap<Uuid, api::OsdConfig>,
+    pub image_profiles: IndexMap<Uuid, CameraImageProfiles>,
+    pub rules: IndexMap<Uuid, IndexMap<String, api::Rule>>,
 }
 
 impl State {
@@ -47,11 +67,64 @@ impl State {
             .map(|(uuid, actuator_settings)| (*uuid, CameraActuators::from(actuator_settings)))
             .collect();
 
-        Ok(Self { actuators })
+        let presets = settings
+            .get_presets()
+            .iter()
+            .map(|(uuid, camera_presets)| {
+                let camera_presets = camera_presets
+                    .iter()
+                    .map(|(name, preset)| (name.clone(), api::Preset::from((name, preset))))
+                    .collect();
+
+                (*uuid, camera_presets)
+            })
+            .collect();
+
+        let osd = settings
+            .get_osd()
+            .iter()
+            .map(|(uuid, osd_settings)| (*uuid, api::OsdConfig::from(osd_settings)))
+            .collect();
+
+        let image_profiles = settings
+            .get_image()
+            .iter()
+            .map(|(uuid, image_settings)| (*uuid, CameraImageProfiles::from(image_settings)))
+            .collect();
+
+        let rules = settings
+            .get_rules()
+            .iter()
+            .map(|(uuid, camera_rules)| {
+                let camera_rules = camera_rules
+                    .iter()
+                    .filter_map(|(name, rule)| match api::Rule::try_from((name, rule)) {
+                        Ok(rule) => Some((name.clone(), rule)),
+                        Err(error) => {
+                            warn!("Ignoring invalid rule {name:?}: {error:?}");
+                            None
+                        }
+                    })
+                    .collect();
+
+                (*uuid, camera_rules)
+            })
+            .collect();
+
+        Ok(Self {
+            actuators,
+            presets,
+            osd,
+            image_profiles,
+            rules,
+        })
     }
 
     #[instrument(level = "debug", skip(self))]
     pub async fn save(&self) -> Result<()> {
+        // The tracker follows the saved cameras, and has their latest state
+        let states = tracker::track_cameras(&self.actuators).await;
+
         let settings = &mut SETTINGS_MANAGER
             .get()
             .context("Not available")?
@@ -62,16 +135,76 @@ impl State {
         let actuators = self
             .actuators
             .iter()
-            .map(|(uuid, actuator_settings)| (*uuid, actuator_settings.into()))
+            .map(|(uuid, actuator_settings)| {
+                let mut actuator_settings =
+                    settings::CameraActuatorsSettings::from(actuator_settings);
+                if let Some(state) = states.get(uuid) {
+                    actuator_settings.state = state.into();
+                }
+
+                (*uuid, actuator_settings)
+            })
             .collect();
 
         *settings.get_actuators_mut() = actuators;
 
+        let presets = self
+            .presets
+            .iter()
+            .map(|(uuid, camera_presets)| {
+                let camera_presets = camera_presets
+                    .iter()
+                    .map(|(name, preset)| (name.clone(), preset.into()))
+                    .collect();
+
+                (*uuid, camera_presets)
+            })
+            .collect();
+
+        *settings.get_presets_mut() = presets;
+
+        let osd = self
+            .osd
+            .iter()
+            .map(|(uuid, osd_config)| (*uuid, osd_config.into()))
+            .collect();
+
+        *settings.get_osd_mut() = osd;
+
+        let image = self
+            .image_profiles
+            .iter()
+            .map(|(uuid, image_profiles)| (*uuid, image_profiles.into()))
+            .collect();
+
+        *settings.get_image_mut() = image;
+
+        let rules = self
+            .rules
+            .iter()
+            .map(|(uuid, camera_rules)| {
+                let camera_rules = camera_rules
+                    .iter()
+                    .map(|(name, rule)| (name.clone(), rule.into()))
+                    .collect();
+
+                (*uuid, camera_rules)
+            })
+            .collect();
+
+        *settings.get_rules_mut() = rules;
+
         settings.save().await
     }
 }
 
 impl Manager {
+    /// The latest state of each camera, without asking the autopilot
+    #[instrument(level = "debug", skip(self))]
+    pub async fn get_states(&self) -> IndexMap<Uuid, api::ActuatorsState> {
+        tracker::track_cameras(&self.settings.actuators).await
+    }
+
     #[instrument(level = "debug", skip(self))]
     pub async fn get_state(&mut self, camera_uuid: &Uuid) -> Result<api::ActuatorsState> {
         let actuators = self
@@ -80,6 +213,11 @@ impl Manager {
             .get_mut(camera_uuid)
             .context("Camera not configured")?;
 
+        // The tracker keeps the state fresh, so we only ask the autopilot when it is stale
+        if let Some(state) = tracker::fresh_state(camera_uuid).await {
+            return Ok(state);
+        }
+
         let camera_settings = self
             .mavlink
             .request_camera_settings((&(actuators).parameters.camera_id).into())
@@ -90,8 +228,10 @@ impl Manager {
             focus: Some(camera_settings.focusLevel),
             zoom: Some(camera_settings.zoomLevel),
             tilt: None, // TODO: Fix this after implementing the tilt API
+            focus_output: None,
         };
 
+        tracker::set_state(camera_uuid, current_state).await;
         actuators.state = current_state;
 
         self.settings.save().await?;
@@ -152,15 +292,21 @@ impl Manager {
                 .context("Failed sending MAV_CMD_SET_CAMERA_ZOOM command")?;
         }
 
-        let camera_settings = self
-            .mavlink
-            .request_camera_settings((&actuators.parameters.camera_id).into())
-            .await
-            .context("Failed waiting for CAMERA_SETTINGS after MAV_CMD_SET_CAMERA_FOCUS")?;
+        // The tracker updates the state as the outputs move, otherwise we ask the autopilot
+        if (new_state.focus.is_some() || new_state.zoom.is_some())
+            && tracker::fresh_state(camera_uuid).await.is_none()
+        {
+            let camera_settings = self
+                .mavlink
+                .request_camera_settings((&actuators.parameters.camera_id).into())
+                .await
+                .context("Failed waiting for CAMERA_SETTINGS after MAV_CMD_SET_CAMERA_FOCUS")?;
 
-        if new_state.focus.is_some() || new_state.zoom.is_some() {
-            actuators.state.focus = none_if_nan(camera_settings.focusLevel);
-            actuators.state.zoom = none_if_nan(camera_settings.zoomLevel);
+            let mut state = tracker::state(camera_uuid).await.unwrap_or(actuators.state);
+            state.focus = none_if_nan(camera_settings.focusLevel);
+            state.zoom = none_if_nan(camera_settings.zoomLevel);
+            tracker::set_state(camera_uuid, state).await;
+            actuators.state = state;
         }
 
         if let Some(_tilt) = new_state.tilt {
@@ -178,11 +324,35 @@ impl Manager {
         camera_uuid: &Uuid,
         new_config: &api::ActuatorsConfig,
         overwrite: bool,
+    ) -> Result<()> {
+        publish_config_progress(camera_uuid, api::ConfigStage::Started, None);
+
+        let res = self.apply_config(camera_uuid, new_config, overwrite).await;
+
+        match &res {
+            Ok(()) => publish_config_progress(camera_uuid, api::ConfigStage::Finished, None),
+            Err(error) => publish_config_progress(
+                camera_uuid,
+                api::ConfigStage::Failed,
+                Some(format!("{error:#}")),
+            ),
+        }
+
+        res
+    }
+
+    async fn apply_config(
+        &mut self,
+        camera_uuid: &Uuid,
+        new_config: &api::ActuatorsConfig,
+        overwrite: bool,
     ) -> Result<()> {
         let mut autopilot_reboot_required = overwrite;
 
         // Parameters update
         if let Some(parameters) = &new_config.parameters {
+            publish_config_progress(camera_uuid, api::ConfigStage::Parameters, None);
+
             autopilot_reboot_required |= self
                 .update_camera_parameters(camera_uuid, parameters, overwrite)
                 .await?;
@@ -207,6 +377,7 @@ impl Manager {
         let mut reload_script = overwrite;
 
         // Callibration update
+        publish_config_progress(camera_uuid, api::ConfigStage::Calibration, None);
         if let Some(points) = &new_config.closest_points {
             reload_script |= self
                 .update_closest_points(camera_uuid, points, overwrite)
@@ -217,16 +388,27 @@ impl Manager {
                 .update_furthest_points(camera_uuid, points, overwrite)
                 .await?;
         }
+        if let Some(points) = &new_config.distance_points {
+            reload_script |= self
+                .update_distance_points(camera_uuid, points, overwrite)
+                .await?;
+        }
+
+        publish_config_progress(camera_uuid, api::ConfigStage::Script, None);
 
         autopilot_reboot_required |= self.mavlink.enable_lua_script(overwrite).await?;
 
         reload_script |= self.export_script(camera_uuid, overwrite).await?;
 
         if reload_script {
+            publish_config_progress(camera_uuid, api::ConfigStage::ReloadingScripts, None);
+
             self.mavlink.reload_lua_scripts(overwrite).await?;
         }
 
         if autopilot_reboot_required {
+            publish_config_progress(camera_uuid, api::ConfigStage::RebootingAutopilot, None);
+
             self.mavlink.reboot_autopilot().await?;
         }
 
@@ -258,18 +440,196 @@ pub async fn init(
         MavlinkComponent::try_new(mavlink_address, mavlink_system_id, mavlink_component_id).await?;
 
     let settings = State::from_settings().await?;
+    tracker::track_cameras(&settings.actuators).await;
+
+    // Otherwise their changes are only published once RadCam sets them again
+    for actuators in settings.actuators.values() {
+        mavlink
+            .own_parameters(owned_parameter_names(&actuators.parameters))
+            .await;
+    }
+
+    let receiver = mavlink.get_receiver().await;
+    let tracker_receiver = mavlink.get_receiver().await;
+    let osd_receiver = mavlink.get_receiver().await;
+    let rules_receiver = mavlink.get_receiver().await;
 
     MANAGER.get_or_init(|| {
         RwLock::new(Manager {
             mavlink,
             autopilot_scripts_file,
             settings,
+            motions: IndexMap::default(),
+            osd: osd::Osd::default(),
         })
     });
 
+    tokio::spawn(joystick::script_buttons_task(receiver));
+    tokio::spawn(motion::watchdog_task());
+    tokio::spawn(tracker::state_tracker_task(tracker_receiver));
+    tokio::spawn(osd::osd_telemetry_task(osd_receiver));
+    tokio::spawn(osd::osd_task());
+    tokio::spawn(reapply::reapply_task());
+    tokio::spawn(rules::rules_task(rules_receiver));
+
     Ok(())
 }
 
+fn publish_config_progress(camera_uuid: &Uuid, stage: api::ConfigStage, error: Option<String>) {
+    events::publish(api::ActuatorsEvent::ConfigProgress(api::ConfigProgress {
+        camera_uuid: *camera_uuid,
+        stage,
+        error,
+    }));
+}
+
 fn none_if_nan(value: f32) -> Option<f32> {
     if value.is_nan() { None } else { Some(value) }
 }
+
+/// The autopilot parameters RadCam configures for a camera
+fn owned_parameter_names(parameters: &ActuatorsParameters) -> Vec<String> {
+    let camera_id = parameters.camera_id as u8;
+
+    let mut names = vec![
+        format!("CAM{camera_id}_TYPE"),
+        format!("MNT{camera_id}_TYPE"),
+        format!("MNT{camera_id}_PITCH_MIN"),
+        format!("MNT{camera_id}_PITCH_MAX"),
+    ];
+
+    names.extend(
+        ["ENABLE", "GAIN", "RF_ENABLE", "RF_ORIENT"]
+            .iter()
+            .map(|suffix| format!("{}{camera_id}_{suffix}", script::PARAM_PREFIX)),
+    );
+
+    for channel in [
+        parameters.focus_channel,
+        parameters.script_channel,
+        parameters.zoom_channel,
+        parameters.tilt_channel,
+    ] {
+        names.extend(
+            ["FUNCTION", "MIN", "TRIM", "MAX"]
+                .iter()
+                .map(|suffix| format!("SERVO{}_{suffix}", channel as u8)),
+        );
+    }
+
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use ::mavlink::ardupilotmega::{MavCmd, MavParamType};
+
+    use super::*;
+    use crate::mavlink::mock::{MockAutopilot, MockAutopilotConfig};
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_update_config() {
+        let directory = tempfile::tempdir().unwrap();
+        settings::init(
+            directory
+                .path()
+                .join("settings.json")
+                .to_string_lossy()
+                .to_string(),
+            true,
+        )
+        .await
+        .unwrap();
+
+        let config = MockAutopilotConfig {
+            parameters: vec![
+                (
+                    "SCR_ENABLE".to_string(),
+                    1.,
+                    MavParamType::MAV_PARAM_TYPE_INT8,
+                ),
+                (
+                    "SERVO10_MIN".to_string(),
+                    870.,
+                    MavParamType::MAV_PARAM_TYPE_INT16,
+                ),
+            ],
+            ..Default::default()
+        };
+        let (mock, mavlink) = MockAutopilot::connect("udp", config).await;
+
+        let camera_uuid = Uuid::new_v5(&Uuid::NAMESPACE_DNS, b"camera.example.com");
+        let mut manager = Manager {
+            mavlink,
+            autopilot_scripts_file: directory
+                .path()
+                .join("radcam.lua")
+                .to_string_lossy()
+                .to_string(),
+            settings: State {
+                actuators: IndexMap::from([(camera_uuid, CameraActuators::default())]),
+                presets: IndexMap::default(),
+                osd: IndexMap::default(),
+                image_profiles: IndexMap::default(),
+                rules: IndexMap::default(),
+            },
+            motions: IndexMap::default(),
+            osd: osd::Osd::default(),
+        };
+
+        let new_config = api::ActuatorsConfig {
+            parameters: Some(api::ActuatorsParametersConfig {
+                focus_channel_min: Some(1000),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        manager
+            .update_config(&camera_uuid, &new_config, false)
+            .await
+            .unwrap();
+
+        assert_eq!(mock.param("SERVO10_MIN"), Some(1000.));
+        assert_eq!(
+            manager.settings.actuators[&camera_uuid]
+                .parameters
+                .focus_channel_min,
+            1000
+        );
+
+        let script = std::fs::read_to_string(&manager.autopilot_scripts_file).unwrap();
+        assert!(!script.is_empty());
+
+        // The script was reloaded, and nothing asked for a reboot
+        let commands = mock.commands();
+        assert!(
+            commands
+                .iter()
+                .any(|command| command.command == MavCmd::MAV_CMD_SCRIPTING)
+        );
+
+        let settings = &SETTINGS_MANAGER.get().unwrap().read().await.settings;
+        assert_eq!(
+            settings.get_actuators()[&camera_uuid]
+                .parameters
+                .focus_channel_min,
+            1000
+        );
+    }
+
+    #[test]
+    fn test_owned_parameter_names() {
+        let names = owned_parameter_names(&ActuatorsParameters::default());
+
+        for name in [
+            "CAM1_TYPE",
+            "MNT1_PITCH_MAX",
+            "RCAM1_GAIN",
+            "RCAM1_RF_ORIENT",
+            "SERVO10_FUNCTION",
+            "SERVO11_TRIM",
+        ] {
+            assert!(names.contains(&name.to_string()), "Missing {name:?}");
+        }
+    }
+}
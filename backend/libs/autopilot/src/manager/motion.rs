@@ -0,0 +1,248 @@
+use std::time::{Duration, Instant};
+
+use ::mavlink::ardupilotmega::{COMMAND_LONG_DATA, CameraZoomType, MavCmd, SetFocusType};
+use anyhow::{Context, Result, anyhow};
+use indexmap::IndexMap;
+use once_cell::sync::Lazy;
+use tracing::*;
+use uuid::Uuid;
+
+use crate::{
+    api,
+    manager::{MANAGER, Manager, none_if_nan, tracker},
+};
+
+/// Continuous motions are stopped if they are not kept alive within this time
+pub const KEEP_ALIVE_TIMEOUT: Duration = Duration::from_secs(1);
+const WATCHDOG_PERIOD: Duration = Duration::from_millis(100);
+
+/// When each moving camera was last kept alive. Behind its own lock, so the keep-alives get through
+/// while the manager is busy
+static KEEP_ALIVES: Lazy<std::sync::Mutex<IndexMap<Uuid, Instant>>> =
+    Lazy::new(|| std::sync::Mutex::new(IndexMap::new()));
+
+#[derive(Debug, Clone, Copy)]
+pub struct Motion {
+    pub zoom_rate: f32,
+    pub focus_rate: f32,
+}
+
+/// Keeps the motion of the camera going, if it is moving
+#[instrument(level = "debug")]
+pub(crate) fn keep_alive(camera_uuid: &Uuid) {
+    if let Some(last_keep_alive) = KEEP_ALIVES.lock().unwrap().get_mut(camera_uuid) {
+        *last_keep_alive = Instant::now();
+    }
+}
+
+impl Manager {
+    #[instrument(level = "debug", skip(self))]
+    pub async fn start_zoom(&mut self, camera_uuid: &Uuid, rate: f32) -> Result<()> {
+        validate_rate(rate)?;
+
+        let motion = self.get_motion(camera_uuid)?;
+
+        if motion.zoom_rate != rate {
+            self.send_zoom_rate(rate).await?;
+        }
+
+        self.update_motion(
+            camera_uuid,
+            Motion {
+                zoom_rate: rate,
+                ..motion
+            },
+        )
+        .await
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    pub async fn start_focus(&mut self, camera_uuid: &Uuid, rate: f32) -> Result<()> {
+        validate_rate(rate)?;
+
+        let motion = self.get_motion(camera_uuid)?;
+
+        if motion.focus_rate != rate {
+            self.send_focus_rate(rate).await?;
+        }
+
+        self.update_motion(
+            camera_uuid,
+            Motion {
+                focus_rate: rate,
+                ..motion
+            },
+        )
+        .await
+    }
+
+    /// Stops any continuous motion, returning the state where the actuators stopped
+    #[instrument(level = "debug", skip(self))]
+    pub async fn stop_motion(&mut self, camera_uuid: &Uuid) -> Result<api::ActuatorsState> {
+        // The motion is only forgotten once stopped, so the watchdog retries a failed stop
+        if let Some(motion) = self.motions.get(camera_uuid).copied() {
+            if motion.zoom_rate != 0. {
+                self.send_zoom_rate(0.).await?;
+            }
+            if motion.focus_rate != 0. {
+                self.send_focus_rate(0.).await?;
+            }
+
+            self.motions.shift_remove(camera_uuid);
+            KEEP_ALIVES.lock().unwrap().shift_remove(camera_uuid);
+        }
+
+        self.update_stopped_state(camera_uuid).await
+    }
+
+    fn get_motion(&self, camera_uuid: &Uuid) -> Result<Motion> {
+        if !self.settings.actuators.contains_key(camera_uuid) {
+            return Err(anyhow!("Camera not configured"));
+        }
+
+        Ok(self.motions.get(camera_uuid).copied().unwrap_or(Motion {
+            zoom_rate: 0.,
+            focus_rate: 0.,
+        }))
+    }
+
+    /// Starting or changing a motion keeps it alive
+    async fn update_motion(&mut self, camera_uuid: &Uuid, motion: Motion) -> Result<()> {
+        if motion.zoom_rate == 0. && motion.focus_rate == 0. {
+            self.motions.shift_remove(camera_uuid);
+            KEEP_ALIVES.lock().unwrap().shift_remove(camera_uuid);
+            self.update_stopped_state(camera_uuid).await?;
+        } else {
+            self.motions.insert(*camera_uuid, motion);
+            KEEP_ALIVES
+                .lock()
+                .unwrap()
+                .insert(*camera_uuid, Instant::now());
+        }
+
+        Ok(())
+    }
+
+    async fn update_stopped_state(&mut self, camera_uuid: &Uuid) -> Result<api::ActuatorsState> {
+        let actuators = self
+            .settings
+            .actuators
+            .get_mut(camera_uuid)
+            .context("Camera not configured")?;
+
+        if let Some(state) = tracker::fresh_state(camera_uuid).await {
+            return Ok(state);
+        }
+
+        let camera_settings = self
+            .mavlink
+            .request_camera_settings((&actuators.parameters.camera_id).into())
+            .await
+            .context("Failed waiting for CAMERA_SETTINGS after stopping")?;
+
+        let mut state = tracker::state(camera_uuid).await.unwrap_or(actuators.state);
+        state.focus = none_if_nan(camera_settings.focusLevel);
+        state.zoom = none_if_nan(camera_settings.zoomLevel);
+        tracker::set_state(camera_uuid, state).await;
+        actuators.state = state;
+
+        self.settings.save().await?;
+
+        Ok(state)
+    }
+
+    async fn send_zoom_rate(&self, rate: f32) -> Result<()> {
+        self.mavlink
+            .send_command(COMMAND_LONG_DATA {
+                target_system: 1,
+                target_component: 1,
+                command: MavCmd::MAV_CMD_SET_CAMERA_ZOOM,
+                confirmation: 0,
+                param1: CameraZoomType::ZOOM_TYPE_CONTINUOUS as u8 as f32,
+                param2: rate,
+                param3: 0 as f32, // autopilot cameras
+                ..Default::default()
+            })
+            .await
+            .context("Failed sending MAV_CMD_SET_CAMERA_ZOOM command")
+    }
+
+    async fn send_focus_rate(&self, rate: f32) -> Result<()> {
+        self.mavlink
+            .send_command(COMMAND_LONG_DATA {
+                target_system: 1,
+                target_component: 1,
+                command: MavCmd::MAV_CMD_SET_CAMERA_FOCUS,
+                confirmation: 0,
+                param1: SetFocusType::FOCUS_TYPE_CONTINUOUS as u8 as f32,
+                param2: rate,
+                param3: 0 as f32, // autopilot cameras
+                ..Default::default()
+            })
+            .await
+            .context("Failed sending MAV_CMD_SET_CAMERA_FOCUS command")
+    }
+}
+
+/// Stops the motions that were not kept alive, like when a client disconnects while holding a button
+#[instrument(level = "debug")]
+pub(crate) async fn watchdog_task() {
+    let mut interval = tokio::time::interval(WATCHDOG_PERIOD);
+
+    loop {
+        interval.tick().await;
+
+        let expired = expired_motions();
+        if expired.is_empty() {
+            continue;
+        }
+
+        let Some(manager) = MANAGER.get() else {
+            continue;
+        };
+
+        let mut manager = manager.write().await;
+        // Some may have been kept alive while waiting for the manager
+        let expired = expired_motions();
+        for camera_uuid in expired {
+            warn!("No keep-alive received for camera {camera_uuid}, stopping its motion");
+
+            if let Err(error) = manager.stop_motion(&camera_uuid).await {
+                warn!("Failed stopping motion of camera {camera_uuid}: {error:?}");
+            }
+        }
+    }
+}
+
+fn expired_motions() -> Vec<Uuid> {
+    KEEP_ALIVES
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(_, last_keep_alive)| last_keep_alive.elapsed() > KEEP_ALIVE_TIMEOUT)
+        .map(|(camera_uuid, _)| *camera_uuid)
+        .collect()
+}
+
+fn validate_rate(rate: f32) -> Result<()> {
+    if !(-1.0..=1.0).contains(&rate) {
+        return Err(anyhow!("Rate should be between -1 and 1, got {rate}"));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_rate() {
+        assert!(validate_rate(-1.0).is_ok());
+        assert!(validate_rate(0.0).is_ok());
+        assert!(validate_rate(1.0).is_ok());
+        assert!(validate_rate(1.5).is_err());
+        assert!(validate_rate(-1.01).is_err());
+        assert!(validate_rate(f32::NAN).is_err());
+    }
+}
@@ -0,0 +1,445 @@
+use std::time::{Duration, Instant};
+
+use ::mavlink::{
+    Message as _,
+    ardupilotmega::{
+        ATTITUDE_DATA, MavMessage, SCALED_PRESSURE2_DATA, SYSTEM_TIME_DATA, VFR_HUD_DATA,
+    },
+};
+use anyhow::{Context, Result, anyhow};
+use chrono::{DateTime, TimeDelta, Utc};
+use indexmap::IndexMap;
+use once_cell::sync::Lazy;
+use radcam_commands::protocol::osd::osd_parameters::{
+    OsdEnableValue, OsdParameterSettings, OsdTextValue,
+};
+use tokio::sync::{RwLock, broadcast};
+use tracing::*;
+use uuid::Uuid;
+
+use crate::{
+    api,
+    manager::{MANAGER, Manager},
+    mavlink::Message,
+};
+
+/// The interval we ask the autopilot to stream the telemetry messages at
+const STREAM_INTERVAL: Duration = Duration::from_millis(250);
+/// After this time without a message, its reading is not displayed anymore
+const STALE_TIMEOUT: Duration = Duration::from_secs(3);
+/// How often the overlays are checked for updates
+const PERIOD: Duration = Duration::from_millis(100);
+/// Each update is a request to the camera, so we don't let it be flooded
+const MIN_UPDATE_INTERVAL: Duration = Duration::from_millis(200);
+/// How many text overlays the camera has
+const MAX_FIELDS: usize = 8;
+/// Atmospheric pressure at sea level, in hPa
+const SURFACE_PRESSURE: f32 = 1013.25;
+/// How much the pressure increases with depth in fresh water, in Pa/m
+const PRESSURE_GRADIENT: f32 = 997.0 * 9.80665;
+
+/// Behind its own lock, so the stream of telemetry messages doesn't contend for the manager
+static TELEMETRY: Lazy<RwLock<Telemetry>> = Lazy::new(|| RwLock::new(Telemetry::default()));
+
+#[derive(Debug, Clone, Copy)]
+struct Reading<T> {
+    value: T,
+    received: Instant,
+}
+
+impl<T: Copy> Reading<T> {
+    fn new(value: T) -> Option<Self> {
+        Some(Self {
+            value,
+            received: Instant::now(),
+        })
+    }
+}
+
+/// When each camera's overlays were last updated
+#[derive(Debug, Default)]
+pub struct Osd {
+    last_update: IndexMap<Uuid, Instant>,
+}
+
+/// The latest vehicle telemetry
+#[derive(Debug, Default, Clone)]
+struct Telemetry {
+    hud_depth: Option<Reading<f32>>,
+    pressure_depth: Option<Reading<f32>>,
+    hud_heading: Option<Reading<f32>>,
+    attitude_heading: Option<Reading<f32>>,
+    system_time: Option<Reading<DateTime<Utc>>>,
+}
+
+impl Telemetry {
+    #[instrument(level = "trace", skip(self, message))]
+    fn track(&mut self, message: &MavMessage) {
+        match message {
+            // ArduSub reports the depth as a negative altitude
+            MavMessage::VFR_HUD(data) => {
+                self.hud_depth = Reading::new(-data.alt);
+                self.hud_heading = Reading::new(data.heading as f32);
+            }
+            MavMessage::SCALED_PRESSURE2(data) => {
+                self.pressure_depth = Reading::new(pressure_to_depth(data.press_abs));
+            }
+            MavMessage::ATTITUDE(data) => {
+                self.attitude_heading = Reading::new(data.yaw.to_degrees().rem_euclid(360.));
+            }
+            MavMessage::SYSTEM_TIME(data) => {
+                // Zero until the autopilot gets the time from a GPS or a companion computer
+                if let Some(time) = Some(data.time_unix_usec)
+                    .filter(|time_unix_usec| *time_unix_usec != 0)
+                    .and_then(|time_unix_usec| {
+                        DateTime::from_timestamp_micros(time_unix_usec as i64)
+                    })
+                {
+                    self.system_time = Reading::new(time);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn depth(&self) -> Option<f32> {
+        fresh(self.hud_depth).or_else(|| fresh(self.pressure_depth))
+    }
+
+    fn heading(&self) -> Option<f32> {
+        fresh(self.hud_heading).or_else(|| fresh(self.attitude_heading))
+    }
+
+    /// The autopilot time, advanced since it was received
+    fn autopilot_time(&self) -> Option<DateTime<Utc>> {
+        self.system_time
+            .filter(|reading| reading.received.elapsed() < STALE_TIMEOUT)
+            .map(|reading| {
+                reading.value + TimeDelta::from_std(reading.received.elapsed()).unwrap_or_default()
+            })
+    }
+
+    /// The autopilot time, or our own clock when it has none
+    fn time(&self) -> DateTime<Utc> {
+        self.autopilot_time().unwrap_or_else(Utc::now)
+    }
+
+    fn text(&self, item: api::OsdItem) -> String {
+        match item {
+            api::OsdItem::Depth => format_depth(self.depth()),
+            api::OsdItem::Heading => format_heading(self.heading()),
+            api::OsdItem::Time => format_time(&self.time()),
+        }
+    }
+}
+
+/// The vehicle depth, if it is known
+pub(super) async fn depth() -> Option<f32> {
+    TELEMETRY.read().await.depth()
+}
+
+/// The autopilot time, advanced since it was received
+pub(crate) async fn autopilot_time() -> Option<DateTime<Utc>> {
+    TELEMETRY.read().await.autopilot_time()
+}
+
+/// The autopilot time, or our own clock when it has none
+pub(super) async fn time() -> DateTime<Utc> {
+    TELEMETRY.read().await.time()
+}
+
+impl Manager {
+    #[instrument(level = "debug", skip(self))]
+    pub fn get_osd_config(&self, camera_uuid: &Uuid) -> api::OsdConfig {
+        self.settings
+            .osd
+            .get(camera_uuid)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    pub async fn update_osd_config(
+        &mut self,
+        camera_uuid: &Uuid,
+        config: &api::OsdConfig,
+    ) -> Result<api::OsdConfig> {
+        validate_osd_config(config)?;
+
+        let old_config = self.settings.osd.insert(*camera_uuid, config.clone());
+        if old_config.as_ref() != Some(config) {
+            info!("OSD config changed from {old_config:?} to {config:?}");
+        }
+
+        // So the new layout shows up right away
+        if config.enabled {
+            self.osd.last_update.shift_remove(camera_uuid);
+        }
+
+        self.settings.save().await?;
+
+        Ok(config.clone())
+    }
+
+    /// The overlays of the cameras due for an update, including the ones to be cleared because
+    /// their OSD was disabled
+    fn due_osd_updates(&mut self, telemetry: &Telemetry) -> Vec<(Uuid, OsdParameterSettings)> {
+        let mut updates = Vec::new();
+
+        for (camera_uuid, config) in &self.settings.osd {
+            if !config.enabled {
+                if self.osd.last_update.shift_remove(camera_uuid).is_some() {
+                    updates.push((*camera_uuid, overlays(&[], |_| String::new())));
+                }
+                continue;
+            }
+
+            let update_interval = Duration::from_millis(config.update_interval_ms);
+            if self
+                .osd
+                .last_update
+                .get(camera_uuid)
+                .is_some_and(|last_update| last_update.elapsed() < update_interval)
+            {
+                continue;
+            }
+
+            self.osd.last_update.insert(*camera_uuid, Instant::now());
+            updates.push((
+                *camera_uuid,
+                overlays(&config.fields, |item| telemetry.text(item)),
+            ));
+        }
+
+        updates
+    }
+}
+
+/// Keeps the telemetry shown in the overlays fresh
+#[instrument(level = "debug", skip(receiver))]
+pub(crate) async fn osd_telemetry_task(mut receiver: broadcast::Receiver<Message>) {
+    if let Err(error) = request_telemetry_messages().await {
+        warn!(
+            "Failed requesting the telemetry messages, relying on their default rates: {error:?}"
+        );
+    }
+
+    loop {
+        use broadcast::error::RecvError;
+
+        let message = match receiver.recv().await {
+            Ok(Message::Received((_, message))) => message,
+            Ok(_) => continue,
+            Err(RecvError::Closed) => {
+                error!("Receiver channel closed");
+                break;
+            }
+            Err(RecvError::Lagged(n)) => {
+                warn!("Receiver lagged by {n} messages");
+                continue;
+            }
+        };
+
+        if !matches!(
+            message,
+            MavMessage::VFR_HUD(_)
+                | MavMessage::SCALED_PRESSURE2(_)
+                | MavMessage::ATTITUDE(_)
+                | MavMessage::SYSTEM_TIME(_)
+        ) {
+            continue;
+        }
+
+        TELEMETRY.write().await.track(&message);
+    }
+}
+
+/// Pushes the overlays to each camera at its configured rate
+#[instrument(level = "debug")]
+pub(crate) async fn osd_task() {
+    let mut interval = tokio::time::interval(PERIOD);
+
+    loop {
+        interval.tick().await;
+
+        let Some(manager) = MANAGER.get() else {
+            continue;
+        };
+
+        let telemetry = TELEMETRY.read().await.clone();
+        let updates = manager.write().await.due_osd_updates(&telemetry);
+
+        for (camera_uuid, osd_parameters) in updates {
+            if let Err(error) =
+                radcam_commands::set_osd_parameters(&camera_uuid, &osd_parameters).await
+            {
+                // Retried in the next update, so it doesn't need to flood the logs
+                debug!("Failed updating the OSD of camera {camera_uuid}: {error:?}");
+            }
+        }
+    }
+}
+
+async fn request_telemetry_messages() -> Result<()> {
+    let manager = MANAGER.get().context("Not available")?.read().await;
+
+    for message_id in [
+        VFR_HUD_DATA::ID,
+        SCALED_PRESSURE2_DATA::ID,
+        ATTITUDE_DATA::ID,
+        SYSTEM_TIME_DATA::ID,
+    ] {
+        manager
+            .mavlink
+            .set_message_interval(message_id, STREAM_INTERVAL)
+            .await?;
+    }
+
+    Ok(())
+}
+
+fn validate_osd_config(config: &api::OsdConfig) -> Result<()> {
+    if Duration::from_millis(config.update_interval_ms) < MIN_UPDATE_INTERVAL {
+        return Err(anyhow!(
+            "OSD update interval should be at least {} ms",
+            MIN_UPDATE_INTERVAL.as_millis()
+        ));
+    }
+
+    if config.fields.len() > MAX_FIELDS {
+        return Err(anyhow!(
+            "The camera only has {MAX_FIELDS} OSD fields, got {}",
+            config.fields.len()
+        ));
+    }
+
+    if let Some(field) = config
+        .fields
+        .iter()
+        .find(|field| field.x > 100 || field.y > 100)
+    {
+        return Err(anyhow!(
+            "Invalid OSD field position {field:?}, it should be between 0 and 100 percent"
+        ));
+    }
+
+    Ok(())
+}
+
+/// All the camera overlays, so the ones without a field are hidden
+fn overlays(
+    fields: &[api::OsdField],
+    text: impl Fn(api::OsdItem) -> String,
+) -> OsdParameterSettings {
+    let text_list = (0..MAX_FIELDS)
+        .map(|index| match fields.get(index) {
+            Some(field) => OsdTextValue {
+                index: index as u8,
+                enable: Some(OsdEnableValue::Open),
+                text: Some(text(field.item)),
+                pos_x: Some(field.x),
+                pos_y: Some(field.y),
+            },
+            None => OsdTextValue {
+                index: index as u8,
+                enable: Some(OsdEnableValue::Close),
+                ..Default::default()
+            },
+        })
+        .collect();
+
+    OsdParameterSettings {
+        text_list: Some(text_list),
+    }
+}
+
+fn fresh<T: Copy>(reading: Option<Reading<T>>) -> Option<T> {
+    reading
+        .filter(|reading| reading.received.elapsed() < STALE_TIMEOUT)
+        .map(|reading| reading.value)
+}
+
+/// The absolute pressure is in hPa
+fn pressure_to_depth(press_abs: f32) -> f32 {
+    (press_abs - SURFACE_PRESSURE) * 100. / PRESSURE_GRADIENT
+}
+
+fn format_depth(depth: Option<f32>) -> String {
+    match depth {
+        Some(depth) => format!("Depth: {depth:.1} m"),
+        None => "Depth: --- m".to_string(),
+    }
+}
+
+fn format_heading(heading: Option<f32>) -> String {
+    match heading {
+        Some(heading) => format!("Heading: {:03.0}", heading.round().rem_euclid(360.)),
+        None => "Heading: ---".to_string(),
+    }
+}
+
+fn format_time(time: &DateTime<Utc>) -> String {
+    time.format("%Y-%m-%d %H:%M:%S UTC").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pressure_to_depth() {
+        assert_eq!(pressure_to_depth(SURFACE_PRESSURE), 0.);
+        assert!((pressure_to_depth(SURFACE_PRESSURE + 97.77) - 1.).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(format_depth(Some(12.345)), "Depth: 12.3 m");
+        assert_eq!(format_depth(None), "Depth: --- m");
+        assert_eq!(format_heading(Some(87.4)), "Heading: 087");
+        assert_eq!(format_heading(Some(359.6)), "Heading: 000");
+        assert_eq!(format_heading(None), "Heading: ---");
+        assert_eq!(
+            format_time(&DateTime::from_timestamp(1_700_000_000, 0).unwrap()),
+            "2023-11-14 22:13:20 UTC"
+        );
+    }
+
+    #[test]
+    fn test_overlays() {
+        let fields = [api::OsdField {
+            item: api::OsdItem::Depth,
+            x: 2,
+            y: 92,
+        }];
+
+        let text_list = overlays(&fields, |_| "Depth: 1.0 m".to_string())
+            .text_list
+            .unwrap();
+
+        assert_eq!(text_list.len(), MAX_FIELDS);
+        assert_eq!(text_list[0].enable, Some(OsdEnableValue::Open));
+        assert_eq!(text_list[0].text.as_deref(), Some("Depth: 1.0 m"));
+        assert_eq!(text_list[0].pos_y, Some(92));
+        assert!(
+            text_list[1..]
+                .iter()
+                .all(|text| text.enable == Some(OsdEnableValue::Close))
+        );
+    }
+
+    #[test]
+    fn test_validate_osd_config() {
+        assert!(validate_osd_config(&api::OsdConfig::default()).is_ok());
+
+        let config = api::OsdConfig {
+            update_interval_ms: 10,
+            ..Default::default()
+        };
+        assert!(validate_osd_config(&config).is_err());
+
+        let mut config = api::OsdConfig::default();
+        config.fields[0].x = 101;
+        assert!(validate_osd_config(&config).is_err());
+    }
+}
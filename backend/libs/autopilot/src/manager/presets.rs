@@ -0,0 +1,95 @@
+use anyhow::{Context, Result, anyhow};
+use radcam_commands::protocol::display::{
+    advanced_display::AdvancedParameterSetting, base_display::BaseParameterSetting,
+};
+use tracing::*;
+use uuid::Uuid;
+
+use crate::{api, manager::Manager};
+
+impl Manager {
+    #[instrument(level = "debug", skip(self))]
+    pub fn get_presets(&self, camera_uuid: &Uuid) -> Vec<api::Preset> {
+        self.settings
+            .presets
+            .get(camera_uuid)
+            .map(|presets| presets.values().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Creates a new preset, or replaces the existing one with the same name
+    #[instrument(level = "debug", skip(self))]
+    pub async fn set_preset(
+        &mut self,
+        camera_uuid: &Uuid,
+        preset: &api::Preset,
+    ) -> Result<Vec<api::Preset>> {
+        if preset.name.trim().is_empty() {
+            return Err(anyhow!("Preset name can't be empty"));
+        }
+
+        let preset = &api::Preset {
+            image_adjustment: preset
+                .image_adjustment
+                .clone()
+                .map(BaseParameterSetting::without_triggers),
+            image_adjustment_ex: preset
+                .image_adjustment_ex
+                .clone()
+                .map(AdvancedParameterSetting::without_triggers),
+            ..preset.clone()
+        };
+
+        let old_preset = self
+            .settings
+            .presets
+            .entry(*camera_uuid)
+            .or_default()
+            .insert(preset.name.clone(), preset.clone());
+
+        match old_preset {
+            Some(old_preset) => info!("Preset {:?} changed from {old_preset:?}", preset.name),
+            None => info!("Preset {:?} created", preset.name),
+        }
+
+        self.settings.save().await?;
+
+        Ok(self.get_presets(camera_uuid))
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    pub async fn delete_preset(
+        &mut self,
+        camera_uuid: &Uuid,
+        name: &str,
+    ) -> Result<Vec<api::Preset>> {
+        self.settings
+            .presets
+            .get_mut(camera_uuid)
+            .and_then(|presets| presets.shift_remove(name))
+            .with_context(|| format!("Preset {name:?} not found"))?;
+
+        info!("Preset {name:?} deleted");
+
+        self.settings.save().await?;
+
+        Ok(self.get_presets(camera_uuid))
+    }
+
+    /// Drives the actuators to the preset's state. The image adjustments are not applied here, as
+    /// they are sent directly to the camera
+    #[instrument(level = "debug", skip(self))]
+    pub async fn recall_preset(&mut self, camera_uuid: &Uuid, name: &str) -> Result<api::Preset> {
+        let preset = self
+            .settings
+            .presets
+            .get(camera_uuid)
+            .and_then(|presets| presets.get(name))
+            .cloned()
+            .with_context(|| format!("Preset {name:?} not found"))?;
+
+        self.update_state(camera_uuid, &preset.state).await?;
+
+        Ok(preset)
+    }
+}
@@ -0,0 +1,184 @@
+use anyhow::{Context, Result, anyhow};
+use radcam_commands::{
+    camera_config::channel_reasons,
+    protocol::display::{
+        advanced_display::AdvancedParameterSetting, base_display::BaseParameterSetting,
+    },
+};
+use tracing::*;
+use uuid::Uuid;
+
+use crate::{
+    api,
+    manager::{Manager, rules::rules_applying_profile},
+};
+
+impl Manager {
+    #[instrument(level = "debug", skip(self))]
+    pub fn get_image_profiles(&self, camera_uuid: &Uuid) -> api::ImageProfiles {
+        self.settings
+            .image_profiles
+            .get(camera_uuid)
+            .map(|image_profiles| api::ImageProfiles {
+                profiles: image_profiles.profiles.values().cloned().collect(),
+                active_profile: image_profiles.active_profile.clone(),
+                reapply_policy: image_profiles.reapply_policy,
+            })
+            .unwrap_or_default()
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    pub fn get_image_profile(&self, camera_uuid: &Uuid, name: &str) -> Result<api::ImageProfile> {
+        self.settings
+            .image_profiles
+            .get(camera_uuid)
+            .and_then(|image_profiles| image_profiles.profiles.get(name))
+            .cloned()
+            .with_context(|| format!("Image profile {name:?} not found"))
+    }
+
+    /// Creates a new profile, or replaces the existing one with the same name. The camera is left
+    /// as it is, even if the profile is the active one
+    #[instrument(level = "debug", skip(self))]
+    pub async fn set_image_profile(
+        &mut self,
+        camera_uuid: &Uuid,
+        profile: &api::ImageProfile,
+    ) -> Result<api::ImageProfiles> {
+        if profile.name.trim().is_empty() {
+            return Err(anyhow!("Image profile name can't be empty"));
+        }
+
+        let reasons = channel_reasons(&profile.video_parameters);
+        if !reasons.is_empty() {
+            return Err(anyhow!("Invalid image profile: {}", reasons.join("; ")));
+        }
+
+        let profile = &api::ImageProfile {
+            image_adjustment: profile
+                .image_adjustment
+                .clone()
+                .map(BaseParameterSetting::without_triggers),
+            image_adjustment_ex: profile
+                .image_adjustment_ex
+                .clone()
+                .map(AdvancedParameterSetting::without_triggers),
+            ..profile.clone()
+        };
+
+        let image_profiles = self
+            .settings
+            .image_profiles
+            .entry(*camera_uuid)
+            .or_default();
+
+        // The new profile takes the place of an invalid stored one with the same name
+        if image_profiles
+            .invalid_profiles
+            .shift_remove(&profile.name)
+            .is_some()
+        {
+            info!("Invalid image profile {:?} replaced", profile.name);
+        }
+
+        let old_profile = image_profiles
+            .profiles
+            .insert(profile.name.clone(), profile.clone());
+
+        match old_profile {
+            Some(old_profile) => info!(
+                "Image profile {:?} changed from {old_profile:?}",
+                profile.name
+            ),
+            None => info!("Image profile {:?} created", profile.name),
+        }
+
+        self.settings.save().await?;
+
+        Ok(self.get_image_profiles(camera_uuid))
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    pub async fn delete_image_profile(
+        &mut self,
+        camera_uuid: &Uuid,
+        name: &str,
+    ) -> Result<api::ImageProfiles> {
+        // Those rules would fail each time they switch
+        let rules = self
+            .settings
+            .rules
+            .get(camera_uuid)
+            .map(|rules| rules_applying_profile(rules, name))
+            .unwrap_or_default();
+        if !rules.is_empty() {
+            return Err(anyhow!(
+                "Image profile {name:?} is applied by the rules {rules:?}, change or delete them first"
+            ));
+        }
+
+        let image_profiles = self
+            .settings
+            .image_profiles
+            .get_mut(camera_uuid)
+            .context("Camera has no image profiles")?;
+
+        let valid_profile = image_profiles.profiles.shift_remove(name);
+        let invalid_profile = image_profiles.invalid_profiles.shift_remove(name);
+        if valid_profile.is_none() && invalid_profile.is_none() {
+            return Err(anyhow!("Image profile {name:?} not found"));
+        }
+
+        if image_profiles.active_profile.as_deref() == Some(name) {
+            image_profiles.active_profile = None;
+        }
+
+        info!("Image profile {name:?} deleted");
+
+        self.settings.save().await?;
+
+        Ok(self.get_image_profiles(camera_uuid))
+    }
+
+    /// Remembers the profile last applied to the camera, so it's known across restarts
+    #[instrument(level = "debug", skip(self))]
+    pub async fn set_active_image_profile(
+        &mut self,
+        camera_uuid: &Uuid,
+        name: &str,
+    ) -> Result<api::ImageProfiles> {
+        let image_profiles = self
+            .settings
+            .image_profiles
+            .get_mut(camera_uuid)
+            .filter(|image_profiles| image_profiles.profiles.contains_key(name))
+            .with_context(|| format!("Image profile {name:?} not found"))?;
+
+        image_profiles.active_profile = Some(name.to_string());
+
+        info!("Image profile {name:?} applied to camera {camera_uuid}");
+
+        self.settings.save().await?;
+
+        Ok(self.get_image_profiles(camera_uuid))
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    pub async fn set_reapply_policy(
+        &mut self,
+        camera_uuid: &Uuid,
+        policy: api::ReapplyPolicy,
+    ) -> Result<api::ImageProfiles> {
+        self.settings
+            .image_profiles
+            .entry(*camera_uuid)
+            .or_default()
+            .reapply_policy = policy;
+
+        info!("Camera {camera_uuid} reapply policy set to {policy:?}");
+
+        self.settings.save().await?;
+
+        Ok(self.get_image_profiles(camera_uuid))
+    }
+}
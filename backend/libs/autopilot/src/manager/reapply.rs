@@ -0,0 +1,263 @@
+use std::{collections::HashSet, time::Duration};
+
+use anyhow::{Context, Result};
+use indexmap::IndexMap;
+use mcm_client::CameraKind;
+use radcam_commands::protocol::video::video_parameters::VideoParameterSettings;
+use serde_json::Value;
+use tokio::sync::broadcast;
+use tracing::*;
+use uuid::Uuid;
+
+use crate::{api, events, manager::MANAGER};
+
+/// How often the cameras uptime is checked for reboots
+const REBOOT_CHECK_PERIOD: Duration = Duration::from_secs(30);
+/// Fields that trigger an action instead of holding a setting
+const IGNORED_FIELDS: [&str; 3] = ["set_default", "onceAWB", "irisLevel"];
+
+/// Compares the cameras against their active image profile when they appear or reboot, applying
+/// it again or notifying the drift, as each camera's policy says
+#[instrument(level = "debug")]
+pub(crate) async fn reapply_task() {
+    use broadcast::error::RecvError;
+
+    let mut appearances = mcm_client::subscribe_appearances();
+    let mut uptimes: IndexMap<Uuid, u64> = IndexMap::new();
+    // Warned about once each
+    let mut without_uptime: HashSet<Uuid> = HashSet::new();
+
+    // Also gives the MCM client the time to start
+    tokio::time::sleep(REBOOT_CHECK_PERIOD).await;
+
+    // The cameras found before we subscribed are checked as if they just appeared
+    for camera_uuid in mcm_client::cameras().await.keys() {
+        check_camera(camera_uuid, api::DriftReason::Appeared).await;
+    }
+
+    let mut interval = tokio::time::interval(REBOOT_CHECK_PERIOD);
+
+    loop {
+        tokio::select! {
+            appearance = appearances.recv() => match appearance {
+                Ok(camera_uuid) => {
+                    uptimes.shift_remove(&camera_uuid);
+
+                    check_camera(&camera_uuid, api::DriftReason::Appeared).await;
+                }
+                Err(RecvError::Lagged(n)) => warn!("Appearances receiver lagged by {n} cameras"),
+                Err(RecvError::Closed) => {
+                    error!("Appearances channel closed");
+                    break;
+                }
+            },
+            _ = interval.tick() => {
+                let cameras = mcm_client::cameras().await;
+                uptimes.retain(|camera_uuid, _| cameras.contains_key(camera_uuid));
+                without_uptime.retain(|camera_uuid| cameras.contains_key(camera_uuid));
+
+                let radcams = cameras
+                    .values()
+                    .filter(|camera| camera.kind == CameraKind::RadCam);
+
+                for camera_uuid in radcams.map(|camera| &camera.uuid) {
+                    let uptime = match radcam_commands::get_sys_config(camera_uuid).await {
+                        Ok(sys_config) => sys_config.uptime,
+                        Err(error) => {
+                            debug!("Failed reading camera {camera_uuid} uptime: {error:?}");
+                            continue;
+                        }
+                    };
+
+                    let Some(uptime) = uptime else {
+                        if without_uptime.insert(*camera_uuid) {
+                            warn!("Camera {camera_uuid} doesn't report its uptime, so its reboots go unnoticed");
+                        }
+                        continue;
+                    };
+
+                    if uptimes
+                        .insert(*camera_uuid, uptime)
+                        .is_some_and(|previous_uptime| uptime < previous_uptime)
+                    {
+                        info!("Camera {camera_uuid} rebooted");
+
+                        check_camera(camera_uuid, api::DriftReason::Rebooted).await;
+                    }
+                }
+            }
+        }
+    }
+}
+
+async fn check_camera(camera_uuid: &Uuid, reason: api::DriftReason) {
+    // The image profiles hold RadCam settings
+    if mcm_client::get_camera(camera_uuid)
+        .await
+        .is_none_or(|camera| camera.kind != CameraKind::RadCam)
+    {
+        return;
+    }
+
+    if let Err(error) = reconcile(camera_uuid, reason).await {
+        warn!("Failed checking camera {camera_uuid} settings: {error:?}");
+    }
+}
+
+#[instrument(level = "debug")]
+async fn reconcile(camera_uuid: &Uuid, reason: api::DriftReason) -> Result<()> {
+    let (profile, policy) = {
+        let manager = MANAGER.get().context("Not available")?.read().await;
+
+        let Some(image_profiles) = manager.settings.image_profiles.get(camera_uuid) else {
+            return Ok(());
+        };
+
+        let Some(profile) = image_profiles
+            .active_profile
+            .as_ref()
+            .and_then(|name| image_profiles.profiles.get(name))
+        else {
+            return Ok(());
+        };
+
+        (profile.clone(), image_profiles.reapply_policy)
+    };
+
+    if policy == api::ReapplyPolicy::Ignore {
+        return Ok(());
+    }
+
+    let differences = profile_differences(camera_uuid, &profile).await?;
+    if differences.is_empty() {
+        debug!(
+            "Camera {camera_uuid} matches its image profile {:?}",
+            profile.name
+        );
+        return Ok(());
+    }
+
+    warn!(
+        "Camera {camera_uuid} drifted from its image profile {:?} ({reason:?}): {differences:?}",
+        profile.name
+    );
+
+    let reapplied = match policy {
+        api::ReapplyPolicy::Auto => match crate::apply_image_profile(camera_uuid, &profile).await {
+            Ok(()) => {
+                info!("Image profile {:?} applied again", profile.name);
+                true
+            }
+            Err(error) => {
+                warn!("Failed applying the image profile again: {error:?}");
+                false
+            }
+        },
+        api::ReapplyPolicy::Notify | api::ReapplyPolicy::Ignore => false,
+    };
+
+    events::publish(api::ActuatorsEvent::SettingsDrift(api::SettingsDrift {
+        camera_uuid: *camera_uuid,
+        profile: profile.name,
+        reason,
+        differences,
+        reapplied,
+    }));
+
+    Ok(())
+}
+
+/// The settings in the profile which the camera doesn't have
+async fn profile_differences(
+    camera_uuid: &Uuid,
+    profile: &api::ImageProfile,
+) -> Result<Vec<String>> {
+    let mut differences = Vec::new();
+
+    if let Some(image_adjustment) = &profile.image_adjustment {
+        let live = radcam_commands::get_image_adjustment(camera_uuid).await?;
+
+        differences.extend(differences_between(
+            "image_adjustment",
+            &serde_json::to_value(image_adjustment)?,
+            &serde_json::to_value(live)?,
+        ));
+    }
+
+    if let Some(image_adjustment_ex) = &profile.image_adjustment_ex {
+        let live = radcam_commands::get_image_adjustment_ex(camera_uuid).await?;
+
+        differences.extend(differences_between(
+            "image_adjustment_ex",
+            &serde_json::to_value(image_adjustment_ex)?,
+            &serde_json::to_value(live)?,
+        ));
+    }
+
+    for video_parameters in &profile.video_parameters {
+        let Some(channel) = &video_parameters.channel else {
+            continue;
+        };
+
+        let live = radcam_commands::get_video_parameters(camera_uuid, channel.clone()).await?;
+
+        // The supported resolutions and frame rates are not settings
+        let expected = VideoParameterSettings {
+            pixel_list: None,
+            max_framerate: None,
+            ..video_parameters.clone()
+        };
+
+        differences.extend(differences_between(
+            &format!("video_parameters[{channel:?}]"),
+            &serde_json::to_value(expected)?,
+            &serde_json::to_value(live)?,
+        ));
+    }
+
+    Ok(differences)
+}
+
+/// The fields set in `expected` that `live` doesn't match
+fn differences_between(prefix: &str, expected: &Value, live: &Value) -> Vec<String> {
+    let Some(expected) = expected.as_object() else {
+        return vec![];
+    };
+
+    expected
+        .iter()
+        .filter(|(field, _)| !IGNORED_FIELDS.contains(&field.as_str()))
+        .filter_map(|(field, expected_value)| {
+            let live_value = live.get(field).unwrap_or(&Value::Null);
+
+            (live_value != expected_value)
+                .then(|| format!("{prefix}.{field}: {live_value} instead of {expected_value}"))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn differences_between_test() {
+        let expected = json!({ "brightness": 60, "contrast": 50, "set_default": 1 });
+        let live = json!({ "brightness": 60, "contrast": 40, "hue": 50 });
+
+        assert_eq!(
+            differences_between("image_adjustment", &expected, &live),
+            vec!["image_adjustment.contrast: 40 instead of 50".to_string()]
+        );
+
+        let live = json!({ "brightness": 60 });
+        assert_eq!(
+            differences_between("image_adjustment", &expected, &live),
+            vec!["image_adjustment.contrast: null instead of 50".to_string()]
+        );
+
+        assert!(differences_between("image_adjustment", &expected, &expected).is_empty());
+    }
+}
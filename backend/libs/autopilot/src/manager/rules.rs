@@ -0,0 +1,503 @@
+use std::time::{Duration, Instant};
+
+use ::mavlink::ardupilotmega::{MavMessage, SERVO_OUTPUT_RAW_DATA};
+use anyhow::{Context, Result, anyhow};
+use axum::Json;
+use chrono::{DateTime, Timelike, Utc};
+use indexmap::IndexMap;
+use tokio::sync::broadcast;
+use tracing::*;
+use uuid::Uuid;
+
+use crate::{
+    api, events,
+    manager::{MANAGER, Manager, osd, tracker::servo_output_state},
+    mavlink::Message,
+};
+
+/// How often the rules are evaluated
+const PERIOD: Duration = Duration::from_millis(500);
+/// After this time without a SERVO_OUTPUT_RAW, the lights output is unknown
+const STALE_TIMEOUT: Duration = Duration::from_secs(3);
+const MINUTES_PER_DAY: u16 = 24 * 60;
+
+/// Whether a rule is active, and since when its condition disagrees with it
+#[derive(Debug, Default, Clone, Copy)]
+struct RuleState {
+    active: bool,
+    pending_since: Option<Instant>,
+}
+
+/// The readings the conditions are evaluated on
+#[derive(Debug)]
+struct Telemetry {
+    depth: Option<f32>,
+    time: DateTime<Utc>,
+    servo_outputs: Vec<SERVO_OUTPUT_RAW_DATA>,
+}
+
+impl Manager {
+    #[instrument(level = "debug", skip(self))]
+    pub fn get_rules(&self, camera_uuid: &Uuid) -> Vec<api::Rule> {
+        self.settings
+            .rules
+            .get(camera_uuid)
+            .map(|rules| rules.values().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Creates a new rule, or replaces the existing one with the same name
+    #[instrument(level = "debug", skip(self))]
+    pub async fn set_rule(
+        &mut self,
+        camera_uuid: &Uuid,
+        rule: &api::Rule,
+    ) -> Result<Vec<api::Rule>> {
+        self.validate_rule(camera_uuid, rule)?;
+
+        let old_rule = self
+            .settings
+            .rules
+            .entry(*camera_uuid)
+            .or_default()
+            .insert(rule.name.clone(), rule.clone());
+
+        match old_rule {
+            Some(old_rule) => info!("Rule {:?} changed from {old_rule:?}", rule.name),
+            None => info!("Rule {:?} created", rule.name),
+        }
+
+        self.settings.save().await?;
+
+        Ok(self.get_rules(camera_uuid))
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    pub async fn delete_rule(&mut self, camera_uuid: &Uuid, name: &str) -> Result<Vec<api::Rule>> {
+        self.settings
+            .rules
+            .get_mut(camera_uuid)
+            .and_then(|rules| rules.shift_remove(name))
+            .with_context(|| format!("Rule {name:?} not found"))?;
+
+        info!("Rule {name:?} deleted");
+
+        self.settings.save().await?;
+
+        Ok(self.get_rules(camera_uuid))
+    }
+
+    fn validate_rule(&self, camera_uuid: &Uuid, rule: &api::Rule) -> Result<()> {
+        if rule.name.trim().is_empty() {
+            return Err(anyhow!("Rule name can't be empty"));
+        }
+
+        match &rule.condition {
+            api::RuleCondition::Depth {
+                threshold,
+                hysteresis,
+                ..
+            } => {
+                if !threshold.is_finite() || !hysteresis.is_finite() || *hysteresis < 0. {
+                    return Err(anyhow!(
+                        "Invalid depth threshold {threshold} or hysteresis {hysteresis}"
+                    ));
+                }
+            }
+            api::RuleCondition::LightsPwm { .. } => (),
+            api::RuleCondition::TimeOfDay {
+                start_minute,
+                end_minute,
+            } => {
+                if *start_minute >= MINUTES_PER_DAY || *end_minute >= MINUTES_PER_DAY {
+                    return Err(anyhow!(
+                        "Time of day minutes should be less than {MINUTES_PER_DAY}"
+                    ));
+                }
+
+                if start_minute == end_minute {
+                    return Err(anyhow!("Time of day start and end can't be the same"));
+                }
+            }
+        }
+
+        for action in std::iter::once(&rule.action).chain(&rule.exit_action) {
+            if let api::RuleAction::ApplyProfile(name) = action {
+                self.get_image_profile(camera_uuid, name)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Applies the rules actions as their conditions start or stop holding
+#[instrument(level = "debug", skip(receiver))]
+pub(crate) async fn rules_task(mut receiver: broadcast::Receiver<Message>) {
+    use broadcast::error::RecvError;
+
+    let mut servo_outputs: IndexMap<u8, (Instant, SERVO_OUTPUT_RAW_DATA)> = IndexMap::new();
+    let mut states: IndexMap<(Uuid, String), RuleState> = IndexMap::new();
+    let mut interval = tokio::time::interval(PERIOD);
+
+    loop {
+        tokio::select! {
+            message = receiver.recv() => match message {
+                // One message for each port, with 16 channels each
+                Ok(Message::Received((_, MavMessage::SERVO_OUTPUT_RAW(data)))) => {
+                    servo_outputs.insert(data.port, (Instant::now(), data));
+                }
+                Ok(_) => (),
+                Err(RecvError::Closed) => {
+                    error!("Receiver channel closed");
+                    break;
+                }
+                Err(RecvError::Lagged(n)) => warn!("Receiver lagged by {n} messages"),
+            },
+            _ = interval.tick() => {
+                let Some(manager) = MANAGER.get() else {
+                    continue;
+                };
+
+                let telemetry = Telemetry {
+                    depth: osd::depth().await,
+                    time: osd::time().await,
+                    servo_outputs: servo_outputs
+                        .values()
+                        .filter(|(received, _)| received.elapsed() < STALE_TIMEOUT)
+                        .map(|(_, data)| data.clone())
+                        .collect(),
+                };
+                let rules = manager.read().await.settings.rules.clone();
+
+                let switches = evaluate(&rules, &telemetry, &mut states, Instant::now());
+
+                for (camera_uuid, rule, active) in switches {
+                    run_rule(&camera_uuid, &rule, active).await;
+                }
+            }
+        }
+    }
+}
+
+/// The names of the rules that apply the profile, when they start or stop holding
+pub(super) fn rules_applying_profile(
+    rules: &IndexMap<String, api::Rule>,
+    profile: &str,
+) -> Vec<String> {
+    rules
+        .values()
+        .filter(|rule| {
+            std::iter::once(&rule.action).chain(&rule.exit_action).any(
+                |action| matches!(action, api::RuleAction::ApplyProfile(name) if name == profile),
+            )
+        })
+        .map(|rule| rule.name.clone())
+        .collect()
+}
+
+/// The rules that switched, and whether they are active now. Disabled and deleted rules are
+/// forgotten, so they start inactive when enabled again
+fn evaluate(
+    rules: &IndexMap<Uuid, IndexMap<String, api::Rule>>,
+    telemetry: &Telemetry,
+    states: &mut IndexMap<(Uuid, String), RuleState>,
+    now: Instant,
+) -> Vec<(Uuid, api::Rule, bool)> {
+    states.retain(|(camera_uuid, name), _| {
+        rules
+            .get(camera_uuid)
+            .and_then(|camera_rules| camera_rules.get(name))
+            .is_some_and(|rule| rule.enabled)
+    });
+
+    let mut switches = Vec::new();
+
+    for (camera_uuid, camera_rules) in rules {
+        for rule in camera_rules.values().filter(|rule| rule.enabled) {
+            let state = states.entry((*camera_uuid, rule.name.clone())).or_default();
+
+            // Without a reading, the rule stays as it is
+            let Some(holds) = condition_holds(&rule.condition, state.active, telemetry) else {
+                state.pending_since = None;
+                continue;
+            };
+
+            if switch(state, holds, now, Duration::from_millis(rule.dwell_ms)) {
+                switches.push((*camera_uuid, rule.clone(), state.active));
+            }
+        }
+    }
+
+    switches
+}
+
+async fn run_rule(camera_uuid: &Uuid, rule: &api::Rule, active: bool) {
+    let action = if active {
+        Some(&rule.action)
+    } else {
+        rule.exit_action.as_ref()
+    };
+
+    info!(
+        "Rule {:?} of camera {camera_uuid} {}",
+        rule.name,
+        if active { "activated" } else { "deactivated" }
+    );
+
+    let Some(action) = action else {
+        return;
+    };
+
+    let error = match run_action(camera_uuid, action).await {
+        Ok(()) => None,
+        Err(error) => {
+            warn!("Failed running rule {:?} action: {error:?}", rule.name);
+            Some(format!("{error:#}"))
+        }
+    };
+
+    events::publish(api::ActuatorsEvent::RuleTriggered(api::RuleTriggered {
+        camera_uuid: *camera_uuid,
+        rule: rule.name.clone(),
+        active,
+        error,
+    }));
+}
+
+async fn run_action(camera_uuid: &Uuid, action: &api::RuleAction) -> Result<()> {
+    match action {
+        api::RuleAction::ApplyProfile(name) => {
+            let profile = MANAGER
+                .get()
+                .context("Not available")?
+                .read()
+                .await
+                .get_image_profile(camera_uuid, name)?;
+
+            crate::apply_image_profile(camera_uuid, &profile).await?;
+
+            MANAGER
+                .get()
+                .context("Not available")?
+                .write()
+                .await
+                .set_active_image_profile(camera_uuid, name)
+                .await?;
+        }
+        api::RuleAction::CameraAction(action) => {
+            radcam_commands::control_inner(Json(radcam_commands::CameraControl {
+                camera_uuid: *camera_uuid,
+                action: action.clone(),
+            }))
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether the condition holds, if its reading is available. An active rule uses the hysteresis
+fn condition_holds(
+    condition: &api::RuleCondition,
+    active: bool,
+    telemetry: &Telemetry,
+) -> Option<bool> {
+    match condition {
+        api::RuleCondition::Depth {
+            comparison,
+            threshold,
+            hysteresis,
+        } => telemetry
+            .depth
+            .map(|depth| threshold_holds(depth, *comparison, *threshold, *hysteresis, active)),
+        api::RuleCondition::LightsPwm {
+            channel,
+            comparison,
+            threshold,
+            hysteresis,
+        } => telemetry
+            .servo_outputs
+            .iter()
+            .find_map(|data| servo_output_state(data, *channel, |pwm| Some(pwm as f32)))
+            .map(|pwm| {
+                threshold_holds(
+                    pwm,
+                    *comparison,
+                    *threshold as f32,
+                    *hysteresis as f32,
+                    active,
+                )
+            }),
+        api::RuleCondition::TimeOfDay {
+            start_minute,
+            end_minute,
+        } => {
+            let minute = (telemetry.time.hour() * 60 + telemetry.time.minute()) as u16;
+
+            Some(time_holds(minute, *start_minute, *end_minute))
+        }
+    }
+}
+
+fn threshold_holds(
+    value: f32,
+    comparison: api::Comparison,
+    threshold: f32,
+    hysteresis: f32,
+    active: bool,
+) -> bool {
+    let hysteresis = if active { hysteresis } else { 0. };
+
+    match comparison {
+        api::Comparison::Greater => value > threshold - hysteresis,
+        api::Comparison::Less => value < threshold + hysteresis,
+    }
+}
+
+fn time_holds(minute: u16, start_minute: u16, end_minute: u16) -> bool {
+    if start_minute <= end_minute {
+        (start_minute..end_minute).contains(&minute)
+    } else {
+        minute >= start_minute || minute < end_minute
+    }
+}
+
+/// Switches the rule once its condition disagrees with it for the dwell time. Returns whether it
+/// switched
+fn switch(state: &mut RuleState, holds: bool, now: Instant, dwell: Duration) -> bool {
+    if holds == state.active {
+        state.pending_since = None;
+        return false;
+    }
+
+    let pending_since = *state.pending_since.get_or_insert(now);
+    if now.duration_since(pending_since) < dwell {
+        return false;
+    }
+
+    state.active = holds;
+    state.pending_since = None;
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_threshold_holds() {
+        use api::Comparison::{Greater, Less};
+
+        // Enters beyond 20 m, and leaves above 18 m
+        assert!(!threshold_holds(19., Greater, 20., 2., false));
+        assert!(threshold_holds(21., Greater, 20., 2., false));
+        assert!(threshold_holds(19., Greater, 20., 2., true));
+        assert!(!threshold_holds(17., Greater, 20., 2., true));
+
+        // Enters below 1200 us, and leaves beyond 1300 us
+        assert!(threshold_holds(1100., Less, 1200., 100., false));
+        assert!(!threshold_holds(1250., Less, 1200., 100., false));
+        assert!(threshold_holds(1250., Less, 1200., 100., true));
+        assert!(!threshold_holds(1350., Less, 1200., 100., true));
+    }
+
+    #[test]
+    fn test_time_holds() {
+        assert!(time_holds(12 * 60, 6 * 60, 18 * 60));
+        assert!(!time_holds(18 * 60, 6 * 60, 18 * 60));
+        assert!(!time_holds(3 * 60, 6 * 60, 18 * 60));
+
+        // Around midnight
+        assert!(time_holds(23 * 60, 22 * 60, 6 * 60));
+        assert!(time_holds(60, 22 * 60, 6 * 60));
+        assert!(!time_holds(12 * 60, 22 * 60, 6 * 60));
+    }
+
+    #[test]
+    fn test_rules_applying_profile() {
+        let rule = |name: &str, action, exit_action| api::Rule {
+            name: name.to_string(),
+            enabled: true,
+            condition: api::RuleCondition::TimeOfDay {
+                start_minute: 0,
+                end_minute: 60,
+            },
+            action,
+            exit_action,
+            dwell_ms: 0,
+        };
+        let apply = |name: &str| api::RuleAction::ApplyProfile(name.to_string());
+
+        let rules = IndexMap::from([
+            ("deep".to_string(), rule("deep", apply("turbid"), None)),
+            (
+                "shallow".to_string(),
+                rule("shallow", apply("clear"), Some(apply("turbid"))),
+            ),
+            ("lights".to_string(), rule("lights", apply("clear"), None)),
+        ]);
+
+        assert_eq!(
+            rules_applying_profile(&rules, "turbid"),
+            vec!["deep".to_string(), "shallow".to_string()]
+        );
+        assert!(rules_applying_profile(&rules, "night").is_empty());
+    }
+
+    #[test]
+    fn test_switch() {
+        let dwell = Duration::from_secs(5);
+        let start = Instant::now();
+        let mut state = RuleState::default();
+
+        assert!(!switch(&mut state, true, start, dwell));
+        assert!(!switch(
+            &mut state,
+            true,
+            start + Duration::from_secs(4),
+            dwell
+        ));
+        assert!(switch(
+            &mut state,
+            true,
+            start + Duration::from_secs(5),
+            dwell
+        ));
+        assert!(state.active);
+
+        // A short glitch restarts the dwell
+        assert!(!switch(
+            &mut state,
+            false,
+            start + Duration::from_secs(6),
+            dwell
+        ));
+        assert!(!switch(
+            &mut state,
+            true,
+            start + Duration::from_secs(7),
+            dwell
+        ));
+        assert!(!switch(
+            &mut state,
+            false,
+            start + Duration::from_secs(8),
+            dwell
+        ));
+        assert!(!switch(
+            &mut state,
+            false,
+            start + Duration::from_secs(12),
+            dwell
+        ));
+        assert!(switch(
+            &mut state,
+            false,
+            start + Duration::from_secs(13),
+            dwell
+        ));
+        assert!(!state.active);
+    }
+}
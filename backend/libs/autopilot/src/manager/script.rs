@@ -12,6 +12,7 @@ use crate::{
 
 const PARAM_TABLE_KEY_BASE: u8 = 73;
 pub const PARAM_PREFIX: &'static str = "RCAM";
+pub const SCRIPT_BUTTON_SUFFIX: &'static str = "_BTN";
 
 impl Manager {
     #[instrument(level = "debug", skip(self))]
@@ -144,15 +145,52 @@ impl Manager {
         self.update_script_channel_parameters(camera_uuid, parameters, autopilot_reboot_required)
             .await?;
 
+        self.update_script_interpolation(camera_uuid, parameters);
+
         self.update_script_enable(camera_uuid, parameters, true)
             .await?;
 
         self.update_script_gain(camera_uuid, parameters, true)
             .await?;
 
+        self.update_script_rangefinder_enable(camera_uuid, parameters, true)
+            .await?;
+
+        self.update_script_rangefinder_orientation(camera_uuid, parameters, true)
+            .await?;
+
         Ok(autopilot_reboot_required)
     }
 
+    /// The interpolation is embedded in the script, so there's no autopilot parameter to set
+    fn update_script_interpolation(
+        &mut self,
+        camera_uuid: &Uuid,
+        parameters: &api::ActuatorsParametersConfig,
+    ) {
+        let Some(new_value) = parameters.focus_interpolation else {
+            return;
+        };
+
+        let current_parameters = &mut self
+            .settings
+            .actuators
+            .entry(*camera_uuid)
+            .or_default()
+            .parameters;
+
+        let old_value = current_parameters.focus_interpolation;
+        if old_value != new_value {
+            info!(
+                "{} changed from {:?} to {:?}",
+                stringify!(focus_interpolation),
+                old_value,
+                new_value
+            );
+            current_parameters.focus_interpolation = new_value;
+        }
+    }
+
     async fn update_script_enable(
         &mut self,
         camera_uuid: &Uuid,
@@ -263,6 +301,26 @@ impl Manager {
         Ok(())
     }
 
+    generate_update_channel_param_function!(
+        update_script_rangefinder_enable,
+        enable_rangefinder_focus,
+        PARAM_PREFIX,
+        "RF_ENABLE",
+        UINT8 as u8,
+        camera_id,
+        created_by_script
+    );
+
+    generate_update_channel_param_function!(
+        update_script_rangefinder_orientation,
+        rangefinder_orientation,
+        PARAM_PREFIX,
+        "RF_ORIENT",
+        UINT8 as u8,
+        camera_id,
+        created_by_script
+    );
+
     #[instrument(level = "debug", skip(self, parameters))]
     pub async fn update_script_channel_parameters(
         &mut self,
@@ -318,10 +376,31 @@ fn generate_lua_script(config: &CameraActuators) -> Result<String> {
 
     context.insert("param_table_key", &param_table_key);
     context.insert("param_prefix", &param_prefix);
+    context.insert(
+        "script_button_name",
+        &format!("\"{PARAM_PREFIX}{channel}{SCRIPT_BUTTON_SUFFIX}\""),
+    );
     context.insert("margin_gain", &(config.parameters.focus_margin_gain as f32));
     context.insert("k_script", &(config.parameters.script_function as u8));
-    context.insert("closest_points", &config.closest_points.to_lua());
-    context.insert("furthest_points", &config.furthest_points.to_lua());
+    context.insert(
+        "rangefinder_enable",
+        &(config.parameters.enable_rangefinder_focus as u8),
+    );
+    context.insert(
+        "rangefinder_orientation",
+        &(config.parameters.rangefinder_orientation as u8),
+    );
+    let interpolation = config.parameters.focus_interpolation;
+    context.insert("interpolation", &format!("{interpolation:?}"));
+    context.insert(
+        "closest_points",
+        &config.closest_points.to_lua(interpolation),
+    );
+    context.insert(
+        "furthest_points",
+        &config.furthest_points.to_lua(interpolation),
+    );
+    context.insert("distance_points", &config.distance_points.to_lua());
 
     let template = include_str!("radcam.lua.template");
 
@@ -349,4 +428,34 @@ mod tests {
 
         validate_lua(&contents).unwrap();
     }
+
+    #[test]
+    fn test_script_generation_for_each_interpolation() {
+        for mode in [
+            api::InterpolationMode::Linear,
+            api::InterpolationMode::MonotoneCubic,
+            api::InterpolationMode::CatmullRom,
+        ] {
+            let mut actuators = CameraActuators::default();
+            actuators.parameters.focus_interpolation = mode;
+
+            let contents = generate_lua_script(&actuators).unwrap();
+
+            validate_lua(&contents).unwrap();
+            assert!(contents.contains(&format!("Interpolation mode: {mode:?}")));
+        }
+    }
+
+    #[test]
+    fn test_script_generation_with_rangefinder_focus() {
+        let mut actuators = CameraActuators::default();
+        actuators.parameters.enable_rangefinder_focus = true;
+        actuators.parameters.rangefinder_orientation = api::RangefinderOrientation::Down;
+
+        let contents = generate_lua_script(&actuators).unwrap();
+
+        validate_lua(&contents).unwrap();
+        assert!(contents.contains(r#"add_param(PARAM_TABLE_KEY, 3, "RF_ENABLE", 1)"#));
+        assert!(contents.contains(r#"add_param(PARAM_TABLE_KEY, 4, "RF_ORIENT", 25)"#));
+    }
 }
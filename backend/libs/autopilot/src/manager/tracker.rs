@@ -0,0 +1,442 @@
+use std::time::{Duration, Instant};
+
+use ::mavlink::{
+    Message as _,
+    ardupilotmega::{MOUNT_STATUS_DATA, MavMessage, SERVO_OUTPUT_RAW_DATA},
+};
+use anyhow::{Context, Result};
+use indexmap::IndexMap;
+use once_cell::sync::Lazy;
+use tokio::sync::{RwLock, broadcast};
+use tracing::*;
+use uuid::Uuid;
+
+use crate::{
+    CameraActuators, api, events, manager::MANAGER, mavlink::Message,
+    parameters::ActuatorsParameters,
+};
+
+/// The interval we ask the autopilot to stream the tracked messages at
+const STREAM_INTERVAL: Duration = Duration::from_millis(200);
+/// After this time without tracked messages, the state is considered stale
+const STALE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Behind its own lock, so the stream of tracked messages doesn't contend for the manager
+static TRACKER: Lazy<RwLock<StateTracker>> = Lazy::new(|| RwLock::new(StateTracker::default()));
+
+/// The actuators state of each camera, refreshed from the autopilot outputs
+#[derive(Debug, Default)]
+struct StateTracker {
+    cameras: IndexMap<Uuid, TrackedCamera>,
+    last_mount_status: Option<Instant>,
+}
+
+#[derive(Debug)]
+struct TrackedCamera {
+    parameters: ActuatorsParameters,
+    state: api::ActuatorsState,
+    /// When the focus or zoom outputs of this camera were last seen
+    last_servo_output: Option<Instant>,
+}
+
+impl StateTracker {
+    #[instrument(level = "debug", skip(self, data))]
+    fn track_servo_output(&mut self, data: &SERVO_OUTPUT_RAW_DATA) {
+        let mount_status_is_fresh = is_recent(self.last_mount_status);
+
+        for (camera_uuid, camera) in self.cameras.iter_mut() {
+            let parameters = &camera.parameters;
+            let old_state = camera.state;
+            let state = &mut camera.state;
+
+            let focus = servo_output_state(data, parameters.focus_channel, |pwm| {
+                normalize_range_pwm(
+                    pwm,
+                    parameters.focus_channel_min,
+                    parameters.focus_channel_max,
+                )
+            });
+            let zoom = servo_output_state(data, parameters.zoom_channel, |pwm| {
+                normalize_range_pwm(
+                    pwm,
+                    parameters.zoom_channel_min,
+                    parameters.zoom_channel_max,
+                )
+            });
+
+            if focus.is_some() || zoom.is_some() {
+                camera.last_servo_output = Some(Instant::now());
+            }
+            if let Some(focus) = focus {
+                state.focus = Some(focus);
+            }
+            if let Some(zoom) = zoom {
+                state.zoom = Some(zoom);
+            }
+
+            if let Some(focus_output) = servo_output_state(data, parameters.script_channel, |pwm| {
+                normalize_range_pwm(
+                    pwm,
+                    parameters.script_channel_min,
+                    parameters.script_channel_max,
+                )
+            }) {
+                state.focus_output = Some(focus_output);
+            }
+
+            // The mount status is more accurate, so it takes precedence for the primary mount
+            let tilt = if mount_status_is_fresh && parameters.camera_id == api::CameraID::CAM1 {
+                None
+            } else {
+                servo_output_state(data, parameters.tilt_channel, |pwm| {
+                    normalize_angle_pwm(
+                        pwm,
+                        parameters.tilt_channel_min,
+                        parameters.tilt_channel_trim,
+                        parameters.tilt_channel_max,
+                    )
+                    .map(|tilt| {
+                        if parameters.tilt_channel_reversed {
+                            100. - tilt
+                        } else {
+                            tilt
+                        }
+                    })
+                })
+            };
+            if let Some(tilt) = tilt {
+                state.tilt = Some(tilt);
+            }
+
+            publish_if_changed(camera_uuid, &old_state, state);
+        }
+    }
+
+    /// MOUNT_STATUS only reports the primary mount
+    #[instrument(level = "debug", skip(self, data))]
+    fn track_mount_status(&mut self, data: &MOUNT_STATUS_DATA) {
+        self.last_mount_status = Some(Instant::now());
+
+        for (camera_uuid, camera) in self.cameras.iter_mut() {
+            let parameters = &camera.parameters;
+
+            if parameters.camera_id != api::CameraID::CAM1 {
+                continue;
+            }
+
+            // pointing_a is the pitch, in centidegrees
+            let pitch = data.pointing_a as f32 / 100.;
+            let old_state = camera.state;
+            if let Some(tilt) = normalize_pitch(pitch, parameters) {
+                camera.state.tilt = Some(tilt);
+            }
+
+            publish_if_changed(camera_uuid, &old_state, &camera.state);
+        }
+    }
+}
+
+/// Follows the configured cameras, returning the state of each. Cameras seen for the first time
+/// start from their stored state, and changing the parameters of a camera makes its state stale
+pub(super) async fn track_cameras(
+    actuators: &IndexMap<Uuid, CameraActuators>,
+) -> IndexMap<Uuid, api::ActuatorsState> {
+    let mut tracker = TRACKER.write().await;
+
+    tracker
+        .cameras
+        .retain(|camera_uuid, _| actuators.contains_key(camera_uuid));
+
+    actuators
+        .iter()
+        .map(|(camera_uuid, camera_actuators)| {
+            let camera = tracker
+                .cameras
+                .entry(*camera_uuid)
+                .or_insert_with(|| TrackedCamera {
+                    parameters: camera_actuators.parameters.clone(),
+                    state: camera_actuators.state,
+                    last_servo_output: None,
+                });
+
+            if camera.parameters != camera_actuators.parameters {
+                camera.parameters = camera_actuators.parameters.clone();
+                camera.last_servo_output = None;
+            }
+
+            (*camera_uuid, camera.state)
+        })
+        .collect()
+}
+
+/// The tracked state of the camera
+pub(super) async fn state(camera_uuid: &Uuid) -> Option<api::ActuatorsState> {
+    TRACKER
+        .read()
+        .await
+        .cameras
+        .get(camera_uuid)
+        .map(|camera| camera.state)
+}
+
+/// The tracked state of the camera, if its focus and zoom are being kept fresh by the autopilot
+/// outputs
+pub(super) async fn fresh_state(camera_uuid: &Uuid) -> Option<api::ActuatorsState> {
+    TRACKER
+        .read()
+        .await
+        .cameras
+        .get(camera_uuid)
+        .filter(|camera| is_recent(camera.last_servo_output))
+        .map(|camera| camera.state)
+}
+
+/// Updates the state of the camera from elsewhere, like the autopilot CAMERA_SETTINGS
+pub(super) async fn set_state(camera_uuid: &Uuid, state: api::ActuatorsState) {
+    let mut tracker = TRACKER.write().await;
+
+    let Some(camera) = tracker.cameras.get_mut(camera_uuid) else {
+        return;
+    };
+
+    publish_if_changed(camera_uuid, &camera.state, &state);
+    camera.state = state;
+}
+
+/// Keeps the tracked state fresh from the autopilot servo outputs and mount status
+#[instrument(level = "debug", skip(receiver))]
+pub(crate) async fn state_tracker_task(mut receiver: broadcast::Receiver<Message>) {
+    if let Err(error) = request_tracked_messages().await {
+        warn!("Failed requesting the tracked messages, relying on their default rates: {error:?}");
+    }
+
+    loop {
+        use broadcast::error::RecvError;
+
+        let message = match receiver.recv().await {
+            Ok(Message::Received((_, message))) => message,
+            Ok(_) => continue,
+            Err(RecvError::Closed) => {
+                error!("Receiver channel closed");
+                break;
+            }
+            Err(RecvError::Lagged(n)) => {
+                warn!("Receiver lagged by {n} messages");
+                continue;
+            }
+        };
+
+        if !matches!(
+            message,
+            MavMessage::SERVO_OUTPUT_RAW(_) | MavMessage::MOUNT_STATUS(_)
+        ) {
+            continue;
+        }
+
+        let mut tracker = TRACKER.write().await;
+
+        match &message {
+            MavMessage::SERVO_OUTPUT_RAW(data) => tracker.track_servo_output(data),
+            MavMessage::MOUNT_STATUS(data) => tracker.track_mount_status(data),
+            _ => unreachable!(),
+        }
+    }
+}
+
+async fn request_tracked_messages() -> Result<()> {
+    let manager = MANAGER.get().context("Not available")?.read().await;
+
+    for message_id in [SERVO_OUTPUT_RAW_DATA::ID, MOUNT_STATUS_DATA::ID] {
+        manager
+            .mavlink
+            .set_message_interval(message_id, STREAM_INTERVAL)
+            .await?;
+    }
+
+    Ok(())
+}
+
+fn publish_if_changed(
+    camera_uuid: &Uuid,
+    old_state: &api::ActuatorsState,
+    state: &api::ActuatorsState,
+) {
+    if old_state != state {
+        events::publish(api::ActuatorsEvent::StateChanged(api::StateChanged {
+            camera_uuid: *camera_uuid,
+            state: *state,
+        }));
+    }
+}
+
+fn is_recent(instant: Option<Instant>) -> bool {
+    instant.is_some_and(|instant| instant.elapsed() < STALE_TIMEOUT)
+}
+
+/// SERVO_OUTPUT_RAW carries 16 channels per port, so channels 17 to 32 come on port 1
+pub(super) fn servo_output_state(
+    data: &SERVO_OUTPUT_RAW_DATA,
+    channel: api::ServoChannel,
+    normalize: impl Fn(u16) -> Option<f32>,
+) -> Option<f32> {
+    let index = channel as u8 - 1;
+    if data.port != index / 16 {
+        return None;
+    }
+
+    let pwm = match index % 16 {
+        0 => data.servo1_raw,
+        1 => data.servo2_raw,
+        2 => data.servo3_raw,
+        3 => data.servo4_raw,
+        4 => data.servo5_raw,
+        5 => data.servo6_raw,
+        6 => data.servo7_raw,
+        7 => data.servo8_raw,
+        8 => data.servo9_raw,
+        9 => data.servo10_raw,
+        10 => data.servo11_raw,
+        11 => data.servo12_raw,
+        12 => data.servo13_raw,
+        13 => data.servo14_raw,
+        14 => data.servo15_raw,
+        _ => data.servo16_raw,
+    };
+
+    // Zero means the channel has no output
+    if pwm == 0 {
+        return None;
+    }
+
+    normalize(pwm)
+}
+
+/// Maps the PWM of a range output (like the camera focus and zoom) linearly into 0-100%
+fn normalize_range_pwm(pwm: u16, min: u16, max: u16) -> Option<f32> {
+    if min >= max {
+        return None;
+    }
+
+    let pwm = pwm.clamp(min, max);
+
+    Some(100. * (pwm - min) as f32 / (max - min) as f32)
+}
+
+/// Maps the PWM of an angle output (like the mount tilt) into 0-100%, with the trim at 50%
+fn normalize_angle_pwm(pwm: u16, min: u16, trim: u16, max: u16) -> Option<f32> {
+    if min >= max || !(min..=max).contains(&trim) {
+        return None;
+    }
+
+    let pwm = pwm.clamp(min, max);
+
+    let tilt = if pwm <= trim {
+        if trim == min {
+            50.
+        } else {
+            50. * (pwm - min) as f32 / (trim - min) as f32
+        }
+    } else {
+        50. + 50. * (pwm - trim) as f32 / (max - trim) as f32
+    };
+
+    Some(tilt)
+}
+
+/// Maps the mount pitch, in degrees, into 0-100% of the configured pitch range
+fn normalize_pitch(pitch: f32, parameters: &ActuatorsParameters) -> Option<f32> {
+    let min = parameters.tilt_mnt_pitch_min as f32;
+    let max = parameters.tilt_mnt_pitch_max as f32;
+
+    if min >= max {
+        return None;
+    }
+
+    Some(100. * (pitch.clamp(min, max) - min) / (max - min))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_range_pwm() {
+        assert_eq!(normalize_range_pwm(870, 870, 2130), Some(0.));
+        assert_eq!(normalize_range_pwm(1500, 870, 2130), Some(50.));
+        assert_eq!(normalize_range_pwm(2130, 870, 2130), Some(100.));
+        assert_eq!(normalize_range_pwm(500, 870, 2130), Some(0.));
+        assert_eq!(normalize_range_pwm(1500, 2130, 870), None);
+    }
+
+    #[test]
+    fn test_normalize_angle_pwm() {
+        assert_eq!(normalize_angle_pwm(750, 750, 1500, 2250), Some(0.));
+        assert_eq!(normalize_angle_pwm(1500, 750, 1500, 2250), Some(50.));
+        assert_eq!(normalize_angle_pwm(2250, 750, 1500, 2250), Some(100.));
+        assert_eq!(normalize_angle_pwm(1700, 1100, 1700, 1900), Some(50.));
+        assert_eq!(normalize_angle_pwm(1800, 1100, 1700, 1900), Some(75.));
+        assert_eq!(normalize_angle_pwm(1500, 750, 2500, 2250), None);
+    }
+
+    #[test]
+    fn test_freshness_per_camera() {
+        let tracked = |focus_channel, zoom_channel| TrackedCamera {
+            parameters: ActuatorsParameters {
+                focus_channel,
+                zoom_channel,
+                ..Default::default()
+            },
+            state: api::ActuatorsState::default(),
+            last_servo_output: None,
+        };
+
+        let first = Uuid::new_v5(&Uuid::NAMESPACE_DNS, b"first.example.com");
+        let second = Uuid::new_v5(&Uuid::NAMESPACE_DNS, b"second.example.com");
+        let mut tracker = StateTracker::default();
+        tracker.cameras.insert(
+            first,
+            tracked(api::ServoChannel::SERVO10, api::ServoChannel::SERVO11),
+        );
+        tracker.cameras.insert(
+            second,
+            tracked(api::ServoChannel::SERVO17, api::ServoChannel::SERVO18),
+        );
+
+        tracker.track_servo_output(&SERVO_OUTPUT_RAW_DATA {
+            servo10_raw: 1500,
+            ..Default::default()
+        });
+
+        assert!(is_recent(tracker.cameras[&first].last_servo_output));
+        assert_eq!(tracker.cameras[&first].state.focus, Some(50.));
+        assert!(!is_recent(tracker.cameras[&second].last_servo_output));
+        assert_eq!(tracker.cameras[&second].state.focus, None);
+    }
+
+    #[test]
+    fn test_servo_output_state() {
+        let data = SERVO_OUTPUT_RAW_DATA {
+            servo3_raw: 1500,
+            servo16_raw: 2130,
+            ..Default::default()
+        };
+        let normalize = |pwm| normalize_range_pwm(pwm, 870, 2130);
+
+        assert_eq!(
+            servo_output_state(&data, api::ServoChannel::SERVO3, normalize),
+            Some(50.)
+        );
+        assert_eq!(
+            servo_output_state(&data, api::ServoChannel::SERVO16, normalize),
+            Some(100.)
+        );
+        assert_eq!(
+            servo_output_state(&data, api::ServoChannel::SERVO1, normalize),
+            None
+        );
+        assert_eq!(
+            servo_output_state(&data, api::ServoChannel::SERVO19, normalize),
+            None
+        );
+    }
+}
@@ -0,0 +1,325 @@
+//! An in-process autopilot answering just enough of the MAVLink protocol to test the
+//! `MavlinkComponent` end-to-end, without a vehicle.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::Result;
+use indexmap::IndexMap;
+use mavlink::{
+    AsyncMavConnection, MavHeader, Message as _, MessageData,
+    ardupilotmega::{
+        AUTOPILOT_VERSION_DATA, CAMERA_SETTINGS_DATA, COMMAND_ACK_DATA, COMMAND_LONG_DATA,
+        CameraZoomType, HEARTBEAT_DATA, MavAutopilot, MavCmd, MavMessage, MavModeFlag,
+        MavParamType, MavProtocolCapability, MavResult, MavState, MavType, PARAM_VALUE_DATA,
+        SetFocusType,
+    },
+};
+use tracing::*;
+
+use crate::{mavlink::MavlinkComponent, parameters::Parameter};
+
+type MavConnection = Arc<dyn AsyncMavConnection<MavMessage> + Sync + Send>;
+
+const HEARTBEAT_PERIOD: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone)]
+pub struct MockAutopilotConfig {
+    pub system_id: u8,
+    pub component_id: u8,
+    /// Reported in AUTOPILOT_VERSION, which defines the parameter encoding
+    pub capabilities: MavProtocolCapability,
+    /// The parameters table, with the raw values as they go in PARAM_VALUE
+    pub parameters: Vec<(String, f32, MavParamType)>,
+}
+
+impl Default for MockAutopilotConfig {
+    fn default() -> Self {
+        Self {
+            system_id: 1,
+            component_id: mavlink::ardupilotmega::MavComponent::MAV_COMP_ID_AUTOPILOT1 as u8,
+            capabilities: MavProtocolCapability::MAV_PROTOCOL_CAPABILITY_PARAM_ENCODE_C_CAST,
+            parameters: Vec::default(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct MockState {
+    pub parameters: IndexMap<String, (f32, MavParamType)>,
+    /// All the received COMMAND_LONGs, in order
+    pub commands: Vec<COMMAND_LONG_DATA>,
+    pub focus_level: f32,
+    pub zoom_level: f32,
+    /// How many of the next replies are dropped
+    pub drop_next: usize,
+    /// How long each reply is delayed
+    pub delay: Duration,
+}
+
+pub struct MockAutopilot {
+    pub state: Arc<Mutex<MockState>>,
+    receiver_task_handle: tokio::task::JoinHandle<()>,
+    heartbeat_task_handle: tokio::task::JoinHandle<()>,
+}
+
+impl MockAutopilot {
+    /// Listens on a server address, like `udpin:127.0.0.1:14550` or `tcpin:127.0.0.1:5760`
+    #[instrument(level = "debug")]
+    pub async fn spawn(address: &str, config: MockAutopilotConfig) -> Result<Self> {
+        let connection: MavConnection =
+            Arc::from(mavlink::connect_async::<MavMessage>(address).await?);
+
+        let state = Arc::new(Mutex::new(MockState {
+            parameters: config
+                .parameters
+                .iter()
+                .map(|(name, value, param_type)| (name.clone(), (*value, *param_type)))
+                .collect(),
+            ..Default::default()
+        }));
+
+        let header = MavHeader {
+            system_id: config.system_id,
+            component_id: config.component_id,
+            sequence: 0,
+        };
+
+        let receiver_task_handle = tokio::spawn(Self::receiver_task(
+            connection.clone(),
+            header,
+            config.capabilities,
+            state.clone(),
+        ));
+        let heartbeat_task_handle = tokio::spawn(Self::heartbeat_task(connection, header));
+
+        Ok(Self {
+            state,
+            receiver_task_handle,
+            heartbeat_task_handle,
+        })
+    }
+
+    /// Connects over `udp` or `tcp`, on a port the OS assigns so the tests can run in parallel
+    pub async fn connect(protocol: &str, config: MockAutopilotConfig) -> (Self, MavlinkComponent) {
+        let port = match protocol {
+            "tcp" => std::net::TcpListener::bind("127.0.0.1:0")
+                .and_then(|listener| listener.local_addr())
+                .unwrap()
+                .port(),
+            _ => std::net::UdpSocket::bind("127.0.0.1:0")
+                .and_then(|socket| socket.local_addr())
+                .unwrap()
+                .port(),
+        };
+
+        // TCP servers only finish connecting once the client shows up
+        let (mock, component) = tokio::join!(
+            Self::spawn(&format!("{protocol}in:127.0.0.1:{port}"), config),
+            MavlinkComponent::try_new(format!("{protocol}out:127.0.0.1:{port}"), 1, 56),
+        );
+
+        (mock.unwrap(), component.unwrap())
+    }
+
+    pub fn drop_next(&self, replies: usize) {
+        self.state.lock().unwrap().drop_next = replies;
+    }
+
+    pub fn set_delay(&self, delay: Duration) {
+        self.state.lock().unwrap().delay = delay;
+    }
+
+    pub fn param(&self, name: &str) -> Option<f32> {
+        self.state
+            .lock()
+            .unwrap()
+            .parameters
+            .get(name)
+            .map(|(value, _)| *value)
+    }
+
+    pub fn commands(&self) -> Vec<COMMAND_LONG_DATA> {
+        self.state.lock().unwrap().commands.clone()
+    }
+
+    async fn receiver_task(
+        connection: MavConnection,
+        header: MavHeader,
+        capabilities: MavProtocolCapability,
+        state: Arc<Mutex<MockState>>,
+    ) {
+        loop {
+            let (_header, message) = match connection.recv().await {
+                Ok(inner) => inner,
+                Err(error) => {
+                    trace!("Mock failed receiving message: {error:?}");
+                    continue;
+                }
+            };
+
+            let (replies, delay) = {
+                let mut state = state.lock().unwrap();
+
+                let replies = handle_message(&mut state, capabilities, &message);
+                if replies.is_empty() {
+                    continue;
+                }
+
+                if state.drop_next > 0 {
+                    state.drop_next -= 1;
+                    debug!("Mock dropping replies to {:?}", message.message_name());
+                    continue;
+                }
+
+                (replies, state.delay)
+            };
+
+            tokio::spawn({
+                let connection = connection.clone();
+
+                async move {
+                    tokio::time::sleep(delay).await;
+
+                    for reply in replies {
+                        if let Err(error) = connection.send(&header, &reply).await {
+                            warn!("Mock failed sending reply: {error:?}");
+                        }
+                    }
+                }
+            });
+        }
+    }
+
+    async fn heartbeat_task(connection: MavConnection, header: MavHeader) {
+        let heartbeat = MavMessage::HEARTBEAT(HEARTBEAT_DATA {
+            custom_mode: 0,
+            mavtype: MavType::MAV_TYPE_SUBMARINE,
+            autopilot: MavAutopilot::MAV_AUTOPILOT_ARDUPILOTMEGA,
+            base_mode: MavModeFlag::empty(),
+            system_status: MavState::MAV_STATE_STANDBY,
+            mavlink_version: 3,
+        });
+
+        loop {
+            // Fails until a client shows up on server connections, which is fine
+            let _ = connection.send(&header, &heartbeat).await;
+
+            tokio::time::sleep(HEARTBEAT_PERIOD).await;
+        }
+    }
+}
+
+impl Drop for MockAutopilot {
+    fn drop(&mut self) {
+        self.receiver_task_handle.abort();
+        self.heartbeat_task_handle.abort();
+    }
+}
+
+/// Updates the mock state from a received message, returning the replies
+fn handle_message(
+    state: &mut MockState,
+    capabilities: MavProtocolCapability,
+    message: &MavMessage,
+) -> Vec<MavMessage> {
+    match message {
+        MavMessage::AUTOPILOT_VERSION_REQUEST(_) => vec![autopilot_version(capabilities)],
+        MavMessage::COMMAND_LONG(data) => {
+            state.commands.push(data.clone());
+
+            let mut replies = vec![MavMessage::COMMAND_ACK(COMMAND_ACK_DATA {
+                command: data.command,
+                result: MavResult::MAV_RESULT_ACCEPTED,
+                ..Default::default()
+            })];
+
+            match data.command {
+                MavCmd::MAV_CMD_REQUEST_MESSAGE
+                    if data.param1 as u32 == CAMERA_SETTINGS_DATA::ID =>
+                {
+                    replies.push(camera_settings(state));
+                }
+                MavCmd::MAV_CMD_REQUEST_MESSAGE
+                    if data.param1 as u32 == AUTOPILOT_VERSION_DATA::ID =>
+                {
+                    replies.push(autopilot_version(capabilities));
+                }
+                MavCmd::MAV_CMD_REQUEST_AUTOPILOT_CAPABILITIES => {
+                    replies.push(autopilot_version(capabilities));
+                }
+                MavCmd::MAV_CMD_SET_CAMERA_FOCUS
+                    if data.param1 == SetFocusType::FOCUS_TYPE_RANGE as u8 as f32 =>
+                {
+                    state.focus_level = data.param2;
+                }
+                MavCmd::MAV_CMD_SET_CAMERA_ZOOM
+                    if data.param1 == CameraZoomType::ZOOM_TYPE_RANGE as u8 as f32 =>
+                {
+                    state.zoom_level = data.param2;
+                }
+                _ => (),
+            }
+
+            replies
+        }
+        MavMessage::PARAM_REQUEST_LIST(_) => (0..state.parameters.len())
+            .filter_map(|index| param_value(state, index))
+            .collect(),
+        MavMessage::PARAM_REQUEST_READ(data) => {
+            let index = if data.param_index < 0 {
+                state
+                    .parameters
+                    .get_index_of(&Parameter::param_id_to_name(data.param_id))
+            } else {
+                Some(data.param_index as usize)
+            };
+
+            index
+                .and_then(|index| param_value(state, index))
+                .into_iter()
+                .collect()
+        }
+        MavMessage::PARAM_SET(data) => {
+            let name = Parameter::param_id_to_name(data.param_id);
+
+            // Just like ArduPilot, unknown parameters are ignored
+            let Some(index) = state.parameters.get_index_of(&name) else {
+                return vec![];
+            };
+            state.parameters[index] = (data.param_value, data.param_type);
+
+            param_value(state, index).into_iter().collect()
+        }
+        _ => vec![],
+    }
+}
+
+fn param_value(state: &MockState, index: usize) -> Option<MavMessage> {
+    let (name, (value, param_type)) = state.parameters.get_index(index)?;
+
+    Some(MavMessage::PARAM_VALUE(PARAM_VALUE_DATA {
+        param_value: *value,
+        param_count: state.parameters.len() as u16,
+        param_index: index as u16,
+        param_id: Parameter::param_name_to_id(name),
+        param_type: *param_type,
+    }))
+}
+
+fn autopilot_version(capabilities: MavProtocolCapability) -> MavMessage {
+    MavMessage::AUTOPILOT_VERSION(AUTOPILOT_VERSION_DATA {
+        capabilities,
+        ..Default::default()
+    })
+}
+
+fn camera_settings(state: &MockState) -> MavMessage {
+    MavMessage::CAMERA_SETTINGS(CAMERA_SETTINGS_DATA {
+        focusLevel: state.focus_level,
+        zoomLevel: state.zoom_level,
+        ..Default::default()
+    })
+}
@@ -1,7 +1,9 @@
 mod connection;
+#[cfg(test)]
+pub(crate) mod mock;
 pub mod parameters;
 
-use std::sync::Arc;
+use std::{collections::HashSet, sync::Arc};
 
 use anyhow::{Context, Result, anyhow};
 use indexmap::IndexMap;
@@ -13,11 +15,9 @@ use settings::CameraID;
 use tokio::sync::{RwLock, broadcast};
 use tracing::*;
 
+pub(crate) use crate::mavlink::connection::Message;
 use crate::{
-    mavlink::{
-        connection::{Connection, Message},
-        parameters::ParamEncodingType,
-    },
+    mavlink::{connection::Connection, parameters::ParamEncodingType},
     parameters::{ParamType, Parameter},
 };
 
@@ -297,6 +297,11 @@ impl MavlinkComponent {
         Ok(())
     }
 
+    /// Subscribes to all the messages going through the MAVLink network
+    pub(crate) async fn get_receiver(&self) -> broadcast::Receiver<Message> {
+        self.inner.read().await.get_receiver().await
+    }
+
     pub async fn request_camera_settings(
         &self,
         camera_id: CameraID,
@@ -324,6 +329,28 @@ impl MavlinkComponent {
         wait_camera_settings_handle.await?
     }
 
+    /// Asks the autopilot to stream a message at the given interval
+    #[instrument(level = "debug", skip(self))]
+    pub async fn set_message_interval(
+        &self,
+        message_id: u32,
+        interval: std::time::Duration,
+    ) -> Result<()> {
+        let target_system = { self.inner.read().await.system_id };
+        let target_component = mavlink::ardupilotmega::MavComponent::MAV_COMP_ID_AUTOPILOT1 as u8;
+
+        self.send_command(COMMAND_LONG_DATA {
+            command: MavCmd::MAV_CMD_SET_MESSAGE_INTERVAL,
+            target_system,
+            target_component,
+            confirmation: 0,
+            param1: message_id as f32,
+            param2: interval.as_micros() as f32,
+            ..Default::default()
+        })
+        .await
+    }
+
     pub async fn wait_camera_settings(
         inner: Arc<RwLock<ComponentInner>>,
     ) -> Result<CAMERA_SETTINGS_DATA> {
@@ -385,6 +412,8 @@ pub(crate) struct ComponentInner {
     pub component_id: u8,
     pub encoding: ParamEncodingType,
     pub parameters: IndexMap<String, Parameter>,
+    /// The parameters RadCam has set, whose changes are published as events
+    pub owned_parameters: HashSet<String>,
     connection: Connection,
 }
 
@@ -448,6 +477,7 @@ impl ComponentInner {
             component_id,
             encoding: ParamEncodingType::default(),
             parameters: IndexMap::with_capacity(2048),
+            owned_parameters: HashSet::default(),
             connection,
         })
     }
@@ -462,3 +492,132 @@ impl ComponentInner {
         self.connection.get_receiver()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use mavlink::ardupilotmega::{MavParamType, MavProtocolCapability};
+
+    use super::*;
+    use crate::mavlink::mock::{MockAutopilot, MockAutopilotConfig};
+
+    fn config() -> MockAutopilotConfig {
+        MockAutopilotConfig {
+            parameters: vec![
+                (
+                    "SCR_ENABLE".to_string(),
+                    1.,
+                    MavParamType::MAV_PARAM_TYPE_INT8,
+                ),
+                (
+                    "SERVO9_FUNCTION".to_string(),
+                    0.,
+                    MavParamType::MAV_PARAM_TYPE_INT16,
+                ),
+                (
+                    "RCAM1_GAIN".to_string(),
+                    0.5,
+                    MavParamType::MAV_PARAM_TYPE_REAL32,
+                ),
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_fetches_all_params_over_udp() {
+        let (_mock, component) = MockAutopilot::connect("udp", config()).await;
+
+        assert!(matches!(
+            component.encoding().await,
+            ParamEncodingType::CCast
+        ));
+
+        let gain = component.get_cached_param("RCAM1_GAIN").await.unwrap();
+        assert_eq!(gain.value, ParamType::REAL32(0.5));
+
+        let function = component.get_cached_param("SERVO9_FUNCTION").await.unwrap();
+        assert_eq!(function.value, ParamType::INT16(0));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_detects_bytewise_encoding_over_tcp() {
+        let config = MockAutopilotConfig {
+            capabilities: MavProtocolCapability::MAV_PROTOCOL_CAPABILITY_PARAM_ENCODE_BYTEWISE,
+            parameters: vec![(
+                "RCAM1_GAIN".to_string(),
+                0.5,
+                MavParamType::MAV_PARAM_TYPE_REAL32,
+            )],
+            ..Default::default()
+        };
+        let (_mock, component) = MockAutopilot::connect("tcp", config).await;
+
+        assert!(matches!(
+            component.encoding().await,
+            ParamEncodingType::ByteWise
+        ));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_sets_param() {
+        let (mock, component) = MockAutopilot::connect("udp", config()).await;
+        let encoding = component.encoding().await;
+
+        let mut param = component.get_param("SERVO9_FUNCTION", true).await.unwrap();
+        param
+            .value
+            .set_value(ParamType::INT16(180), encoding)
+            .unwrap();
+        component.set_param(param).await.unwrap();
+
+        assert_eq!(mock.param("SERVO9_FUNCTION"), Some(180.));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_retries_dropped_command() {
+        let (mock, component) = MockAutopilot::connect("udp", config()).await;
+
+        mock.drop_next(1);
+        component
+            .send_command(COMMAND_LONG_DATA {
+                command: MavCmd::MAV_CMD_DO_SET_SERVO,
+                param1: 9.,
+                param2: 1500.,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let commands = mock.commands();
+        assert_eq!(commands.len(), 2);
+        assert!(
+            commands
+                .iter()
+                .all(|command| command.command == MavCmd::MAV_CMD_DO_SET_SERVO)
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_camera_settings_with_delay() {
+        let (mock, component) = MockAutopilot::connect("udp", config()).await;
+
+        mock.set_delay(Duration::from_millis(500));
+        component
+            .send_command(COMMAND_LONG_DATA {
+                command: MavCmd::MAV_CMD_SET_CAMERA_ZOOM,
+                param1: mavlink::ardupilotmega::CameraZoomType::ZOOM_TYPE_RANGE as u8 as f32,
+                param2: 42.,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let camera_settings = component
+            .request_camera_settings(CameraID::CAM1)
+            .await
+            .unwrap();
+        assert_eq!(camera_settings.zoomLevel, 42.);
+    }
+}
@@ -10,6 +10,7 @@ use tokio::sync::{RwLock, broadcast};
 use tracing::*;
 
 use crate::{
+    api, events,
     mavlink::{ComponentInner, MavlinkComponent, connection::Message},
     parameters::Parameter,
 };
@@ -275,9 +276,11 @@ impl MavlinkComponent {
                 }
             };
 
-            inner
-                .write()
-                .await
+            let mut inner_guard = inner.write().await;
+            let is_owned = inner_guard.owned_parameters.contains(&parameter.name);
+
+            let mut has_changed = false;
+            inner_guard
                 .parameters
                 .entry(parameter.name.clone())
                 .and_modify(|v| {
@@ -286,16 +289,42 @@ impl MavlinkComponent {
                             "Parameter {:?} updated from {:?} to {:?}",
                             v.name, v.value, parameter.value,
                         );
+                        has_changed = true;
                     }
                     *v = parameter.clone()
                 })
                 .or_insert_with(|| {
                     trace!("New parameter added: {parameter:?}");
-                    parameter
+                    parameter.clone()
                 });
+            drop(inner_guard);
+
+            if is_owned && has_changed {
+                events::publish(api::ActuatorsEvent::ParameterChanged(
+                    api::ParameterChanged {
+                        name: parameter.name,
+                        value: parameter.value.as_f64(),
+                    },
+                ));
+            }
         }
     }
 
+    /// Gets a parameter from the cache only, without requesting it from the autopilot
+    pub async fn get_cached_param(&self, param_name: &str) -> Option<Parameter> {
+        self.inner.read().await.parameters.get(param_name).cloned()
+    }
+
+    /// Publishes the changes of these parameters, as if RadCam had set them
+    #[instrument(level = "debug", skip(self, param_names))]
+    pub async fn own_parameters(&self, param_names: impl IntoIterator<Item = String>) {
+        self.inner
+            .write()
+            .await
+            .owned_parameters
+            .extend(param_names);
+    }
+
     #[instrument(level = "debug", skip(self))]
     pub async fn get_param(&self, param_name: &str, skip_cache: bool) -> Result<Parameter> {
         Self::get_param_inner(self.inner.clone(), param_name, skip_cache).await
@@ -472,6 +501,12 @@ impl MavlinkComponent {
             encoding = inner_guard.encoding;
         }
 
+        inner
+            .write()
+            .await
+            .owned_parameters
+            .insert(parameter.name.clone());
+
         let header = MavHeader {
             system_id: this_system,
             component_id: this_component,
@@ -1,4 +1,6 @@
 pub mod api;
+mod events;
+mod interpolation;
 mod manager;
 mod mavlink;
 pub mod parameters;
@@ -7,14 +9,18 @@ mod settings_translations;
 
 use anyhow::{Context, Result};
 use axum::Json;
+use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 use tracing::*;
+use uuid::Uuid;
 
 pub use manager::init;
 
 use crate::{
     manager::MANAGER,
-    parameters::{ActuatorsParameters, CLOSEST_POINTS, FURTHEST_POINTS},
+    parameters::{
+        ActuatorsParameters, CLOSEST_POINTS, DISTANCE_POINTS, FURTHEST_POINTS, SCRIPT_BUTTONS,
+    },
 };
 
 pub use routes::router;
@@ -24,7 +30,9 @@ struct CameraActuators {
     pub parameters: ActuatorsParameters,
     pub closest_points: api::FocusZoomPoints,
     pub furthest_points: api::FocusZoomPoints,
+    pub distance_points: api::DistanceFocusPoints,
     pub state: api::ActuatorsState,
+    pub script_buttons: Vec<api::ScriptButton>,
 }
 
 impl Default for CameraActuators {
@@ -33,11 +41,24 @@ impl Default for CameraActuators {
             parameters: ActuatorsParameters::default(),
             closest_points: api::FocusZoomPoints(CLOSEST_POINTS.to_vec()),
             furthest_points: api::FocusZoomPoints(FURTHEST_POINTS.to_vec()),
+            distance_points: api::DistanceFocusPoints(DISTANCE_POINTS.to_vec()),
             state: api::ActuatorsState::default(),
+            script_buttons: SCRIPT_BUTTONS.to_vec(),
         }
     }
 }
 
+/// The image profiles of a camera, by name
+#[derive(Debug, Clone, Default, PartialEq)]
+struct CameraImageProfiles {
+    pub profiles: IndexMap<String, api::ImageProfile>,
+    /// The stored profiles that don't match the current camera protocol, kept as they are so
+    /// they aren't lost
+    pub invalid_profiles: IndexMap<String, settings::ImageProfile>,
+    pub active_profile: Option<String>,
+    pub reapply_policy: api::ReapplyPolicy,
+}
+
 #[instrument(level = "debug")]
 pub(crate) async fn control_inner(
     actuators_control: Json<api::ActuatorsControl>,
@@ -123,6 +144,194 @@ pub(crate) async fn control_inner(
 
             serde_json::to_value(config)?
         }
+        Action::GetPresets => {
+            let manager = MANAGER.get().context("Not available")?.read().await;
+
+            let presets = manager.get_presets(&actuators_control.camera_uuid);
+
+            serde_json::to_value(presets)?
+        }
+        Action::SetPreset(preset) => {
+            let mut manager = MANAGER.get().context("Not available")?.write().await;
+
+            let presets = manager
+                .set_preset(&actuators_control.camera_uuid, preset)
+                .await?;
+
+            serde_json::to_value(presets)?
+        }
+        Action::DeletePreset(api::PresetName { name }) => {
+            let mut manager = MANAGER.get().context("Not available")?.write().await;
+
+            let presets = manager
+                .delete_preset(&actuators_control.camera_uuid, name)
+                .await?;
+
+            serde_json::to_value(presets)?
+        }
+        Action::RecallPreset(api::PresetName { name }) => {
+            let preset = MANAGER
+                .get()
+                .context("Not available")?
+                .write()
+                .await
+                .recall_preset(&actuators_control.camera_uuid, name)
+                .await?;
+
+            recall_image_adjustments(&actuators_control.camera_uuid, &preset).await?;
+
+            serde_json::to_value(preset)?
+        }
+        Action::GetJoystickButtons => {
+            let manager = MANAGER.get().context("Not available")?.read().await;
+
+            let buttons = manager.get_joystick_buttons().await;
+
+            serde_json::to_value(buttons)?
+        }
+        Action::ProposeJoystickMapping => {
+            let manager = MANAGER.get().context("Not available")?.read().await;
+
+            let mapping = manager
+                .propose_joystick_mapping(&actuators_control.camera_uuid)
+                .await?;
+
+            serde_json::to_value(mapping)?
+        }
+        Action::SetJoystickMapping(mapping) => {
+            let mut manager = MANAGER.get().context("Not available")?.write().await;
+
+            let mapping = manager
+                .update_joystick_mapping(&actuators_control.camera_uuid, mapping, false)
+                .await?;
+
+            serde_json::to_value(mapping)?
+        }
+        Action::StartZoom(api::ActuatorRate { rate }) => {
+            let mut manager = MANAGER.get().context("Not available")?.write().await;
+
+            manager
+                .start_zoom(&actuators_control.camera_uuid, *rate)
+                .await?;
+
+            serde_json::to_value({})?
+        }
+        Action::StartFocus(api::ActuatorRate { rate }) => {
+            let mut manager = MANAGER.get().context("Not available")?.write().await;
+
+            manager
+                .start_focus(&actuators_control.camera_uuid, *rate)
+                .await?;
+
+            serde_json::to_value({})?
+        }
+        Action::KeepAlive => {
+            manager::keep_alive(&actuators_control.camera_uuid);
+
+            serde_json::to_value({})?
+        }
+        Action::Stop => {
+            let mut manager = MANAGER.get().context("Not available")?.write().await;
+
+            let state = manager.stop_motion(&actuators_control.camera_uuid).await?;
+
+            serde_json::to_value(state)?
+        }
+        Action::GetOsdConfig => {
+            let manager = MANAGER.get().context("Not available")?.read().await;
+
+            let config = manager.get_osd_config(&actuators_control.camera_uuid);
+
+            serde_json::to_value(config)?
+        }
+        Action::SetOsdConfig(config) => {
+            let mut manager = MANAGER.get().context("Not available")?.write().await;
+
+            let config = manager
+                .update_osd_config(&actuators_control.camera_uuid, config)
+                .await?;
+
+            serde_json::to_value(config)?
+        }
+        Action::GetImageProfiles => {
+            let manager = MANAGER.get().context("Not available")?.read().await;
+
+            let profiles = manager.get_image_profiles(&actuators_control.camera_uuid);
+
+            serde_json::to_value(profiles)?
+        }
+        Action::SetImageProfile(profile) => {
+            let mut manager = MANAGER.get().context("Not available")?.write().await;
+
+            let profiles = manager
+                .set_image_profile(&actuators_control.camera_uuid, profile)
+                .await?;
+
+            serde_json::to_value(profiles)?
+        }
+        Action::DeleteImageProfile(api::ImageProfileName { name }) => {
+            let mut manager = MANAGER.get().context("Not available")?.write().await;
+
+            let profiles = manager
+                .delete_image_profile(&actuators_control.camera_uuid, name)
+                .await?;
+
+            serde_json::to_value(profiles)?
+        }
+        Action::ApplyImageProfile(api::ImageProfileName { name }) => {
+            let profile = MANAGER
+                .get()
+                .context("Not available")?
+                .read()
+                .await
+                .get_image_profile(&actuators_control.camera_uuid, name)?;
+
+            apply_image_profile(&actuators_control.camera_uuid, &profile).await?;
+
+            let profiles = MANAGER
+                .get()
+                .context("Not available")?
+                .write()
+                .await
+                .set_active_image_profile(&actuators_control.camera_uuid, name)
+                .await?;
+
+            serde_json::to_value(profiles)?
+        }
+        Action::SetReapplyPolicy(api::ReapplyPolicyConfig { policy }) => {
+            let mut manager = MANAGER.get().context("Not available")?.write().await;
+
+            let profiles = manager
+                .set_reapply_policy(&actuators_control.camera_uuid, *policy)
+                .await?;
+
+            serde_json::to_value(profiles)?
+        }
+        Action::GetRules => {
+            let manager = MANAGER.get().context("Not available")?.read().await;
+
+            let rules = manager.get_rules(&actuators_control.camera_uuid);
+
+            serde_json::to_value(rules)?
+        }
+        Action::SetRule(rule) => {
+            let mut manager = MANAGER.get().context("Not available")?.write().await;
+
+            let rules = manager
+                .set_rule(&actuators_control.camera_uuid, rule)
+                .await?;
+
+            serde_json::to_value(rules)?
+        }
+        Action::DeleteRule(api::RuleName { name }) => {
+            let mut manager = MANAGER.get().context("Not available")?.write().await;
+
+            let rules = manager
+                .delete_rule(&actuators_control.camera_uuid, name)
+                .await?;
+
+            serde_json::to_value(rules)?
+        }
     };
 
     settings::MANAGER
@@ -136,3 +345,84 @@ pub(crate) async fn control_inner(
 
     Ok(res)
 }
+
+/// The current state of each camera, empty if the autopilot is not available
+#[instrument(level = "debug")]
+pub async fn states() -> IndexMap<Uuid, api::ActuatorsState> {
+    let Some(manager) = MANAGER.get() else {
+        return IndexMap::default();
+    };
+
+    manager.read().await.get_states().await
+}
+
+/// The presets of each camera, empty if the autopilot is not available
+#[instrument(level = "debug")]
+pub async fn presets() -> IndexMap<Uuid, Vec<api::Preset>> {
+    let Some(manager) = MANAGER.get() else {
+        return IndexMap::default();
+    };
+
+    manager
+        .read()
+        .await
+        .settings
+        .presets
+        .iter()
+        .map(|(camera_uuid, presets)| (*camera_uuid, presets.values().cloned().collect()))
+        .collect()
+}
+
+/// The autopilot `SYSTEM_TIME`, if it knows the time
+#[instrument(level = "debug")]
+pub async fn autopilot_time() -> Option<chrono::DateTime<chrono::Utc>> {
+    manager::autopilot_time().await
+}
+
+#[instrument(level = "debug")]
+pub(crate) async fn recall_image_adjustments(
+    camera_uuid: &Uuid,
+    preset: &api::Preset,
+) -> Result<()> {
+    use radcam_commands::{Action, CameraControl};
+
+    // Presets stored before the triggers were left out may still have them
+    let actions = [
+        preset.image_adjustment.clone().map(|image_adjustment| {
+            Action::SetImageAdjustment(image_adjustment.without_triggers())
+        }),
+        preset
+            .image_adjustment_ex
+            .clone()
+            .map(|image_adjustment_ex| {
+                Action::SetImageAdjustmentEx(image_adjustment_ex.without_triggers())
+            }),
+    ];
+
+    for action in actions.into_iter().flatten() {
+        radcam_commands::control_inner(Json(CameraControl {
+            camera_uuid: *camera_uuid,
+            action,
+        }))
+        .await
+        .with_context(|| format!("Failed recalling preset {:?}", preset.name))?;
+    }
+
+    Ok(())
+}
+
+/// Sends the profile to the camera
+#[instrument(level = "debug")]
+pub(crate) async fn apply_image_profile(
+    camera_uuid: &Uuid,
+    profile: &api::ImageProfile,
+) -> Result<()> {
+    radcam_commands::camera_config::apply_settings(
+        camera_uuid,
+        &profile.video_parameters,
+        profile.image_adjustment.as_ref(),
+        profile.image_adjustment_ex.as_ref(),
+    )
+    .await
+    .with_context(|| format!("Failed applying image profile {:?}", profile.name))
+}
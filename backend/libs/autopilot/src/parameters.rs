@@ -29,6 +29,21 @@ pub enum ParamType {
 }
 
 impl ParamType {
+    pub fn as_f64(&self) -> f64 {
+        match *self {
+            ParamType::UINT8(v) => v as f64,
+            ParamType::INT8(v) => v as f64,
+            ParamType::UINT16(v) => v as f64,
+            ParamType::INT16(v) => v as f64,
+            ParamType::UINT32(v) => v as f64,
+            ParamType::INT32(v) => v as f64,
+            ParamType::UINT64(v) => v as f64,
+            ParamType::INT64(v) => v as f64,
+            ParamType::REAL32(v) => v as f64,
+            ParamType::REAL64(v) => v,
+        }
+    }
+
     pub fn encode(&self, encoding: ParamEncodingType) -> Result<f32> {
         use ParamEncodingType::*;
 
@@ -210,6 +225,9 @@ pub struct ActuatorsParameters {
     pub script_channel_trim: u16,
     pub script_channel_max: u16,
     pub enable_focus_and_zoom_correlation: bool,
+    pub focus_interpolation: api::InterpolationMode,
+    pub enable_rangefinder_focus: bool,
+    pub rangefinder_orientation: api::RangefinderOrientation,
 
     // Zoom channel parameters
     pub zoom_channel: api::ServoChannel,
@@ -250,6 +268,9 @@ impl Default for ActuatorsParameters {
             script_channel_max: 2130,
             script_function: api::ScriptFunction::SCRIPT1,
             enable_focus_and_zoom_correlation: true,
+            focus_interpolation: api::InterpolationMode::Linear,
+            enable_rangefinder_focus: false,
+            rangefinder_orientation: api::RangefinderOrientation::Forward,
 
             // Zoom - used as Zoom output, controlled by the ZoomFocus.lua script
             zoom_channel: api::ServoChannel::SERVO11,
@@ -299,6 +320,15 @@ impl From<ActuatorsParametersConfig> for ActuatorsParameters {
             enable_focus_and_zoom_correlation: value
                 .enable_focus_and_zoom_correlation
                 .unwrap_or(default.enable_focus_and_zoom_correlation),
+            focus_interpolation: value
+                .focus_interpolation
+                .unwrap_or(default.focus_interpolation),
+            enable_rangefinder_focus: value
+                .enable_rangefinder_focus
+                .unwrap_or(default.enable_rangefinder_focus),
+            rangefinder_orientation: value
+                .rangefinder_orientation
+                .unwrap_or(default.rangefinder_orientation),
             zoom_channel: value.zoom_channel.unwrap_or(default.zoom_channel),
             zoom_channel_min: value.zoom_channel_min.unwrap_or(default.zoom_channel_min),
             zoom_channel_trim: value.zoom_channel_trim.unwrap_or(default.zoom_channel_trim),
@@ -354,6 +384,26 @@ pub enum CameraType {
     Servo = 1,
 }
 
+/// The ArduSub script buttons the Lua script forwards to us
+pub const SCRIPT_BUTTONS: &[api::ScriptButton] = &[
+    api::ScriptButton {
+        button: 1,
+        action: api::ScriptButtonAction::ZoomIn,
+    },
+    api::ScriptButton {
+        button: 2,
+        action: api::ScriptButtonAction::ZoomOut,
+    },
+    api::ScriptButton {
+        button: 3,
+        action: api::ScriptButtonAction::FocusNear,
+    },
+    api::ScriptButton {
+        button: 4,
+        action: api::ScriptButtonAction::FocusFar,
+    },
+];
+
 pub const CLOSEST_POINTS: &[api::FocusZoomPoint] = &[
     api::FocusZoomPoint {
         zoom: 870,
@@ -423,3 +473,27 @@ pub const FURTHEST_POINTS: &[api::FocusZoomPoint] = &[
         focus: 2155,
     },
 ];
+
+/// Rough defaults for the rangefinder focus, meant to be calibrated for each setup
+pub const DISTANCE_POINTS: &[api::DistanceFocusPoint] = &[
+    api::DistanceFocusPoint {
+        distance: 20,
+        position: 0.0,
+    },
+    api::DistanceFocusPoint {
+        distance: 50,
+        position: 0.35,
+    },
+    api::DistanceFocusPoint {
+        distance: 100,
+        position: 0.6,
+    },
+    api::DistanceFocusPoint {
+        distance: 300,
+        position: 0.85,
+    },
+    api::DistanceFocusPoint {
+        distance: 1000,
+        position: 1.0,
+    },
+];
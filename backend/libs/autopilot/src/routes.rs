@@ -1,18 +1,29 @@
-use axum::{Json, Router, response::IntoResponse, routing::post};
+use axum::{
+    Json, Router,
+    extract::{WebSocketUpgrade, ws::WebSocket},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
 use reqwest::StatusCode;
 use tower_http::{cors::CorsLayer, trace::TraceLayer};
 use tracing::*;
 
-use crate::{api, control_inner};
+use crate::{api, control_inner, events};
 
 pub fn router() -> Router {
     Router::new()
         .route("/control", post(control))
+        .route("/jobs", post(start_job))
+        .route("/events", get(events_websocket_handler))
         .layer(CorsLayer::permissive())
         .layer(TraceLayer::new_for_http())
 }
 
 pub async fn control(actuators_control: Json<api::ActuatorsControl>) -> impl IntoResponse {
+    if runs_as_job(&actuators_control.action) {
+        return start_job(actuators_control).await.into_response();
+    }
+
     let res = match control_inner(actuators_control).await {
         Ok(res) => res,
         Err(error) => {
@@ -23,3 +34,52 @@ pub async fn control(actuators_control: Json<api::ActuatorsControl>) -> impl Int
 
     (StatusCode::OK, res.to_string()).into_response()
 }
+
+/// Whether the action calibrates the actuators, which takes too long to be answered in its own
+/// request
+fn runs_as_job(action: &api::Action) -> bool {
+    match action {
+        api::Action::ResetActuatorsConfig => true,
+        api::Action::SetActuatorsConfig(config) => {
+            config.closest_points.is_some()
+                || config.furthest_points.is_some()
+                || config.distance_points.is_some()
+        }
+        _ => false,
+    }
+}
+
+/// Runs the action as a job, like the configurations that calibrate the actuators and reboot the
+/// autopilot. They can't be cancelled, as that could leave the autopilot half configured
+pub async fn start_job(Json(actuators_control): Json<api::ActuatorsControl>) -> impl IntoResponse {
+    let name = utils::jobs::action_name(&actuators_control.action);
+    let camera_uuid = actuators_control.camera_uuid;
+
+    let job = utils::jobs::spawn(&name.clone(), Some(camera_uuid), false, |job| async move {
+        job.progress(None, format!("Running {name}")).await;
+
+        control_inner(Json(actuators_control)).await
+    })
+    .await;
+
+    (StatusCode::ACCEPTED, serde_json::to_string(&job).unwrap()).into_response()
+}
+
+async fn events_websocket_handler(ws: WebSocketUpgrade) -> Response {
+    ws.on_upgrade(events_websocket_connection)
+}
+
+#[instrument(level = "debug", skip_all)]
+async fn events_websocket_connection(socket: WebSocket) {
+    let receiver = events::subscribe();
+
+    // Starts the client with the current state of each camera
+    let states = crate::states()
+        .await
+        .into_iter()
+        .map(|(camera_uuid, state)| {
+            api::ActuatorsEvent::StateChanged(api::StateChanged { camera_uuid, state })
+        });
+
+    utils::websocket::forward_broadcast(socket, "Events", states, receiver).await;
+}
@@ -1,4 +1,8 @@
-use crate::{CameraActuators, api, parameters};
+use indexmap::IndexMap;
+use serde::{Serialize, de::DeserializeOwned};
+use tracing::*;
+
+use crate::{CameraActuators, CameraImageProfiles, api, parameters};
 
 impl From<&settings::CameraActuatorsSettings> for CameraActuators {
     fn from(value: &settings::CameraActuatorsSettings) -> Self {
@@ -6,7 +10,13 @@ impl From<&settings::CameraActuatorsSettings> for CameraActuators {
             parameters: (&value.parameters).into(),
             closest_points: (&value.calibration.closest_points).into(),
             furthest_points: (&value.calibration.furthest_points).into(),
+            distance_points: if value.calibration.distance_points.is_empty() {
+                api::DistanceFocusPoints(parameters::DISTANCE_POINTS.to_vec())
+            } else {
+                (&value.calibration.distance_points).into()
+            },
             state: (&value.state).into(),
+            script_buttons: value.script_buttons.iter().map(|v| v.into()).collect(),
         }
     }
 }
@@ -17,8 +27,10 @@ impl From<&CameraActuators> for settings::CameraActuatorsSettings {
             calibration: settings::Calibration {
                 closest_points: (&value.closest_points).into(),
                 furthest_points: (&value.furthest_points).into(),
+                distance_points: (&value.distance_points).into(),
             },
             state: (&value.state).into(),
+            script_buttons: value.script_buttons.iter().map(|v| v.into()).collect(),
         }
     }
 }
@@ -38,6 +50,9 @@ impl From<&settings::ActuatorsParameters> for parameters::ActuatorsParameters {
             script_channel_trim: value.script_channel_trim,
             script_channel_max: value.script_channel_max,
             enable_focus_and_zoom_correlation: value.enable_focus_and_zoom_correlation,
+            focus_interpolation: (&value.focus_interpolation).into(),
+            enable_rangefinder_focus: value.enable_rangefinder_focus,
+            rangefinder_orientation: (&value.rangefinder_orientation).into(),
             zoom_channel: (&value.zoom_channel).into(),
             zoom_channel_min: value.zoom_channel_min,
             zoom_channel_trim: value.zoom_channel_trim,
@@ -68,6 +83,9 @@ impl From<&parameters::ActuatorsParameters> for settings::ActuatorsParameters {
             script_channel_trim: value.script_channel_trim,
             script_channel_max: value.script_channel_max,
             enable_focus_and_zoom_correlation: value.enable_focus_and_zoom_correlation,
+            focus_interpolation: (&value.focus_interpolation).into(),
+            enable_rangefinder_focus: value.enable_rangefinder_focus,
+            rangefinder_orientation: (&value.rangefinder_orientation).into(),
             zoom_channel: (&value.zoom_channel).into(),
             zoom_channel_min: value.zoom_channel_min,
             zoom_channel_trim: value.zoom_channel_trim,
@@ -223,6 +241,50 @@ impl From<&api::CameraID> for settings::CameraID {
     }
 }
 
+impl From<&settings::InterpolationMode> for api::InterpolationMode {
+    fn from(value: &settings::InterpolationMode) -> Self {
+        match value {
+            settings::InterpolationMode::Linear => Self::Linear,
+            settings::InterpolationMode::MonotoneCubic => Self::MonotoneCubic,
+            settings::InterpolationMode::CatmullRom => Self::CatmullRom,
+        }
+    }
+}
+impl From<&api::InterpolationMode> for settings::InterpolationMode {
+    fn from(value: &api::InterpolationMode) -> Self {
+        match value {
+            api::InterpolationMode::Linear => Self::Linear,
+            api::InterpolationMode::MonotoneCubic => Self::MonotoneCubic,
+            api::InterpolationMode::CatmullRom => Self::CatmullRom,
+        }
+    }
+}
+
+impl From<&settings::RangefinderOrientation> for api::RangefinderOrientation {
+    fn from(value: &settings::RangefinderOrientation) -> Self {
+        match value {
+            settings::RangefinderOrientation::Forward => Self::Forward,
+            settings::RangefinderOrientation::Right => Self::Right,
+            settings::RangefinderOrientation::Back => Self::Back,
+            settings::RangefinderOrientation::Left => Self::Left,
+            settings::RangefinderOrientation::Up => Self::Up,
+            settings::RangefinderOrientation::Down => Self::Down,
+        }
+    }
+}
+impl From<&api::RangefinderOrientation> for settings::RangefinderOrientation {
+    fn from(value: &api::RangefinderOrientation) -> Self {
+        match value {
+            api::RangefinderOrientation::Forward => Self::Forward,
+            api::RangefinderOrientation::Right => Self::Right,
+            api::RangefinderOrientation::Back => Self::Back,
+            api::RangefinderOrientation::Left => Self::Left,
+            api::RangefinderOrientation::Up => Self::Up,
+            api::RangefinderOrientation::Down => Self::Down,
+        }
+    }
+}
+
 impl From<&settings::MountType> for api::MountType {
     fn from(value: &settings::MountType) -> Self {
         match value {
@@ -268,12 +330,45 @@ impl From<&api::FocusZoomPoint> for settings::FocusZoomPoint {
     }
 }
 
+impl From<&settings::DistanceFocusPoints> for api::DistanceFocusPoints {
+    fn from(value: &settings::DistanceFocusPoints) -> Self {
+        Self(value.iter().map(|v| v.into()).collect())
+    }
+}
+impl From<&api::DistanceFocusPoints> for settings::DistanceFocusPoints {
+    fn from(value: &api::DistanceFocusPoints) -> Self {
+        value
+            .0
+            .iter()
+            .map(settings::DistanceFocusPoint::from)
+            .collect()
+    }
+}
+
+impl From<&settings::DistanceFocusPoint> for api::DistanceFocusPoint {
+    fn from(value: &settings::DistanceFocusPoint) -> Self {
+        Self {
+            distance: value.distance,
+            position: value.position,
+        }
+    }
+}
+impl From<&api::DistanceFocusPoint> for settings::DistanceFocusPoint {
+    fn from(value: &api::DistanceFocusPoint) -> Self {
+        Self {
+            distance: value.distance,
+            position: value.position,
+        }
+    }
+}
+
 impl From<&settings::ActuatorsState> for api::ActuatorsState {
     fn from(value: &settings::ActuatorsState) -> Self {
         Self {
             focus: value.focus,
             zoom: value.zoom,
             tilt: value.tilt,
+            focus_output: None,
         }
     }
 }
@@ -286,3 +381,429 @@ impl From<&api::ActuatorsState> for settings::ActuatorsState {
         }
     }
 }
+
+impl From<(&String, &settings::Preset)> for api::Preset {
+    fn from((name, value): (&String, &settings::Preset)) -> Self {
+        Self {
+            name: name.clone(),
+            state: (&value.state).into(),
+            image_adjustment: from_stored(
+                value.image_adjustment.as_ref(),
+                &format!("image adjustment of preset {name:?}"),
+            ),
+            image_adjustment_ex: from_stored(
+                value.image_adjustment_ex.as_ref(),
+                &format!("extended image adjustment of preset {name:?}"),
+            ),
+        }
+    }
+}
+impl From<&api::Preset> for settings::Preset {
+    fn from(value: &api::Preset) -> Self {
+        Self {
+            state: (&value.state).into(),
+            image_adjustment: to_stored(
+                value.image_adjustment.as_ref(),
+                &format!("image adjustment of preset {:?}", value.name),
+            ),
+            image_adjustment_ex: to_stored(
+                value.image_adjustment_ex.as_ref(),
+                &format!("extended image adjustment of preset {:?}", value.name),
+            ),
+        }
+    }
+}
+
+/// The stored camera json, or `None` when it doesn't match the current camera protocol anymore
+fn from_stored<T: DeserializeOwned>(json: Option<&serde_json::Value>, what: &str) -> Option<T> {
+    match serde_json::from_value(json?.clone()) {
+        Ok(value) => Some(value),
+        Err(error) => {
+            warn!("Ignoring the invalid stored {what}: {error:?}");
+            None
+        }
+    }
+}
+
+fn to_stored<T: Serialize>(value: Option<&T>, what: &str) -> Option<serde_json::Value> {
+    match serde_json::to_value(value?) {
+        Ok(json) => Some(json),
+        Err(error) => {
+            warn!("Failed to store the {what}: {error:?}");
+            None
+        }
+    }
+}
+
+impl From<&settings::ScriptButton> for api::ScriptButton {
+    fn from(value: &settings::ScriptButton) -> Self {
+        Self {
+            button: value.button,
+            action: match &value.action {
+                settings::ScriptButtonAction::ZoomIn => api::ScriptButtonAction::ZoomIn,
+                settings::ScriptButtonAction::ZoomOut => api::ScriptButtonAction::ZoomOut,
+                settings::ScriptButtonAction::FocusNear => api::ScriptButtonAction::FocusNear,
+                settings::ScriptButtonAction::FocusFar => api::ScriptButtonAction::FocusFar,
+                settings::ScriptButtonAction::RecallPreset(name) => {
+                    api::ScriptButtonAction::RecallPreset(name.clone())
+                }
+            },
+        }
+    }
+}
+impl From<&api::ScriptButton> for settings::ScriptButton {
+    fn from(value: &api::ScriptButton) -> Self {
+        Self {
+            button: value.button,
+            action: match &value.action {
+                api::ScriptButtonAction::ZoomIn => settings::ScriptButtonAction::ZoomIn,
+                api::ScriptButtonAction::ZoomOut => settings::ScriptButtonAction::ZoomOut,
+                api::ScriptButtonAction::FocusNear => settings::ScriptButtonAction::FocusNear,
+                api::ScriptButtonAction::FocusFar => settings::ScriptButtonAction::FocusFar,
+                api::ScriptButtonAction::RecallPreset(name) => {
+                    settings::ScriptButtonAction::RecallPreset(name.clone())
+                }
+            },
+        }
+    }
+}
+
+impl From<&settings::OsdSettings> for api::OsdConfig {
+    fn from(value: &settings::OsdSettings) -> Self {
+        Self {
+            enabled: value.enabled,
+            update_interval_ms: value.update_interval_ms,
+            fields: value.fields.iter().map(|v| v.into()).collect(),
+        }
+    }
+}
+impl From<&api::OsdConfig> for settings::OsdSettings {
+    fn from(value: &api::OsdConfig) -> Self {
+        Self {
+            enabled: value.enabled,
+            update_interval_ms: value.update_interval_ms,
+            fields: value.fields.iter().map(|v| v.into()).collect(),
+        }
+    }
+}
+
+impl From<&settings::OsdField> for api::OsdField {
+    fn from(value: &settings::OsdField) -> Self {
+        Self {
+            item: match value.item {
+                settings::OsdItem::Depth => api::OsdItem::Depth,
+                settings::OsdItem::Heading => api::OsdItem::Heading,
+                settings::OsdItem::Time => api::OsdItem::Time,
+            },
+            x: value.x,
+            y: value.y,
+        }
+    }
+}
+impl From<&api::OsdField> for settings::OsdField {
+    fn from(value: &api::OsdField) -> Self {
+        Self {
+            item: match value.item {
+                api::OsdItem::Depth => settings::OsdItem::Depth,
+                api::OsdItem::Heading => settings::OsdItem::Heading,
+                api::OsdItem::Time => settings::OsdItem::Time,
+            },
+            x: value.x,
+            y: value.y,
+        }
+    }
+}
+
+impl From<&settings::CameraImageSettings> for CameraImageProfiles {
+    fn from(value: &settings::CameraImageSettings) -> Self {
+        let mut profiles = IndexMap::new();
+        let mut invalid_profiles = IndexMap::new();
+
+        for (name, profile) in &value.profiles {
+            match api::ImageProfile::try_from((name, profile)) {
+                Ok(profile) => {
+                    profiles.insert(name.clone(), profile);
+                }
+                Err(error) => {
+                    warn!("Keeping the invalid image profile {name:?} as it is: {error:?}");
+                    invalid_profiles.insert(name.clone(), profile.clone());
+                }
+            }
+        }
+
+        Self {
+            profiles,
+            invalid_profiles,
+            active_profile: value.active_profile.clone(),
+            reapply_policy: (&value.reapply_policy).into(),
+        }
+    }
+}
+impl From<&CameraImageProfiles> for settings::CameraImageSettings {
+    fn from(value: &CameraImageProfiles) -> Self {
+        let mut profiles: IndexMap<String, settings::ImageProfile> = value
+            .profiles
+            .iter()
+            .map(|(name, profile)| (name.clone(), profile.into()))
+            .collect();
+
+        for (name, profile) in &value.invalid_profiles {
+            profiles
+                .entry(name.clone())
+                .or_insert_with(|| profile.clone());
+        }
+
+        Self {
+            profiles,
+            active_profile: value.active_profile.clone(),
+            reapply_policy: (&value.reapply_policy).into(),
+        }
+    }
+}
+
+impl From<&settings::ReapplyPolicy> for api::ReapplyPolicy {
+    fn from(value: &settings::ReapplyPolicy) -> Self {
+        match value {
+            settings::ReapplyPolicy::Auto => Self::Auto,
+            settings::ReapplyPolicy::Notify => Self::Notify,
+            settings::ReapplyPolicy::Ignore => Self::Ignore,
+        }
+    }
+}
+impl From<&api::ReapplyPolicy> for settings::ReapplyPolicy {
+    fn from(value: &api::ReapplyPolicy) -> Self {
+        match value {
+            api::ReapplyPolicy::Auto => Self::Auto,
+            api::ReapplyPolicy::Notify => Self::Notify,
+            api::ReapplyPolicy::Ignore => Self::Ignore,
+        }
+    }
+}
+
+/// Fails when the stored camera json doesn't match the current camera protocol anymore
+impl TryFrom<(&String, &settings::ImageProfile)> for api::ImageProfile {
+    type Error = serde_json::Error;
+
+    fn try_from((name, value): (&String, &settings::ImageProfile)) -> Result<Self, Self::Error> {
+        Ok(Self {
+            name: name.clone(),
+            image_adjustment: value
+                .image_adjustment
+                .clone()
+                .map(serde_json::from_value)
+                .transpose()?,
+            image_adjustment_ex: value
+                .image_adjustment_ex
+                .clone()
+                .map(serde_json::from_value)
+                .transpose()?,
+            video_parameters: value
+                .video_parameters
+                .iter()
+                .map(|json| serde_json::from_value(json.clone()))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+impl From<&api::ImageProfile> for settings::ImageProfile {
+    fn from(value: &api::ImageProfile) -> Self {
+        Self {
+            image_adjustment: to_stored(
+                value.image_adjustment.as_ref(),
+                &format!("image adjustment of image profile {:?}", value.name),
+            ),
+            image_adjustment_ex: to_stored(
+                value.image_adjustment_ex.as_ref(),
+                &format!(
+                    "extended image adjustment of image profile {:?}",
+                    value.name
+                ),
+            ),
+            video_parameters: value
+                .video_parameters
+                .iter()
+                .filter_map(|video_parameters| {
+                    to_stored(
+                        Some(video_parameters),
+                        &format!("encoder settings of image profile {:?}", value.name),
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Fails when the stored camera action is not valid anymore
+impl TryFrom<(&String, &settings::Rule)> for api::Rule {
+    type Error = serde_json::Error;
+
+    fn try_from((name, value): (&String, &settings::Rule)) -> Result<Self, Self::Error> {
+        Ok(Self {
+            name: name.clone(),
+            enabled: value.enabled,
+            condition: (&value.condition).into(),
+            action: (&value.action).try_into()?,
+            exit_action: value
+                .exit_action
+                .as_ref()
+                .map(|action| action.try_into())
+                .transpose()?,
+            dwell_ms: value.dwell_ms,
+        })
+    }
+}
+impl From<&api::Rule> for settings::Rule {
+    fn from(value: &api::Rule) -> Self {
+        Self {
+            enabled: value.enabled,
+            condition: (&value.condition).into(),
+            action: (&value.action).into(),
+            exit_action: value.exit_action.as_ref().map(|action| action.into()),
+            dwell_ms: value.dwell_ms,
+        }
+    }
+}
+
+impl From<&settings::RuleCondition> for api::RuleCondition {
+    fn from(value: &settings::RuleCondition) -> Self {
+        match value {
+            settings::RuleCondition::Depth {
+                comparison,
+                threshold,
+                hysteresis,
+            } => Self::Depth {
+                comparison: comparison.into(),
+                threshold: *threshold,
+                hysteresis: *hysteresis,
+            },
+            settings::RuleCondition::LightsPwm {
+                channel,
+                comparison,
+                threshold,
+                hysteresis,
+            } => Self::LightsPwm {
+                channel: channel.into(),
+                comparison: comparison.into(),
+                threshold: *threshold,
+                hysteresis: *hysteresis,
+            },
+            settings::RuleCondition::TimeOfDay {
+                start_minute,
+                end_minute,
+            } => Self::TimeOfDay {
+                start_minute: *start_minute,
+                end_minute: *end_minute,
+            },
+        }
+    }
+}
+impl From<&api::RuleCondition> for settings::RuleCondition {
+    fn from(value: &api::RuleCondition) -> Self {
+        match value {
+            api::RuleCondition::Depth {
+                comparison,
+                threshold,
+                hysteresis,
+            } => Self::Depth {
+                comparison: comparison.into(),
+                threshold: *threshold,
+                hysteresis: *hysteresis,
+            },
+            api::RuleCondition::LightsPwm {
+                channel,
+                comparison,
+                threshold,
+                hysteresis,
+            } => Self::LightsPwm {
+                channel: channel.into(),
+                comparison: comparison.into(),
+                threshold: *threshold,
+                hysteresis: *hysteresis,
+            },
+            api::RuleCondition::TimeOfDay {
+                start_minute,
+                end_minute,
+            } => Self::TimeOfDay {
+                start_minute: *start_minute,
+                end_minute: *end_minute,
+            },
+        }
+    }
+}
+
+impl From<&settings::Comparison> for api::Comparison {
+    fn from(value: &settings::Comparison) -> Self {
+        match value {
+            settings::Comparison::Greater => Self::Greater,
+            settings::Comparison::Less => Self::Less,
+        }
+    }
+}
+impl From<&api::Comparison> for settings::Comparison {
+    fn from(value: &api::Comparison) -> Self {
+        match value {
+            api::Comparison::Greater => Self::Greater,
+            api::Comparison::Less => Self::Less,
+        }
+    }
+}
+
+impl TryFrom<&settings::RuleAction> for api::RuleAction {
+    type Error = serde_json::Error;
+
+    fn try_from(value: &settings::RuleAction) -> Result<Self, Self::Error> {
+        Ok(match value {
+            settings::RuleAction::ApplyProfile(name) => Self::ApplyProfile(name.clone()),
+            settings::RuleAction::CameraAction(json) => {
+                Self::CameraAction(serde_json::from_value(json.clone())?)
+            }
+        })
+    }
+}
+impl From<&api::RuleAction> for settings::RuleAction {
+    fn from(value: &api::RuleAction) -> Self {
+        match value {
+            api::RuleAction::ApplyProfile(name) => Self::ApplyProfile(name.clone()),
+            api::RuleAction::CameraAction(action) => {
+                Self::CameraAction(serde_json::to_value(action).unwrap_or(serde_json::Value::Null))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn invalid_image_profiles_are_kept() {
+        let stored = settings::CameraImageSettings {
+            profiles: IndexMap::from([
+                ("day".to_string(), settings::ImageProfile::default()),
+                (
+                    "night".to_string(),
+                    settings::ImageProfile {
+                        video_parameters: vec![json!("not an encoder")],
+                        ..Default::default()
+                    },
+                ),
+            ]),
+            active_profile: Some("night".to_string()),
+            reapply_policy: settings::ReapplyPolicy::Notify,
+        };
+
+        let image_profiles = CameraImageProfiles::from(&stored);
+        assert_eq!(
+            image_profiles.profiles.keys().collect::<Vec<_>>(),
+            vec!["day"]
+        );
+        assert_eq!(
+            image_profiles.invalid_profiles.keys().collect::<Vec<_>>(),
+            vec!["night"]
+        );
+
+        assert_eq!(settings::CameraImageSettings::from(&image_profiles), stored);
+    }
+}
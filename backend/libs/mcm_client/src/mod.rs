@@ -1,11 +1,17 @@
-use std::net::{Ipv4Addr, SocketAddr};
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    time::{Duration, Instant},
+};
 
 use anyhow::{Context, Result};
 use indexmap::IndexMap;
 use mcm_client::MCMClient;
-use once_cell::sync::OnceCell;
+use once_cell::sync::{Lazy, OnceCell};
 use serde::{Deserialize, Serialize};
-use tokio::{sync::RwLock, task::JoinHandle};
+use tokio::{
+    sync::{RwLock, broadcast},
+    task::JoinHandle,
+};
 use tracing::*;
 use ts_rs::TS;
 use url::Url;
@@ -17,8 +23,28 @@ pub mod mcm_types;
 
 static MANAGER: OnceCell<RwLock<Manager>> = OnceCell::new();
 
+const APPEARANCES_CAPACITY: usize = 16;
+/// How long the address a camera was moved to wins over the one discovery reports, as the MCM
+/// keeps the old one until it discovers the camera again
+const MOVE_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+/// A camera is gone once it misses this many discovery polls in a row, so a single missed poll
+/// doesn't make it new again
+const GONE_AFTER_MISSED_POLLS: usize = 10;
+
+/// Cameras added to the registry, either new ones or ones that came back
+static APPEARANCES: Lazy<broadcast::Sender<Uuid>> =
+    Lazy::new(|| broadcast::channel(APPEARANCES_CAPACITY).0);
+
+/// Credentials set by the user, replacing the defaults of the cameras they are set for
+static CREDENTIALS: Lazy<RwLock<IndexMap<Uuid, Credentials>>> =
+    Lazy::new(|| RwLock::new(IndexMap::new()));
+
+/// The cameras moved to a new address, which discovery may still report at the old one
+static MOVES: Lazy<RwLock<Moves>> = Lazy::new(|| RwLock::new(Moves::default()));
+
 #[derive(Debug)]
 struct Manager {
+    address: SocketAddr,
     cameras: Cameras,
     _authentication_task_handler: JoinHandle<()>,
     _start_radcams_task_handler: JoinHandle<()>,
@@ -30,8 +56,22 @@ pub type Cameras = IndexMap<Uuid, Camera>;
 pub struct Camera {
     pub uuid: Uuid,
     pub hostname: Ipv4Addr,
+    /// Never answered, so the passwords don't leave the backend
+    #[serde(skip_serializing)]
+    #[ts(skip)]
     pub credentials: Option<Credentials>,
     pub streams: Streams,
+    #[serde(default)]
+    pub kind: CameraKind,
+}
+
+/// Which dialect the camera speaks
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq, TS)]
+pub enum CameraKind {
+    #[default]
+    RadCam,
+    /// Any other camera, controlled through the standard ONVIF services
+    Onvif,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, TS)]
@@ -49,6 +89,46 @@ pub struct Stream {
     stream_endpoints: Vec<Url>,
 }
 
+#[derive(Debug, Default)]
+struct Moves {
+    moved: IndexMap<Uuid, Moved>,
+}
+
+#[derive(Debug)]
+struct Moved {
+    hostname: Ipv4Addr,
+    since: Instant,
+}
+
+impl Moves {
+    fn insert(&mut self, uuid: Uuid, hostname: Ipv4Addr, now: Instant) {
+        self.moved.insert(
+            uuid,
+            Moved {
+                hostname,
+                since: now,
+            },
+        );
+    }
+
+    /// The discovered camera, at the address it was moved to until discovery reports that one too
+    fn follow(&mut self, camera: &Camera, now: Instant) -> Camera {
+        let Some(moved) = self.moved.get(&camera.uuid) else {
+            return camera.clone();
+        };
+
+        if camera.hostname == moved.hostname || now.duration_since(moved.since) > MOVE_TIMEOUT {
+            self.moved.shift_remove(&camera.uuid);
+            return camera.clone();
+        }
+
+        Camera {
+            hostname: moved.hostname,
+            ..camera.clone()
+        }
+    }
+}
+
 /// Constructs our manager, Should be done inside main
 #[instrument(level = "debug")]
 pub async fn init(mcm_address: SocketAddr) {
@@ -60,6 +140,7 @@ pub async fn init(mcm_address: SocketAddr) {
 
     MANAGER.get_or_init(|| {
         RwLock::new(Manager {
+            address: mcm_address,
             cameras,
             _authentication_task_handler,
             _start_radcams_task_handler,
@@ -92,11 +173,15 @@ async fn authenticate_radcams(mcm_address: &SocketAddr) {
             }
         };
 
+        let mut missed_polls: IndexMap<Uuid, usize> = IndexMap::new();
+
         loop {
             tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
 
-            let radcams = match mcm.get_radcams().await {
-                Ok(radcams) => radcams,
+            let credentials = CREDENTIALS.read().await.clone();
+
+            let found_cameras = match mcm.get_cameras(&credentials).await {
+                Ok(cameras) => cameras,
                 Err(error) => {
                     debug!("Failed to create MCM client: {error:?}");
                     break;
@@ -105,21 +190,48 @@ async fn authenticate_radcams(mcm_address: &SocketAddr) {
 
             let known_cameras = cameras().await;
 
-            for camera in &radcams {
+            let found_cameras: Vec<Camera> = {
+                let mut moves = MOVES.write().await;
+                let now = Instant::now();
+
+                found_cameras
+                    .iter()
+                    .map(|camera| moves.follow(camera, now))
+                    .collect()
+            };
+
+            for camera in &found_cameras {
                 if let Some(known_camera) = known_cameras.get(&camera.uuid) {
-                    if known_camera != camera {
-                        if let Err(error) = add_camera(camera).await {
-                            debug!("Failed updating camera {camera:?}: {error:?}");
-                            continue;
+                    if known_camera == camera {
+                        continue;
+                    }
+
+                    // The user may have just set the credentials
+                    if known_camera.credentials != camera.credentials {
+                        match mcm.authenticate(camera).await {
+                            Ok(()) => info!("Camera {} authenticated again", camera.uuid),
+                            Err(error) => {
+                                debug!("Failed authenticating onvif camera {camera:?}: {error:?}")
+                            }
                         }
                     }
 
-                    break;
+                    if let Err(error) = add_camera(camera).await {
+                        debug!("Failed updating camera {camera:?}: {error:?}");
+                    }
+
+                    continue;
                 }
 
-                debug!("New RadCam found: {camera:?}");
+                debug!("New {:?} camera found: {camera:?}", camera.kind);
 
-                if let Err(error) = mcm.authenticate(camera).await {
+                // Cameras without credentials are still listed, so the user can set them
+                let authentication = match &camera.credentials {
+                    Some(_) => mcm.authenticate(camera).await,
+                    None => Ok(()),
+                };
+
+                if let Err(error) = authentication {
                     debug!("Failed authenticating onvif camera {camera:?}: {error:?}");
                     continue;
                 }
@@ -129,7 +241,34 @@ async fn authenticate_radcams(mcm_address: &SocketAddr) {
                     continue;
                 }
 
-                debug!("New RadCam added: {camera:?}");
+                debug!("New {:?} camera added: {camera:?}", camera.kind);
+
+                utils::broadcast(&APPEARANCES, camera.uuid);
+            }
+
+            // Forgetting the cameras that are gone makes them new again when they come back
+            missed_polls.retain(|camera_uuid, _| known_cameras.contains_key(camera_uuid));
+            for camera_uuid in known_cameras.keys() {
+                if found_cameras
+                    .iter()
+                    .any(|camera| camera.uuid == *camera_uuid)
+                {
+                    missed_polls.shift_remove(camera_uuid);
+                    continue;
+                }
+
+                let missed = missed_polls.entry(*camera_uuid).or_default();
+                *missed += 1;
+                if *missed < GONE_AFTER_MISSED_POLLS {
+                    continue;
+                }
+
+                missed_polls.shift_remove(camera_uuid);
+                debug!("Camera gone: {camera_uuid}");
+
+                if let Err(error) = remove_camera(camera_uuid).await {
+                    debug!("Failed removing camera {camera_uuid}: {error:?}");
+                }
             }
         }
     }
@@ -193,6 +332,27 @@ async fn start_radcams_streams(mcm_address: &SocketAddr) {
     }
 }
 
+/// Notifies the UUID of each camera added to the registry. Cameras are added again after the MCM
+/// connection is recovered, or after they disappeared
+pub fn subscribe_appearances() -> broadcast::Receiver<Uuid> {
+    APPEARANCES.subscribe()
+}
+
+/// Sets the credentials used for the camera from now on, or goes back to its default ones
+#[instrument(level = "debug", skip(credentials))]
+pub async fn set_credentials(uuid: &Uuid, credentials: Option<Credentials>) {
+    let mut all_credentials = CREDENTIALS.write().await;
+
+    match credentials {
+        Some(credentials) => {
+            all_credentials.insert(*uuid, credentials);
+        }
+        None => {
+            all_credentials.shift_remove(uuid);
+        }
+    }
+}
+
 #[instrument(level = "debug")]
 pub async fn cameras() -> Cameras {
     MANAGER.get().unwrap().read().await.cameras.clone()
@@ -223,6 +383,62 @@ pub async fn remove_camera(uuid: &Uuid) -> Result<Camera> {
     lock.cameras.swap_remove(uuid).context("context")
 }
 
+/// Follows a camera to a new address: authenticates it again in the MCM, updates the registry,
+/// and deletes the streams from its old address, so they are recreated from the new one
+#[instrument(level = "debug")]
+pub async fn move_camera(uuid: &Uuid, hostname: Ipv4Addr) -> Result<()> {
+    let address = MANAGER
+        .get()
+        .context("MCM client not initialized")?
+        .read()
+        .await
+        .address;
+
+    let mut camera = get_camera(uuid).await.context("Camera not found")?;
+    let old_hostname = camera.hostname;
+    camera.hostname = hostname;
+
+    let mcm = MCMClient::try_new(&address).await?;
+
+    if let Err(error) = mcm.unauthenticate(&camera).await {
+        debug!("Failed unauthenticating onvif camera {camera:?}: {error:?}");
+    }
+    // The registry keeps the old address until the camera is known to answer on the new one
+    mcm.authenticate(&camera).await?;
+
+    add_camera(&camera).await?;
+    MOVES.write().await.insert(*uuid, hostname, Instant::now());
+
+    mcm.delete_streams_from(&old_hostname).await?;
+
+    info!("Camera {uuid} moved from {old_hostname} to {hostname}");
+
+    Ok(())
+}
+
+/// Follows a change in the camera encoder, recreating the stream sourced from the channel's
+/// `source` address, so it advertises the new configuration. Answers whether there was such a
+/// stream
+#[instrument(level = "debug")]
+pub async fn reconfigure_stream(
+    uuid: &Uuid,
+    source: &str,
+    configuration: mcm_types::VideoCaptureConfiguration,
+) -> Result<bool> {
+    let camera = get_camera(uuid).await.context("Camera not found")?;
+
+    let address = MANAGER
+        .get()
+        .context("MCM client not initialized")?
+        .read()
+        .await
+        .address;
+    let mcm = MCMClient::try_new(&address).await?;
+
+    mcm.reconfigure_stream_from(&camera.hostname, source, configuration)
+        .await
+}
+
 #[instrument(level = "debug")]
 pub async fn clear_cameras() {
     let mut lock = MANAGER.get().unwrap().write().await;
@@ -243,6 +459,7 @@ async fn test_camera_manager_full_cycle() {
             password: "test_password".to_string(),
         }),
         streams: IndexMap::new(),
+        kind: CameraKind::RadCam,
     };
 
     // Add the test camera
@@ -267,3 +484,37 @@ async fn test_camera_manager_full_cycle() {
     assert_eq!(all_cameras.len(), 0);
     assert!(!all_cameras.contains_key(&test_camera.uuid));
 }
+
+#[test]
+fn test_moved_camera_keeps_its_new_address() {
+    let old_camera = Camera {
+        uuid: "bc071801-c50f-8301-ac36-bc071801c50f".parse().unwrap(),
+        hostname: "192.168.0.200".parse().unwrap(),
+        credentials: None,
+        streams: IndexMap::new(),
+        kind: CameraKind::RadCam,
+    };
+    let new_hostname: Ipv4Addr = "192.168.0.201".parse().unwrap();
+    let new_camera = Camera {
+        hostname: new_hostname,
+        ..old_camera.clone()
+    };
+    let now = Instant::now();
+
+    let mut moves = Moves::default();
+    moves.insert(old_camera.uuid, new_hostname, now);
+
+    // Discovery still reports the old address
+    assert_eq!(moves.follow(&old_camera, now), new_camera);
+
+    // Until it catches up, and later moves are followed again
+    assert_eq!(moves.follow(&new_camera, now), new_camera);
+    assert_eq!(moves.follow(&old_camera, now), old_camera);
+
+    // Or until the move is too old to be the reason
+    moves.insert(old_camera.uuid, new_hostname, now);
+    assert_eq!(
+        moves.follow(&old_camera, now + MOVE_TIMEOUT + Duration::from_secs(1)),
+        old_camera
+    );
+}
@@ -0,0 +1,273 @@
+use anyhow::Result;
+use axum::{Json, http::StatusCode, response::IntoResponse};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use tracing::*;
+use ts_rs::TS;
+use utils::jobs::action_name;
+use uuid::Uuid;
+
+use crate::{Action, CameraControl, control_inner, labels::camera_labels};
+
+/// The same actions sent to several cameras at once
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+pub struct BatchControl {
+    pub cameras: CameraSelector,
+    /// Run in order on each camera, stopping at the first one that fails
+    pub actions: Vec<Action>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+pub enum CameraSelector {
+    #[serde(rename = "uuids")]
+    Uuids(#[ts(as = "Vec<String>")] Vec<Uuid>),
+    /// Every camera known by the Mavlink Camera Manager
+    #[serde(rename = "all")]
+    All,
+    #[serde(rename = "label")]
+    Label(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+pub struct CameraBatchResult {
+    pub succeeded: bool,
+    /// The answer of each action that succeeded, in order
+    #[ts(type = "unknown[]")]
+    pub results: Vec<serde_json::Value>,
+    /// Only present when an action failed
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BatchError {
+    NoActions,
+    NoCameras {
+        selector: CameraSelector,
+    },
+    /// Actions that have to run on one camera at a time, or as a job
+    NotBatchable {
+        action: String,
+    },
+}
+
+impl std::fmt::Display for BatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            BatchError::NoActions => write!(f, "No actions to run"),
+            BatchError::NoCameras { selector } => write!(f, "No cameras match {selector:?}"),
+            BatchError::NotBatchable { action } => {
+                write!(
+                    f,
+                    "Action {action:?} can't run in a batch, run it as a job on each camera"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for BatchError {}
+
+/// Runs the actions on all the selected cameras concurrently, answering how it went for each one.
+/// The cameras that are not connected are answered as failed
+#[instrument(level = "debug")]
+pub async fn run_batch(batch_control: &BatchControl) -> Result<IndexMap<Uuid, CameraBatchResult>> {
+    if batch_control.actions.is_empty() {
+        return Err(BatchError::NoActions.into());
+    }
+
+    if let Some(action) = batch_control
+        .actions
+        .iter()
+        .find(|action| !is_batchable(action))
+    {
+        return Err(BatchError::NotBatchable {
+            action: action_name(action),
+        }
+        .into());
+    }
+
+    let known_cameras = mcm_client::cameras()
+        .await
+        .keys()
+        .copied()
+        .collect::<Vec<Uuid>>();
+
+    let labels = match &batch_control.cameras {
+        CameraSelector::Label(_) => camera_labels().await?,
+        _ => IndexMap::new(),
+    };
+
+    let cameras = select_cameras(&batch_control.cameras, &known_cameras, &labels);
+
+    if cameras.is_empty() {
+        return Err(BatchError::NoCameras {
+            selector: batch_control.cameras.clone(),
+        }
+        .into());
+    }
+
+    let results = futures::future::join_all(cameras.iter().map(|camera_uuid| {
+        let known = known_cameras.contains(camera_uuid);
+
+        async move {
+            if !known {
+                return CameraBatchResult {
+                    succeeded: false,
+                    results: vec![],
+                    error: Some(format!("Camera {camera_uuid} is not connected")),
+                };
+            }
+
+            run_actions(camera_uuid, &batch_control.actions).await
+        }
+    }))
+    .await;
+
+    Ok(cameras.into_iter().zip(results).collect())
+}
+
+async fn run_actions(camera_uuid: &Uuid, actions: &[Action]) -> CameraBatchResult {
+    let mut results = Vec::new();
+
+    for action in actions {
+        let res = control_inner(Json(CameraControl {
+            camera_uuid: *camera_uuid,
+            action: action.clone(),
+        }))
+        .await;
+
+        match res {
+            Ok(res) => results.push(res),
+            Err(error) => {
+                warn!("Batch action {action} failed on camera {camera_uuid}: {error:#}");
+
+                return CameraBatchResult {
+                    succeeded: false,
+                    results,
+                    error: Some(format!("{action}: {error:#}")),
+                };
+            }
+        }
+    }
+
+    CameraBatchResult {
+        succeeded: true,
+        results,
+        error: None,
+    }
+}
+
+/// The static addresses are only checked against where the other cameras are now, so a batch would
+/// give them all the same one. Restarts wait for each camera to come back, which jobs can track
+fn is_batchable(action: &Action) -> bool {
+    !matches!(action, Action::SetNetParameterSettings(_) | Action::Restart)
+}
+
+/// The cameras the selector points to, without repetitions
+fn select_cameras(
+    selector: &CameraSelector,
+    known_cameras: &[Uuid],
+    labels: &IndexMap<Uuid, Vec<String>>,
+) -> Vec<Uuid> {
+    let mut cameras: Vec<Uuid> = match selector {
+        CameraSelector::Uuids(uuids) => uuids.clone(),
+        CameraSelector::All => known_cameras.to_vec(),
+        CameraSelector::Label(label) => labels
+            .iter()
+            .filter(|(_, camera_labels)| camera_labels.contains(label))
+            .map(|(camera_uuid, _)| *camera_uuid)
+            .collect(),
+    };
+
+    let mut seen = Vec::new();
+    cameras.retain(|camera_uuid| {
+        if seen.contains(camera_uuid) {
+            return false;
+        }
+
+        seen.push(*camera_uuid);
+        true
+    });
+
+    cameras
+}
+
+#[instrument(level = "debug")]
+pub async fn batch(Json(batch_control): Json<BatchControl>) -> impl IntoResponse {
+    match run_batch(&batch_control).await {
+        Ok(results) => (StatusCode::OK, serde_json::to_string(&results).unwrap()).into_response(),
+        Err(error) => {
+            let status = match error.downcast_ref::<BatchError>() {
+                Some(_) => StatusCode::BAD_REQUEST,
+                None => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+
+            (status, format!("{error:?}")).into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_batchable_test() {
+        assert!(is_batchable(&Action::GetSysConfig));
+        assert!(!is_batchable(&Action::Restart));
+        assert!(!is_batchable(&Action::SetNetParameterSettings(
+            Default::default()
+        )));
+    }
+
+    #[test]
+    fn select_cameras_test() {
+        let first = Uuid::from_u128(1);
+        let second = Uuid::from_u128(2);
+        let third = Uuid::from_u128(3);
+
+        let known_cameras = [first, second];
+        let labels = IndexMap::from([
+            (first, vec!["port".to_string()]),
+            (third, vec!["port".to_string(), "forward".to_string()]),
+        ]);
+
+        assert_eq!(
+            select_cameras(&CameraSelector::All, &known_cameras, &labels),
+            vec![first, second]
+        );
+        assert_eq!(
+            select_cameras(
+                &CameraSelector::Uuids(vec![third, first, third]),
+                &known_cameras,
+                &labels
+            ),
+            vec![third, first]
+        );
+        assert_eq!(
+            select_cameras(
+                &CameraSelector::Label("port".to_string()),
+                &known_cameras,
+                &labels
+            ),
+            vec![first, third]
+        );
+        assert!(
+            select_cameras(
+                &CameraSelector::Label("aft".to_string()),
+                &known_cameras,
+                &labels
+            )
+            .is_empty()
+        );
+    }
+
+    #[test]
+    fn camera_selector_serde_test() {
+        let selector: CameraSelector = serde_json::from_str(r#""all""#).unwrap();
+        assert_eq!(selector, CameraSelector::All);
+
+        let selector: CameraSelector = serde_json::from_str(r#"{"label":"port"}"#).unwrap();
+        assert_eq!(selector, CameraSelector::Label("port".to_string()));
+    }
+}
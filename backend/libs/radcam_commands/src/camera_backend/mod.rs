@@ -0,0 +1,530 @@
+//! The camera controls that don't depend on which dialect the camera speaks. RadCams are
+//! controlled through their CGI actions, the other cameras through the ONVIF Imaging and Media
+//! services
+
+use anyhow::{Context, Result};
+use axum::{Json, extract::Path, http::StatusCode, response::IntoResponse};
+use futures::future::BoxFuture;
+use mcm_client::{
+    Camera, CameraKind, Credentials, get_camera,
+    mcm_types::{FrameInterval, VideoCaptureConfiguration, VideoEncodeType},
+};
+use serde::{Deserialize, Serialize};
+use settings::{CameraCredentials, MANAGER as SETTINGS_MANAGER};
+use tracing::*;
+use ts_rs::TS;
+use uuid::Uuid;
+
+use crate::{
+    Action,
+    protocol::video::video_parameters::{VideoChannelValue, VideoResolutionValue},
+};
+
+mod onvif;
+pub(crate) mod radcam;
+mod xml;
+
+/// What the camera can do through its backend
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+pub struct Capabilities {
+    pub image: bool,
+    pub encoder: bool,
+    pub restart: bool,
+    pub snapshot: bool,
+    /// How many encoder channels the camera has
+    pub channels: u8,
+}
+
+/// The image settings every camera has, scaled to [0..=100] whatever the camera range is
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+pub struct ImageSettings {
+    pub brightness: Option<u8>,
+    pub contrast: Option<u8>,
+    pub saturation: Option<u8>,
+    pub sharpness: Option<u8>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, TS)]
+pub struct EncoderSettings {
+    /// The main channel is 0
+    pub channel: u8,
+    pub encoding: Option<Encoding>,
+    pub width: Option<u16>,
+    pub height: Option<u16>,
+    pub frame_rate: Option<u16>,
+    /// In kbps
+    pub bitrate: Option<u32>,
+    /// The resolutions the channel supports, ignored when setting
+    #[serde(default)]
+    pub resolutions: Vec<VideoResolutionValue>,
+    /// The maximum frame rate the channel supports, ignored when setting
+    pub max_frame_rate: Option<u16>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+pub enum Encoding {
+    H264,
+    H265,
+    Mjpeg,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+pub struct BackendControl {
+    #[ts(as = "String")]
+    pub camera_uuid: Uuid,
+    #[serde(flatten)]
+    pub action: BackendAction,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(tag = "action", content = "json")]
+pub enum BackendAction {
+    #[serde(rename = "getCapabilities")]
+    GetCapabilities,
+    #[serde(rename = "getImageSettings")]
+    GetImageSettings,
+    #[serde(rename = "setImageSettings")]
+    SetImageSettings(ImageSettings),
+    /// Takes the channel
+    #[serde(rename = "getEncoderSettings")]
+    GetEncoderSettings(u8),
+    #[serde(rename = "setEncoderSettings")]
+    SetEncoderSettings(EncoderSettings),
+    #[serde(rename = "restart")]
+    Restart,
+    /// Answers the JPEG, without storing it
+    #[serde(rename = "takeSnapshot")]
+    TakeSnapshot,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BackendError {
+    /// The camera can't do it, as told by its capabilities
+    Unsupported { kind: CameraKind, what: String },
+}
+
+impl std::fmt::Display for BackendError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            BackendError::Unsupported { kind, what } => {
+                write!(f, "{kind:?} camera doesn't support {what}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for BackendError {}
+
+/// How the camera is controlled. Settings are answered as the camera has them after changing them
+pub trait CameraBackend: Send + Sync {
+    fn capabilities<'a>(&'a self, camera: &'a Camera) -> BoxFuture<'a, Result<Capabilities>>;
+
+    fn image_settings<'a>(&'a self, camera: &'a Camera) -> BoxFuture<'a, Result<ImageSettings>>;
+
+    fn set_image_settings<'a>(
+        &'a self,
+        camera: &'a Camera,
+        image_settings: &'a ImageSettings,
+    ) -> BoxFuture<'a, Result<ImageSettings>>;
+
+    fn encoder_settings<'a>(
+        &'a self,
+        camera: &'a Camera,
+        channel: u8,
+    ) -> BoxFuture<'a, Result<EncoderSettings>>;
+
+    fn set_encoder_settings<'a>(
+        &'a self,
+        camera: &'a Camera,
+        encoder_settings: &'a EncoderSettings,
+    ) -> BoxFuture<'a, Result<EncoderSettings>>;
+
+    /// Returns once the camera is back, or gave up waiting for it
+    fn restart<'a>(&'a self, camera: &'a Camera) -> BoxFuture<'a, Result<()>>;
+
+    /// A JPEG still from the main channel
+    fn snapshot<'a>(&'a self, camera: &'a Camera) -> BoxFuture<'a, Result<Vec<u8>>>;
+}
+
+/// The backend for the cameras of this kind
+pub fn backend(kind: CameraKind) -> &'static dyn CameraBackend {
+    match kind {
+        CameraKind::RadCam => &radcam::RadCamBackend,
+        CameraKind::Onvif => &onvif::OnvifBackend,
+    }
+}
+
+/// Fetches a JPEG still from any camera
+#[instrument(level = "debug")]
+pub(crate) async fn fetch_snapshot(camera_uuid: &Uuid) -> Result<Vec<u8>> {
+    let camera = get_camera(camera_uuid).await.context("Camera not found")?;
+
+    backend(camera.kind).snapshot(&camera).await
+}
+
+/// The RTSP address the camera streams the channel from. RadCams are ONVIF devices too, so both
+/// kinds answer it through ONVIF
+pub(crate) async fn stream_source(camera: &Camera, channel: u8) -> Result<String> {
+    onvif::stream_uri(camera, channel).await
+}
+
+/// Answers the RadCam actions every camera has through its backend, in the RadCam shapes. Only the
+/// image settings every camera has are changed
+#[instrument(level = "debug", skip(camera), fields(camera_uuid = %camera.uuid))]
+pub(crate) async fn control_shared_action(
+    camera: &Camera,
+    action: &Action,
+) -> Result<serde_json::Value> {
+    let backend = backend(camera.kind);
+
+    let res = match action {
+        Action::GetImageAdjustment => {
+            let image_settings = backend.image_settings(camera).await?;
+
+            serde_json::to_value(radcam::base_parameters(&image_settings))?
+        }
+        Action::SetImageAdjustment(base_parameters) => {
+            let image_settings = backend
+                .set_image_settings(camera, &radcam::image_settings(base_parameters))
+                .await?;
+
+            serde_json::to_value(radcam::base_parameters(&image_settings))?
+        }
+        Action::GetVideoParameterSettings(video_parameters) => {
+            let channel = video_parameters
+                .channel
+                .clone()
+                .unwrap_or(VideoChannelValue::MainStream);
+            let encoder_settings = backend.encoder_settings(camera, channel as u8).await?;
+
+            serde_json::to_value(radcam::answered_video_parameters(&encoder_settings)?)?
+        }
+        Action::SetVideoParameterSettings(video_parameters) => {
+            let channel = video_parameters
+                .channel
+                .clone()
+                .unwrap_or(VideoChannelValue::MainStream);
+            let encoder_settings = backend
+                .set_encoder_settings(
+                    camera,
+                    &radcam::encoder_settings(channel as u8, video_parameters),
+                )
+                .await?;
+
+            serde_json::to_value(radcam::answered_video_parameters(&encoder_settings)?)?
+        }
+        Action::Restart => serde_json::to_value(backend.restart(camera).await?)?,
+        Action::TakeSnapshot => {
+            serde_json::to_value(crate::snapshot::take_snapshot(&camera.uuid).await?)?
+        }
+        _ => {
+            return Err(BackendError::Unsupported {
+                kind: camera.kind,
+                what: "the RadCam actions".to_string(),
+            }
+            .into());
+        }
+    };
+
+    Ok(res)
+}
+
+#[instrument(level = "debug")]
+pub async fn control_inner(backend_control: &BackendControl) -> Result<serde_json::Value> {
+    let camera = get_camera(&backend_control.camera_uuid)
+        .await
+        .context("Camera not found")?;
+    let backend = backend(camera.kind);
+
+    let res = match &backend_control.action {
+        BackendAction::GetCapabilities => {
+            serde_json::to_value(backend.capabilities(&camera).await?)?
+        }
+        BackendAction::GetImageSettings => {
+            serde_json::to_value(backend.image_settings(&camera).await?)?
+        }
+        BackendAction::SetImageSettings(image_settings) => {
+            serde_json::to_value(backend.set_image_settings(&camera, image_settings).await?)?
+        }
+        BackendAction::GetEncoderSettings(channel) => {
+            serde_json::to_value(backend.encoder_settings(&camera, *channel).await?)?
+        }
+        BackendAction::SetEncoderSettings(encoder_settings) => serde_json::to_value(
+            backend
+                .set_encoder_settings(&camera, encoder_settings)
+                .await?,
+        )?,
+        BackendAction::Restart => serde_json::to_value(backend.restart(&camera).await?)?,
+        BackendAction::TakeSnapshot => {
+            let snapshot = crate::snapshot::take_snapshot(&camera.uuid).await?;
+
+            serde_json::to_value(snapshot)?
+        }
+    };
+
+    Ok(res)
+}
+
+#[instrument(level = "debug")]
+pub async fn control(Json(backend_control): Json<BackendControl>) -> impl IntoResponse {
+    match control_inner(&backend_control).await {
+        Ok(res) => (StatusCode::OK, res.to_string()).into_response(),
+        Err(error) => {
+            warn!("Backend control failed: {error:#?}");
+
+            (crate::error_status(&error), format!("{error:?}")).into_response()
+        }
+    }
+}
+
+/// Loads the credentials set by the user into the MCM client, Should be done inside main, after
+/// the settings
+#[instrument(level = "debug")]
+pub async fn init() -> Result<()> {
+    let credentials = SETTINGS_MANAGER
+        .get()
+        .context("Not available")?
+        .read()
+        .await
+        .settings
+        .get_credentials()
+        .clone();
+
+    for (camera_uuid, CameraCredentials { username, password }) in credentials {
+        mcm_client::set_credentials(&camera_uuid, Some(Credentials { username, password })).await;
+    }
+
+    Ok(())
+}
+
+/// Replaces the credentials the camera is authenticated with, or goes back to its default ones
+#[instrument(level = "debug", skip(credentials))]
+pub async fn set_camera_credentials(
+    camera_uuid: &Uuid,
+    credentials: Option<Credentials>,
+) -> Result<()> {
+    let settings = &mut SETTINGS_MANAGER
+        .get()
+        .context("Not available")?
+        .write()
+        .await
+        .settings;
+
+    match &credentials {
+        Some(Credentials { username, password }) => {
+            settings.get_credentials_mut().insert(
+                *camera_uuid,
+                CameraCredentials {
+                    username: username.clone(),
+                    password: password.clone(),
+                },
+            );
+        }
+        None => {
+            settings.get_credentials_mut().shift_remove(camera_uuid);
+        }
+    }
+
+    settings.save().await?;
+
+    mcm_client::set_credentials(camera_uuid, credentials).await;
+
+    Ok(())
+}
+
+#[instrument(level = "debug", skip(credentials))]
+pub async fn credentials(
+    Path(camera_uuid): Path<Uuid>,
+    Json(credentials): Json<Option<Credentials>>,
+) -> impl IntoResponse {
+    match set_camera_credentials(&camera_uuid, credentials).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(error) => (StatusCode::INTERNAL_SERVER_ERROR, format!("{error:?}")).into_response(),
+    }
+}
+
+/// Why the channel can't take the settings, if it can't. `current` holds what the channel supports
+pub(crate) fn unsupported_reasons(
+    encoder_settings: &EncoderSettings,
+    current: &EncoderSettings,
+) -> Vec<String> {
+    let channel = encoder_settings.channel;
+    let mut reasons = Vec::new();
+
+    match (encoder_settings.width, encoder_settings.height) {
+        (Some(width), Some(height))
+            if !current.resolutions.is_empty()
+                && !current
+                    .resolutions
+                    .iter()
+                    .any(|resolution| resolution.width == width && resolution.height == height) =>
+        {
+            reasons.push(format!(
+                "Channel {channel} doesn't support {width}x{height}"
+            ));
+        }
+        _ => (),
+    }
+
+    match (encoder_settings.frame_rate, current.max_frame_rate) {
+        (Some(frame_rate), Some(max_frame_rate)) if frame_rate > max_frame_rate => {
+            reasons.push(format!(
+                "Channel {channel} supports up to {max_frame_rate} fps, not {frame_rate}"
+            ));
+        }
+        _ => (),
+    }
+
+    reasons
+}
+
+/// What the MCM stream should advertise for the encoder settings
+fn capture_configuration(encoder_settings: &EncoderSettings) -> Option<VideoCaptureConfiguration> {
+    let encode = match encoder_settings.encoding? {
+        Encoding::H264 => VideoEncodeType::H264,
+        Encoding::H265 => VideoEncodeType::H265,
+        Encoding::Mjpeg => VideoEncodeType::Mjpg,
+    };
+
+    Some(VideoCaptureConfiguration {
+        encode,
+        height: encoder_settings.height?.into(),
+        width: encoder_settings.width?.into(),
+        frame_interval: FrameInterval {
+            numerator: 1,
+            denominator: encoder_settings.frame_rate?.into(),
+        },
+    })
+}
+
+/// Recreates the MCM stream of the channel when its encoding, size or frame rate changed
+pub(crate) async fn follow_in_stream(
+    camera: &Camera,
+    previous: &EncoderSettings,
+    current: &EncoderSettings,
+) {
+    let channel = current.channel;
+    let (Some(previous), Some(current)) = (
+        capture_configuration(previous),
+        capture_configuration(current),
+    ) else {
+        return;
+    };
+
+    if previous == current {
+        return;
+    }
+
+    let result = match stream_source(camera, channel).await {
+        Ok(source) => mcm_client::reconfigure_stream(&camera.uuid, &source, current).await,
+        Err(error) => Err(error.context("Failed reading the channel stream address")),
+    };
+
+    match result {
+        Ok(true) => info!(
+            "Camera {} stream recreated after its encoder changed",
+            camera.uuid
+        ),
+        Ok(false) => debug!(
+            "Camera {} has no stream from channel {channel}",
+            camera.uuid
+        ),
+        Err(error) => warn!(
+            "Camera {} encoder changed, but its stream didn't follow: {error:#}",
+            camera.uuid
+        ),
+    }
+}
+
+/// Scales a value in [min..=max] to [0..=100]
+pub(crate) fn to_percent(value: f32, min: f32, max: f32) -> u8 {
+    if max <= min {
+        return 0;
+    }
+
+    (((value - min) / (max - min)) * 100.)
+        .round()
+        .clamp(0., 100.) as u8
+}
+
+/// Scales a value in [0..=100] to [min..=max]
+pub(crate) fn from_percent(percent: u8, min: f32, max: f32) -> f32 {
+    min + (max - min) * f32::from(percent.min(100)) / 100.
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percent_test() {
+        assert_eq!(to_percent(0., 0., 255.), 0);
+        assert_eq!(to_percent(128., 0., 255.), 50);
+        assert_eq!(to_percent(255., 0., 255.), 100);
+        assert_eq!(to_percent(-10., -10., 10.), 0);
+        assert_eq!(to_percent(20., -10., 10.), 100);
+
+        for percent in [0, 1, 33, 50, 99, 100] {
+            let value = from_percent(percent, 0., 255.).round();
+            assert_eq!(to_percent(value, 0., 255.), percent);
+        }
+    }
+
+    #[test]
+    fn unsupported_reasons_test() {
+        let current = EncoderSettings {
+            resolutions: vec![VideoResolutionValue {
+                width: 1920,
+                height: 1080,
+            }],
+            max_frame_rate: Some(30),
+            ..Default::default()
+        };
+
+        let encoder_settings = EncoderSettings {
+            width: Some(1920),
+            height: Some(1080),
+            frame_rate: Some(25),
+            ..Default::default()
+        };
+        assert!(unsupported_reasons(&encoder_settings, &current).is_empty());
+
+        let encoder_settings = EncoderSettings {
+            width: Some(1280),
+            height: Some(720),
+            frame_rate: Some(60),
+            ..Default::default()
+        };
+        assert_eq!(unsupported_reasons(&encoder_settings, &current).len(), 2);
+    }
+
+    #[test]
+    fn capture_configuration_test() {
+        let encoder_settings = EncoderSettings {
+            encoding: Some(Encoding::H265),
+            width: Some(1920),
+            height: Some(1080),
+            frame_rate: Some(25),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            capture_configuration(&encoder_settings),
+            Some(VideoCaptureConfiguration {
+                encode: VideoEncodeType::H265,
+                height: 1080,
+                width: 1920,
+                frame_interval: FrameInterval {
+                    numerator: 1,
+                    denominator: 25,
+                },
+            })
+        );
+
+        let encoder_settings = EncoderSettings {
+            frame_rate: None,
+            ..encoder_settings
+        };
+        assert_eq!(capture_configuration(&encoder_settings), None);
+    }
+}
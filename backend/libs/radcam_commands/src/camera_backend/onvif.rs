@@ -0,0 +1,719 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use anyhow::{Context, Result, anyhow};
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use futures::{FutureExt, future::BoxFuture};
+use mcm_client::{Camera, CameraKind, Credentials};
+use quick_xml::escape::escape;
+use reqwest::{StatusCode, header};
+use sha1::{Digest, Sha1};
+use tracing::*;
+
+use super::{
+    BackendError, CameraBackend, Capabilities, EncoderSettings, Encoding, ImageSettings,
+    follow_in_stream, from_percent, to_percent, unsupported_reasons, xml,
+};
+use crate::{
+    CameraError, encoder::EncoderError, protocol::video::video_parameters::VideoResolutionValue,
+    snapshot::JPEG_SOI,
+};
+
+const DEVICE_SERVICE_PATH: &str = "onvif/device_service";
+const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+/// The image settings range of cameras that don't tell theirs
+const DEFAULT_RANGE: (f32, f32) = (0., 100.);
+
+/// The image settings, in the order the Imaging service schema declares them
+const IMAGE_SETTINGS: [&str; 4] = ["Brightness", "ColorSaturation", "Contrast", "Sharpness"];
+
+/// Makes the nonces of requests sent at the same time different
+static NONCE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Through the ONVIF Device, Media (version 1) and Imaging services
+pub(super) struct OnvifBackend;
+
+/// Where the camera services are, as the camera tells
+#[derive(Debug)]
+struct Services {
+    media: Option<String>,
+    imaging: Option<String>,
+}
+
+impl CameraBackend for OnvifBackend {
+    fn capabilities<'a>(&'a self, camera: &'a Camera) -> BoxFuture<'a, Result<Capabilities>> {
+        async move {
+            let services = services(camera).await?;
+
+            let channels = match &services.media {
+                Some(media) => encoder_configurations(camera, media).await?.len(),
+                None => 0,
+            };
+
+            Ok(Capabilities {
+                image: services.imaging.is_some(),
+                encoder: channels > 0,
+                restart: true,
+                snapshot: services.media.is_some(),
+                channels: u8::try_from(channels).unwrap_or(u8::MAX),
+            })
+        }
+        .boxed()
+    }
+
+    fn image_settings<'a>(&'a self, camera: &'a Camera) -> BoxFuture<'a, Result<ImageSettings>> {
+        async move {
+            let (imaging, video_source) = imaging_service(camera).await?;
+
+            let settings = call(
+                camera,
+                &imaging,
+                &format!(
+                    "<timg:GetImagingSettings><timg:VideoSourceToken>{}</timg:VideoSourceToken></timg:GetImagingSettings>",
+                    escape(&video_source)
+                ),
+            )
+            .await?;
+            let options = imaging_options(camera, &imaging, &video_source).await?;
+
+            image_settings(&settings, &options)
+        }
+        .boxed()
+    }
+
+    fn set_image_settings<'a>(
+        &'a self,
+        camera: &'a Camera,
+        image_settings: &'a ImageSettings,
+    ) -> BoxFuture<'a, Result<ImageSettings>> {
+        async move {
+            let (imaging, video_source) = imaging_service(camera).await?;
+            let options = imaging_options(camera, &imaging, &video_source).await?;
+
+            let percents = [
+                image_settings.brightness,
+                image_settings.saturation,
+                image_settings.contrast,
+                image_settings.sharpness,
+            ];
+
+            let mut settings = String::new();
+            for (name, percent) in IMAGE_SETTINGS.iter().zip(percents) {
+                let Some(percent) = percent else {
+                    continue;
+                };
+
+                let (min, max) = range(&options, name)?;
+                settings.push_str(&format!(
+                    "<tt:{name}>{}</tt:{name}>",
+                    from_percent(percent, min, max)
+                ));
+            }
+
+            call(
+                camera,
+                &imaging,
+                &format!(
+                    "<timg:SetImagingSettings><timg:VideoSourceToken>{}</timg:VideoSourceToken><timg:ImagingSettings>{settings}</timg:ImagingSettings><timg:ForcePersistence>true</timg:ForcePersistence></timg:SetImagingSettings>",
+                    escape(&video_source)
+                ),
+            )
+            .await?;
+
+            self.image_settings(camera).await
+        }
+        .boxed()
+    }
+
+    fn encoder_settings<'a>(
+        &'a self,
+        camera: &'a Camera,
+        channel: u8,
+    ) -> BoxFuture<'a, Result<EncoderSettings>> {
+        async move {
+            let media = media_service(camera).await?;
+            let configuration = encoder_configuration(camera, &media, channel).await?;
+            let options = encoder_options(camera, &media, &configuration).await?;
+
+            encoder_settings(channel, &configuration, &options)
+        }
+        .boxed()
+    }
+
+    fn set_encoder_settings<'a>(
+        &'a self,
+        camera: &'a Camera,
+        encoder_settings: &'a EncoderSettings,
+    ) -> BoxFuture<'a, Result<EncoderSettings>> {
+        async move {
+            let channel = encoder_settings.channel;
+            let media = media_service(camera).await?;
+            let configuration = encoder_configuration(camera, &media, channel).await?;
+            let options = encoder_options(camera, &media, &configuration).await?;
+
+            let previous = self::encoder_settings(channel, &configuration, &options)?;
+
+            let reasons = unsupported_reasons(encoder_settings, &previous);
+            if !reasons.is_empty() {
+                return Err(EncoderError::Unsupported { reasons }.into());
+            }
+
+            let replaced = replace_encoder_settings(&configuration, encoder_settings)?;
+            let token = xml::attribute(&configuration, "token")?
+                .context("Encoder configuration without a token")?;
+
+            // The configuration keeps the prefixes the camera answered, which are the usual ones
+            // declared in our envelope
+            call(
+                camera,
+                &media,
+                &format!(
+                    "<trt:SetVideoEncoderConfiguration><trt:Configuration token=\"{}\">{}</trt:Configuration><trt:ForcePersistence>true</trt:ForcePersistence></trt:SetVideoEncoderConfiguration>",
+                    escape(&token),
+                    xml::inner(&replaced)
+                ),
+            )
+            .await?;
+
+            let current = self.encoder_settings(camera, channel).await?;
+
+            follow_in_stream(camera, &previous, &current).await;
+
+            Ok(current)
+        }
+        .boxed()
+    }
+
+    fn restart<'a>(&'a self, camera: &'a Camera) -> BoxFuture<'a, Result<()>> {
+        async move {
+            call(camera, &device_service(camera), "<tds:SystemReboot/>").await?;
+
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn snapshot<'a>(&'a self, camera: &'a Camera) -> BoxFuture<'a, Result<Vec<u8>>> {
+        async move {
+            let media = media_service(camera).await?;
+
+            let profiles = call(camera, &media, "<trt:GetProfiles/>").await?;
+            let profile = xml::elements(&profiles, "Profiles")?
+                .first()
+                .map(|profile| xml::attribute(profile, "token"))
+                .transpose()?
+                .flatten()
+                .context("Camera has no media profiles")?;
+
+            let uri = call(
+                camera,
+                &media,
+                &format!(
+                    "<trt:GetSnapshotUri><trt:ProfileToken>{}</trt:ProfileToken></trt:GetSnapshotUri>",
+                    escape(&profile)
+                ),
+            )
+            .await?;
+            let uri = xml::text(&uri, &["MediaUri", "Uri"])?.context("Camera has no snapshot URI")?;
+
+            fetch_jpeg(camera, &uri).await
+        }
+        .boxed()
+    }
+}
+
+fn device_service(camera: &Camera) -> String {
+    format!("http://{}/{DEVICE_SERVICE_PATH}", camera.hostname)
+}
+
+#[instrument(level = "debug", skip(camera), fields(camera_uuid = %camera.uuid))]
+async fn services(camera: &Camera) -> Result<Services> {
+    let capabilities = call(
+        camera,
+        &device_service(camera),
+        "<tds:GetCapabilities><tds:Category>All</tds:Category></tds:GetCapabilities>",
+    )
+    .await?;
+
+    Ok(Services {
+        media: xml::text(&capabilities, &["Media", "XAddr"])?,
+        imaging: xml::text(&capabilities, &["Imaging", "XAddr"])?,
+    })
+}
+
+async fn media_service(camera: &Camera) -> Result<String> {
+    services(camera)
+        .await?
+        .media
+        .ok_or_else(|| unsupported("the Media service"))
+}
+
+/// The Imaging service, and the token of the video source it is asked about
+async fn imaging_service(camera: &Camera) -> Result<(String, String)> {
+    let services = services(camera).await?;
+    let imaging = services
+        .imaging
+        .ok_or_else(|| unsupported("the Imaging service"))?;
+    let media = services
+        .media
+        .ok_or_else(|| unsupported("the Media service"))?;
+
+    let video_sources = call(camera, &media, "<trt:GetVideoSources/>").await?;
+    let video_source = xml::elements(&video_sources, "VideoSources")?
+        .first()
+        .map(|video_source| xml::attribute(video_source, "token"))
+        .transpose()?
+        .flatten()
+        .context("Camera has no video sources")?;
+
+    Ok((imaging, video_source))
+}
+
+async fn imaging_options(camera: &Camera, imaging: &str, video_source: &str) -> Result<String> {
+    call(
+        camera,
+        imaging,
+        &format!(
+            "<timg:GetOptions><timg:VideoSourceToken>{}</timg:VideoSourceToken></timg:GetOptions>",
+            escape(video_source)
+        ),
+    )
+    .await
+}
+
+/// The range of the image setting, as the camera tells in its imaging options
+fn range(options: &str, name: &str) -> Result<(f32, f32)> {
+    match (
+        xml::number(options, &["ImagingOptions", name, "Min"])?,
+        xml::number(options, &["ImagingOptions", name, "Max"])?,
+    ) {
+        (Some(min), Some(max)) => Ok((min, max)),
+        _ => Ok(DEFAULT_RANGE),
+    }
+}
+
+fn image_settings(settings: &str, options: &str) -> Result<ImageSettings> {
+    let percent = |name: &str| -> Result<Option<u8>> {
+        let (min, max) = range(options, name)?;
+
+        Ok(xml::number(settings, &["ImagingSettings", name])?
+            .map(|value| to_percent(value, min, max)))
+    };
+
+    Ok(ImageSettings {
+        brightness: percent("Brightness")?,
+        contrast: percent("Contrast")?,
+        saturation: percent("ColorSaturation")?,
+        sharpness: percent("Sharpness")?,
+    })
+}
+
+async fn encoder_configurations(camera: &Camera, media: &str) -> Result<Vec<String>> {
+    let configurations = call(camera, media, "<trt:GetVideoEncoderConfigurations/>").await?;
+
+    Ok(xml::elements(&configurations, "Configurations")?
+        .into_iter()
+        .map(str::to_string)
+        .collect())
+}
+
+/// The channels are the encoder configurations, in the order the camera answers them
+async fn encoder_configuration(camera: &Camera, media: &str, channel: u8) -> Result<String> {
+    encoder_configurations(camera, media)
+        .await?
+        .into_iter()
+        .nth(channel.into())
+        .ok_or_else(|| {
+            EncoderError::Unsupported {
+                reasons: vec![format!("Camera has no channel {channel}")],
+            }
+            .into()
+        })
+}
+
+/// The RTSP address of the channel, from the media profile that uses its encoder configuration
+pub(super) async fn stream_uri(camera: &Camera, channel: u8) -> Result<String> {
+    let media = media_service(camera).await?;
+    let configuration = encoder_configuration(camera, &media, channel).await?;
+    let encoder_token = xml::attribute(&configuration, "token")?
+        .context("Encoder configuration without a token")?;
+
+    let profiles = call(camera, &media, "<trt:GetProfiles/>").await?;
+    let profile = profile_token(&profiles, &encoder_token)?
+        .with_context(|| format!("Camera has no media profile for channel {channel}"))?;
+
+    let uri = call(
+        camera,
+        &media,
+        &format!(
+            "<trt:GetStreamUri><trt:StreamSetup><tt:Stream>RTP-Unicast</tt:Stream><tt:Transport><tt:Protocol>RTSP</tt:Protocol></tt:Transport></trt:StreamSetup><trt:ProfileToken>{}</trt:ProfileToken></trt:GetStreamUri>",
+            escape(&profile)
+        ),
+    )
+    .await?;
+
+    xml::text(&uri, &["MediaUri", "Uri"])?.context("Camera has no stream URI")
+}
+
+/// The token of the first media profile that uses the encoder configuration
+fn profile_token(profiles: &str, encoder_token: &str) -> Result<Option<String>> {
+    for profile in xml::elements(profiles, "Profiles")? {
+        let token = xml::elements(profile, "VideoEncoderConfiguration")?
+            .first()
+            .map(|configuration| xml::attribute(configuration, "token"))
+            .transpose()?
+            .flatten();
+
+        if token.as_deref() == Some(encoder_token) {
+            return xml::attribute(profile, "token");
+        }
+    }
+
+    Ok(None)
+}
+
+async fn encoder_options(camera: &Camera, media: &str, configuration: &str) -> Result<String> {
+    let token =
+        xml::attribute(configuration, "token")?.context("Encoder configuration without a token")?;
+
+    call(
+        camera,
+        media,
+        &format!(
+            "<trt:GetVideoEncoderConfigurationOptions><trt:ConfigurationToken>{}</trt:ConfigurationToken></trt:GetVideoEncoderConfigurationOptions>",
+            escape(&token)
+        ),
+    )
+    .await
+}
+
+fn encoding(name: &str) -> Option<Encoding> {
+    match name {
+        "H264" => Some(Encoding::H264),
+        "H265" => Some(Encoding::H265),
+        "JPEG" => Some(Encoding::Mjpeg),
+        _ => None,
+    }
+}
+
+fn encoding_name(encoding: Encoding) -> &'static str {
+    match encoding {
+        Encoding::H264 => "H264",
+        Encoding::H265 => "H265",
+        Encoding::Mjpeg => "JPEG",
+    }
+}
+
+fn encoder_settings(channel: u8, configuration: &str, options: &str) -> Result<EncoderSettings> {
+    let encoding_name = xml::text(configuration, &["Encoding"])?;
+
+    // The options are grouped by encoding
+    let (resolutions, max_frame_rate) = match &encoding_name {
+        Some(encoding_name) => {
+            let widths = xml::texts(options, &[encoding_name, "ResolutionsAvailable", "Width"])?;
+            let heights = xml::texts(options, &[encoding_name, "ResolutionsAvailable", "Height"])?;
+
+            let resolutions = widths
+                .iter()
+                .zip(heights.iter())
+                .filter_map(|(width, height)| {
+                    Some(VideoResolutionValue {
+                        width: width.parse().ok()?,
+                        height: height.parse().ok()?,
+                    })
+                })
+                .collect();
+
+            let max_frame_rate = xml::number(options, &[encoding_name, "FrameRateRange", "Max"])?
+                .map(|max| max as u16);
+
+            (resolutions, max_frame_rate)
+        }
+        None => (vec![], None),
+    };
+
+    let number = |path: &[&str]| -> Result<Option<f32>> { xml::number(configuration, path) };
+
+    Ok(EncoderSettings {
+        channel,
+        encoding: encoding_name.as_deref().and_then(encoding),
+        width: number(&["Resolution", "Width"])?.map(|width| width as u16),
+        height: number(&["Resolution", "Height"])?.map(|height| height as u16),
+        frame_rate: number(&["RateControl", "FrameRateLimit"])?.map(|frame_rate| frame_rate as u16),
+        bitrate: number(&["RateControl", "BitrateLimit"])?.map(|bitrate| bitrate as u32),
+        resolutions,
+        max_frame_rate,
+    })
+}
+
+fn replace_encoder_settings(
+    configuration: &str,
+    encoder_settings: &EncoderSettings,
+) -> Result<String> {
+    let mut replacements: Vec<(&[&str], String)> = Vec::new();
+
+    if let Some(encoding) = encoder_settings.encoding {
+        replacements.push((&["Encoding"], encoding_name(encoding).to_string()));
+    }
+    if let Some(width) = encoder_settings.width {
+        replacements.push((&["Resolution", "Width"], width.to_string()));
+    }
+    if let Some(height) = encoder_settings.height {
+        replacements.push((&["Resolution", "Height"], height.to_string()));
+    }
+    if let Some(frame_rate) = encoder_settings.frame_rate {
+        replacements.push((&["RateControl", "FrameRateLimit"], frame_rate.to_string()));
+    }
+    if let Some(bitrate) = encoder_settings.bitrate {
+        replacements.push((&["RateControl", "BitrateLimit"], bitrate.to_string()));
+    }
+
+    xml::replace_texts(configuration, &replacements)
+}
+
+/// Sends the SOAP request, answering the SOAP body
+#[instrument(level = "debug", skip(camera), fields(camera_uuid = %camera.uuid))]
+async fn call(camera: &Camera, url: &str, body: &str) -> Result<String> {
+    let response = reqwest::Client::new()
+        .post(url)
+        .timeout(TIMEOUT)
+        .header(header::CONTENT_TYPE, "application/soap+xml; charset=utf-8")
+        .body(envelope(camera.credentials.as_ref(), body))
+        .send()
+        .await
+        .inspect_err(|error| {
+            warn!("Error from call(): {error:#?}");
+        })?;
+
+    let status = response.status();
+    let content = response.text().await?;
+
+    if status.is_success() {
+        return Ok(content);
+    }
+
+    if status == StatusCode::UNAUTHORIZED
+        || status == StatusCode::FORBIDDEN
+        || content.contains("NotAuthorized")
+    {
+        return Err(unauthorized(camera));
+    }
+
+    let reason = xml::text(&content, &["Reason", "Text"])
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| format!("HTTP {status}"));
+
+    Err(anyhow!("Camera failed answering {body:?}: {reason}"))
+}
+
+async fn fetch_jpeg(camera: &Camera, uri: &str) -> Result<Vec<u8>> {
+    let mut request_builder = reqwest::Client::new()
+        .get(uri)
+        .timeout(TIMEOUT)
+        .header(header::ACCEPT, "image/jpeg");
+
+    if let Some(Credentials { username, password }) = &camera.credentials {
+        request_builder = request_builder.basic_auth(username, Some(password));
+    }
+
+    let response = request_builder.send().await.inspect_err(|error| {
+        warn!("Error from fetch_jpeg(): {error:#?}");
+    })?;
+
+    let status = response.status();
+    let content = response.bytes().await?;
+
+    match status {
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Err(unauthorized(camera)),
+        status if !status.is_success() => {
+            Err(anyhow!("Camera failed answering a snapshot: HTTP {status}"))
+        }
+        _ if !content.starts_with(&JPEG_SOI) => {
+            Err(anyhow!("Camera answered a snapshot that is not a JPEG"))
+        }
+        _ => Ok(content.to_vec()),
+    }
+}
+
+fn unauthorized(camera: &Camera) -> anyhow::Error {
+    match &camera.credentials {
+        Some(Credentials { username, .. }) => CameraError::Unauthorized {
+            username: username.clone(),
+        }
+        .into(),
+        None => anyhow!("Camera requires authentication, but it has no credentials"),
+    }
+}
+
+fn unsupported(what: &str) -> anyhow::Error {
+    BackendError::Unsupported {
+        kind: CameraKind::Onvif,
+        what: what.to_string(),
+    }
+    .into()
+}
+
+fn envelope(credentials: Option<&Credentials>, body: &str) -> String {
+    let security = credentials
+        .map(|Credentials { username, password }| {
+            let nonce = nonce();
+            let created = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string();
+
+            format!(
+                concat!(
+                    r#"<wsse:Security s:mustUnderstand="1" xmlns:wsse="http://docs.oasis-open.org/wss/2004/01/oasis-200401-wss-wssecurity-secext-1.0.xsd" xmlns:wsu="http://docs.oasis-open.org/wss/2004/01/oasis-200401-wss-wssecurity-utility-1.0.xsd">"#,
+                    r#"<wsse:UsernameToken><wsse:Username>{}</wsse:Username>"#,
+                    r#"<wsse:Password Type="http://docs.oasis-open.org/wss/2004/01/oasis-200401-wss-username-token-profile-1.0#PasswordDigest">{}</wsse:Password>"#,
+                    r#"<wsse:Nonce EncodingType="http://docs.oasis-open.org/wss/2004/01/oasis-200401-soap-message-security-1.0#Base64Binary">{}</wsse:Nonce>"#,
+                    r#"<wsu:Created>{}</wsu:Created></wsse:UsernameToken></wsse:Security>"#,
+                ),
+                escape(username),
+                password_digest(&nonce, &created, password),
+                BASE64.encode(nonce),
+                created,
+            )
+        })
+        .unwrap_or_default();
+
+    format!(
+        concat!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+            r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:tds="http://www.onvif.org/ver10/device/wsdl" xmlns:trt="http://www.onvif.org/ver10/media/wsdl" xmlns:timg="http://www.onvif.org/ver20/imaging/wsdl" xmlns:tt="http://www.onvif.org/ver10/schema">"#,
+            r#"<s:Header>{}</s:Header><s:Body>{}</s:Body></s:Envelope>"#,
+        ),
+        security, body,
+    )
+}
+
+/// The WS-Security digest: Base64(SHA-1(nonce + created + password))
+fn password_digest(nonce: &[u8], created: &str, password: &str) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(nonce);
+    hasher.update(created.as_bytes());
+    hasher.update(password.as_bytes());
+
+    BASE64.encode(hasher.finalize())
+}
+
+/// Only has to be unique, not secret, as the digest already hides the password
+fn nonce() -> Vec<u8> {
+    let mut hasher = Sha1::new();
+    hasher.update(
+        chrono::Utc::now()
+            .timestamp_nanos_opt()
+            .unwrap_or_default()
+            .to_le_bytes(),
+    );
+    hasher.update(NONCE_COUNTER.fetch_add(1, Ordering::Relaxed).to_le_bytes());
+
+    hasher.finalize()[..16].to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn password_digest_test() {
+        let nonce = BASE64.decode("LKqI6G/AikKCQrN0zqZFlg==").unwrap();
+
+        assert_eq!(
+            password_digest(&nonce, "2010-09-16T07:50:45Z", "userpassword"),
+            "tuOSpGlFlIXsozq4HFNeeGeFLEI="
+        );
+    }
+
+    #[test]
+    fn encoder_settings_test() {
+        let configuration = r#"<trt:Configurations token="main">
+            <tt:Encoding>H264</tt:Encoding>
+            <tt:Resolution><tt:Width>1920</tt:Width><tt:Height>1080</tt:Height></tt:Resolution>
+            <tt:RateControl><tt:FrameRateLimit>25</tt:FrameRateLimit><tt:BitrateLimit>4096</tt:BitrateLimit></tt:RateControl>
+        </trt:Configurations>"#;
+        let options = r#"<trt:Options>
+            <tt:JPEG><tt:ResolutionsAvailable><tt:Width>640</tt:Width><tt:Height>480</tt:Height></tt:ResolutionsAvailable></tt:JPEG>
+            <tt:H264>
+                <tt:ResolutionsAvailable><tt:Width>1920</tt:Width><tt:Height>1080</tt:Height></tt:ResolutionsAvailable>
+                <tt:ResolutionsAvailable><tt:Width>1280</tt:Width><tt:Height>720</tt:Height></tt:ResolutionsAvailable>
+                <tt:FrameRateRange><tt:Min>1</tt:Min><tt:Max>30</tt:Max></tt:FrameRateRange>
+            </tt:H264>
+        </trt:Options>"#;
+
+        let settings = encoder_settings(0, configuration, options).unwrap();
+        assert_eq!(
+            settings,
+            EncoderSettings {
+                channel: 0,
+                encoding: Some(Encoding::H264),
+                width: Some(1920),
+                height: Some(1080),
+                frame_rate: Some(25),
+                bitrate: Some(4096),
+                resolutions: vec![
+                    VideoResolutionValue {
+                        width: 1920,
+                        height: 1080
+                    },
+                    VideoResolutionValue {
+                        width: 1280,
+                        height: 720
+                    },
+                ],
+                max_frame_rate: Some(30),
+            }
+        );
+
+        let replaced = replace_encoder_settings(
+            configuration,
+            &EncoderSettings {
+                width: Some(1280),
+                height: Some(720),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let settings = encoder_settings(0, &replaced, options).unwrap();
+        assert_eq!((settings.width, settings.height), (Some(1280), Some(720)));
+        assert_eq!(settings.frame_rate, Some(25));
+    }
+
+    #[test]
+    fn profile_token_test() {
+        let profiles = r#"<trt:GetProfilesResponse>
+            <trt:Profiles token="profile_main" fixed="true">
+                <tt:Name>main</tt:Name>
+                <tt:VideoEncoderConfiguration token="encoder_main"><tt:Name>main</tt:Name></tt:VideoEncoderConfiguration>
+            </trt:Profiles>
+            <trt:Profiles token="profile_sub" fixed="true">
+                <tt:VideoEncoderConfiguration token="encoder_sub"/>
+            </trt:Profiles>
+        </trt:GetProfilesResponse>"#;
+
+        assert_eq!(
+            profile_token(profiles, "encoder_sub").unwrap().as_deref(),
+            Some("profile_sub")
+        );
+        assert_eq!(profile_token(profiles, "encoder_third").unwrap(), None);
+    }
+
+    #[test]
+    fn image_settings_test() {
+        let settings = r#"<timg:ImagingSettings>
+            <tt:Brightness>128</tt:Brightness>
+            <tt:ColorSaturation>50</tt:ColorSaturation>
+        </timg:ImagingSettings>"#;
+        let options = r#"<timg:ImagingOptions>
+            <tt:Brightness><tt:Min>0</tt:Min><tt:Max>255</tt:Max></tt:Brightness>
+        </timg:ImagingOptions>"#;
+
+        assert_eq!(
+            image_settings(settings, options).unwrap(),
+            ImageSettings {
+                brightness: Some(50),
+                contrast: None,
+                saturation: Some(50),
+                sharpness: None,
+            }
+        );
+    }
+}
@@ -0,0 +1,261 @@
+use anyhow::Result;
+use axum::Json;
+use futures::{FutureExt, future::BoxFuture};
+use mcm_client::Camera;
+
+use super::{
+    CameraBackend, Capabilities, EncoderSettings, Encoding, ImageSettings, from_percent, to_percent,
+};
+use crate::{
+    Action, CameraControl, client, control_inner,
+    encoder::{self, EncoderError},
+    get_image_adjustment, get_video_parameters,
+    protocol::{
+        display::base_display::BaseParameterSetting,
+        video::video_parameters::{
+            VideoChannelValue, VideoEncodeTypeValue, VideoParameterSettings,
+        },
+    },
+};
+
+/// The RadCam image settings range
+const RANGE: (f32, f32) = (0., 255.);
+
+/// Through the RadCam CGI actions
+pub(super) struct RadCamBackend;
+
+impl CameraBackend for RadCamBackend {
+    fn capabilities<'a>(&'a self, _camera: &'a Camera) -> BoxFuture<'a, Result<Capabilities>> {
+        async move {
+            Ok(Capabilities {
+                image: true,
+                encoder: true,
+                restart: true,
+                snapshot: true,
+                channels: 3,
+            })
+        }
+        .boxed()
+    }
+
+    fn image_settings<'a>(&'a self, camera: &'a Camera) -> BoxFuture<'a, Result<ImageSettings>> {
+        async move {
+            let base_parameters = get_image_adjustment(&camera.uuid).await?;
+
+            Ok(image_settings(&base_parameters))
+        }
+        .boxed()
+    }
+
+    fn set_image_settings<'a>(
+        &'a self,
+        camera: &'a Camera,
+        image_settings: &'a ImageSettings,
+    ) -> BoxFuture<'a, Result<ImageSettings>> {
+        async move {
+            let base_parameters = base_parameters(image_settings);
+
+            let res = control_inner(Json(CameraControl {
+                camera_uuid: camera.uuid,
+                action: Action::SetImageAdjustment(base_parameters),
+            }))
+            .await?;
+
+            Ok(self::image_settings(&serde_json::from_value(res)?))
+        }
+        .boxed()
+    }
+
+    fn encoder_settings<'a>(
+        &'a self,
+        camera: &'a Camera,
+        channel: u8,
+    ) -> BoxFuture<'a, Result<EncoderSettings>> {
+        async move {
+            let video_parameters =
+                get_video_parameters(&camera.uuid, video_channel(channel)?).await?;
+
+            Ok(encoder_settings(channel, &video_parameters))
+        }
+        .boxed()
+    }
+
+    fn set_encoder_settings<'a>(
+        &'a self,
+        camera: &'a Camera,
+        encoder_settings: &'a EncoderSettings,
+    ) -> BoxFuture<'a, Result<EncoderSettings>> {
+        async move {
+            let video_parameters = video_parameters(encoder_settings)?;
+
+            let video_parameters =
+                encoder::set_video_parameters(&camera.uuid, &video_parameters).await?;
+
+            Ok(self::encoder_settings(
+                encoder_settings.channel,
+                &video_parameters,
+            ))
+        }
+        .boxed()
+    }
+
+    fn restart<'a>(&'a self, camera: &'a Camera) -> BoxFuture<'a, Result<()>> {
+        async move {
+            control_inner(Json(CameraControl {
+                camera_uuid: camera.uuid,
+                action: Action::Restart,
+            }))
+            .await?;
+
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn snapshot<'a>(&'a self, camera: &'a Camera) -> BoxFuture<'a, Result<Vec<u8>>> {
+        client::fetch_snapshot(&camera.uuid).boxed()
+    }
+}
+
+/// The image settings every camera has, out of the RadCam ones
+pub(crate) fn image_settings(base_parameters: &BaseParameterSetting) -> ImageSettings {
+    let scale = |value: Option<u8>| value.map(|value| to_percent(value.into(), RANGE.0, RANGE.1));
+
+    ImageSettings {
+        brightness: scale(base_parameters.brightness),
+        contrast: scale(base_parameters.contrast),
+        saturation: scale(base_parameters.saturation),
+        sharpness: scale(base_parameters.sharpness),
+    }
+}
+
+/// The RadCam image settings, with only the ones every camera has
+pub(crate) fn base_parameters(image_settings: &ImageSettings) -> BaseParameterSetting {
+    let scale = |percent: Option<u8>| {
+        percent.map(|percent| from_percent(percent, RANGE.0, RANGE.1).round() as u8)
+    };
+
+    BaseParameterSetting {
+        brightness: scale(image_settings.brightness),
+        contrast: scale(image_settings.contrast),
+        saturation: scale(image_settings.saturation),
+        sharpness: scale(image_settings.sharpness),
+        ..Default::default()
+    }
+}
+
+fn video_channel(channel: u8) -> Result<VideoChannelValue> {
+    match channel {
+        0 => Ok(VideoChannelValue::MainStream),
+        1 => Ok(VideoChannelValue::AuxiliaryStream),
+        2 => Ok(VideoChannelValue::ThirdStream),
+        _ => Err(EncoderError::Unsupported {
+            reasons: vec![format!("RadCams have no channel {channel}")],
+        }
+        .into()),
+    }
+}
+
+/// The RadCam encoder settings of the channel, as every backend has them
+pub(crate) fn encoder_settings(
+    channel: u8,
+    video_parameters: &VideoParameterSettings,
+) -> EncoderSettings {
+    EncoderSettings {
+        channel,
+        encoding: video_parameters
+            .encode_type
+            .as_ref()
+            .map(|encode_type| match encode_type {
+                VideoEncodeTypeValue::H264 => Encoding::H264,
+                VideoEncodeTypeValue::H265 => Encoding::H265,
+            }),
+        width: video_parameters.pic_width,
+        height: video_parameters.pic_height,
+        frame_rate: video_parameters.frame_rate,
+        bitrate: video_parameters.bitrate.map(u32::from),
+        resolutions: video_parameters.pixel_list.clone().unwrap_or_default(),
+        max_frame_rate: video_parameters.max_framerate,
+    }
+}
+
+/// The RadCam encoder settings to set, leaving out what the channel supports
+pub(crate) fn video_parameters(
+    encoder_settings: &EncoderSettings,
+) -> Result<VideoParameterSettings> {
+    let encode_type = match encoder_settings.encoding {
+        Some(Encoding::H264) => Some(VideoEncodeTypeValue::H264),
+        Some(Encoding::H265) => Some(VideoEncodeTypeValue::H265),
+        Some(Encoding::Mjpeg) => {
+            return Err(EncoderError::Unsupported {
+                reasons: vec!["RadCam encoder settings have no MJPEG".to_string()],
+            }
+            .into());
+        }
+        None => None,
+    };
+
+    let bitrate = encoder_settings
+        .bitrate
+        .map(|bitrate| {
+            u16::try_from(bitrate).map_err(|_| EncoderError::Unsupported {
+                reasons: vec![format!("RadCams support up to {} kbps", u16::MAX)],
+            })
+        })
+        .transpose()?;
+
+    Ok(VideoParameterSettings {
+        channel: Some(video_channel(encoder_settings.channel)?),
+        encode_type,
+        pic_width: encoder_settings.width,
+        pic_height: encoder_settings.height,
+        frame_rate: encoder_settings.frame_rate,
+        bitrate,
+        ..Default::default()
+    })
+}
+
+/// The RadCam encoder settings answered, with what the channel supports
+pub(crate) fn answered_video_parameters(
+    encoder_settings: &EncoderSettings,
+) -> Result<VideoParameterSettings> {
+    Ok(VideoParameterSettings {
+        pixel_list: Some(encoder_settings.resolutions.clone()),
+        max_framerate: encoder_settings.max_frame_rate,
+        ..video_parameters(encoder_settings)?
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn video_parameters_test() {
+        let settings = EncoderSettings {
+            channel: 1,
+            encoding: Some(Encoding::H265),
+            width: Some(1280),
+            height: Some(720),
+            frame_rate: Some(30),
+            bitrate: Some(2048),
+            ..Default::default()
+        };
+
+        let parameters = video_parameters(&settings).unwrap();
+        assert_eq!(parameters.channel, Some(VideoChannelValue::AuxiliaryStream));
+        assert_eq!(
+            encoder_settings(1, &parameters),
+            EncoderSettings {
+                resolutions: vec![],
+                ..settings.clone()
+            }
+        );
+
+        let settings = EncoderSettings {
+            encoding: Some(Encoding::Mjpeg),
+            ..settings
+        };
+        assert!(video_parameters(&settings).is_err());
+    }
+}
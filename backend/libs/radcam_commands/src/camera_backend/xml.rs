@@ -0,0 +1,256 @@
+//! Just enough XML for the ONVIF answers. Elements are matched by their local names, ignoring the
+//! namespace prefixes, which each camera picks differently
+
+use anyhow::{Context, Result};
+use quick_xml::{
+    Reader, Writer,
+    events::{BytesText, Event},
+};
+
+/// The text of every element at the end of `path`, like `["Resolution", "Width"]`
+pub(super) fn texts(xml: &str, path: &[&str]) -> Result<Vec<String>> {
+    let mut reader = Reader::from_str(xml);
+    let mut stack: Vec<String> = Vec::new();
+    let mut texts = Vec::new();
+
+    loop {
+        match reader.read_event().context("Invalid XML")? {
+            Event::Start(start) => stack.push(local_name(start.local_name().as_ref())),
+            Event::End(_) => {
+                stack.pop();
+            }
+            Event::Text(text) if ends_with(&stack, path) => {
+                texts.push(
+                    text.unescape()
+                        .context("Invalid XML text")?
+                        .trim()
+                        .to_string(),
+                );
+            }
+            Event::Eof => break,
+            _ => (),
+        }
+    }
+
+    Ok(texts)
+}
+
+/// The text of the first element at the end of `path`
+pub(super) fn text(xml: &str, path: &[&str]) -> Result<Option<String>> {
+    Ok(texts(xml, path)?.into_iter().next())
+}
+
+/// Like `text`, but as a number
+pub(super) fn number(xml: &str, path: &[&str]) -> Result<Option<f32>> {
+    text(xml, path)?
+        .map(|text| {
+            text.parse::<f32>()
+                .with_context(|| format!("Invalid number {text:?} in {path:?}"))
+        })
+        .transpose()
+}
+
+/// Each element named `name`, with its tags, in order. Elements inside the ones found are not
+/// searched
+pub(super) fn elements<'a>(xml: &'a str, name: &str) -> Result<Vec<&'a str>> {
+    let mut reader = Reader::from_str(xml);
+    let mut elements = Vec::new();
+    // Where the element being read starts, and how deep it is
+    let mut start: Option<(usize, usize)> = None;
+    let mut depth = 0;
+
+    loop {
+        let position = reader.buffer_position() as usize;
+
+        match reader.read_event().context("Invalid XML")? {
+            Event::Start(element) => {
+                if start.is_none() && local_name(element.local_name().as_ref()) == name {
+                    start = Some((position, depth));
+                }
+
+                depth += 1;
+            }
+            Event::Empty(element)
+                if start.is_none() && local_name(element.local_name().as_ref()) == name =>
+            {
+                elements.push(&xml[position..reader.buffer_position() as usize]);
+            }
+            Event::End(_) => {
+                depth -= 1;
+
+                match start {
+                    Some((element_start, element_depth)) if element_depth == depth => {
+                        elements.push(&xml[element_start..reader.buffer_position() as usize]);
+                        start = None;
+                    }
+                    _ => (),
+                }
+            }
+            Event::Eof => break,
+            _ => (),
+        }
+    }
+
+    Ok(elements)
+}
+
+/// The value of the attribute of the element's opening tag
+pub(super) fn attribute(element: &str, name: &str) -> Result<Option<String>> {
+    let mut reader = Reader::from_str(element);
+
+    loop {
+        match reader.read_event().context("Invalid XML")? {
+            Event::Start(start) | Event::Empty(start) => {
+                for attribute in start.attributes() {
+                    let attribute = attribute.context("Invalid XML attribute")?;
+
+                    if local_name(attribute.key.local_name().as_ref()) == name {
+                        let value = attribute
+                            .unescape_value()
+                            .context("Invalid XML attribute")?;
+
+                        return Ok(Some(value.to_string()));
+                    }
+                }
+
+                return Ok(None);
+            }
+            Event::Eof => return Ok(None),
+            _ => (),
+        }
+    }
+}
+
+/// What is between the element's opening and closing tags
+pub(super) fn inner(element: &str) -> &str {
+    let start = element.find('>').map(|index| index + 1).unwrap_or(0);
+    let end = element.rfind("</").unwrap_or(element.len()).max(start);
+
+    &element[start..end]
+}
+
+/// Replaces the text of the first element at the end of each path. Paths that are not found are
+/// ignored, as the cameras leave out the optional elements
+pub(super) fn replace_texts(xml: &str, replacements: &[(&[&str], String)]) -> Result<String> {
+    let mut reader = Reader::from_str(xml);
+    let mut writer = Writer::new(Vec::new());
+    let mut stack: Vec<String> = Vec::new();
+    let mut replaced = vec![false; replacements.len()];
+
+    loop {
+        let event = reader.read_event().context("Invalid XML")?;
+
+        match &event {
+            Event::Start(start) => stack.push(local_name(start.local_name().as_ref())),
+            Event::End(_) => {
+                stack.pop();
+            }
+            Event::Text(_) => {
+                let replacement = replacements
+                    .iter()
+                    .enumerate()
+                    .find(|(index, (path, _))| !replaced[*index] && ends_with(&stack, path));
+
+                if let Some((index, (_, value))) = replacement {
+                    replaced[index] = true;
+                    writer.write_event(Event::Text(BytesText::new(value)))?;
+                    continue;
+                }
+            }
+            Event::Eof => break,
+            _ => (),
+        }
+
+        writer.write_event(event)?;
+    }
+
+    String::from_utf8(writer.into_inner()).context("Invalid XML")
+}
+
+fn local_name(name: &[u8]) -> String {
+    String::from_utf8_lossy(name).to_string()
+}
+
+fn ends_with(stack: &[String], path: &[&str]) -> bool {
+    stack.len() >= path.len()
+        && stack[stack.len() - path.len()..]
+            .iter()
+            .zip(path)
+            .all(|(name, expected)| name == expected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIGURATIONS: &str = r#"<trt:GetVideoEncoderConfigurationsResponse>
+        <trt:Configurations token="main">
+            <tt:Name>Main</tt:Name>
+            <tt:Encoding>H264</tt:Encoding>
+            <tt:Resolution><tt:Width>1920</tt:Width><tt:Height>1080</tt:Height></tt:Resolution>
+            <tt:RateControl><tt:FrameRateLimit>25</tt:FrameRateLimit></tt:RateControl>
+        </trt:Configurations>
+        <trt:Configurations token="sub">
+            <tt:Name>Sub</tt:Name>
+            <tt:Encoding>H265</tt:Encoding>
+            <tt:Resolution><tt:Width>640</tt:Width><tt:Height>360</tt:Height></tt:Resolution>
+        </trt:Configurations>
+    </trt:GetVideoEncoderConfigurationsResponse>"#;
+
+    #[test]
+    fn texts_test() {
+        assert_eq!(
+            texts(CONFIGURATIONS, &["Resolution", "Width"]).unwrap(),
+            vec!["1920".to_string(), "640".to_string()]
+        );
+        assert_eq!(
+            number(CONFIGURATIONS, &["RateControl", "FrameRateLimit"]).unwrap(),
+            Some(25.)
+        );
+        assert_eq!(text(CONFIGURATIONS, &["Bitrate"]).unwrap(), None);
+    }
+
+    #[test]
+    fn elements_test() {
+        let configurations = elements(CONFIGURATIONS, "Configurations").unwrap();
+        assert_eq!(configurations.len(), 2);
+
+        assert_eq!(
+            attribute(configurations[1], "token").unwrap(),
+            Some("sub".to_string())
+        );
+        assert_eq!(
+            text(configurations[1], &["Encoding"]).unwrap(),
+            Some("H265".to_string())
+        );
+        assert!(inner(configurations[0]).trim().starts_with("<tt:Name>Main"));
+    }
+
+    #[test]
+    fn replace_texts_test() {
+        let configuration = elements(CONFIGURATIONS, "Configurations").unwrap()[0];
+
+        let replaced = replace_texts(
+            configuration,
+            &[
+                (&["Resolution", "Width"], "1280".to_string()),
+                (&["Resolution", "Height"], "720".to_string()),
+                (&["Quality"], "5".to_string()),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(
+            text(&replaced, &["Resolution", "Width"]).unwrap(),
+            Some("1280".to_string())
+        );
+        assert_eq!(
+            text(&replaced, &["Resolution", "Height"]).unwrap(),
+            Some("720".to_string())
+        );
+        assert_eq!(
+            text(&replaced, &["Encoding"]).unwrap(),
+            Some("H264".to_string())
+        );
+    }
+}
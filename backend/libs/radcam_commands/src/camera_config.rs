@@ -0,0 +1,342 @@
+use anyhow::{Context, Result};
+use axum::Json;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use tracing::*;
+use ts_rs::TS;
+use uuid::Uuid;
+
+use crate::{
+    Action, CameraControl,
+    camera_backend::{radcam, unsupported_reasons},
+    control_inner, get_image_adjustment, get_image_adjustment_ex, get_sys_config,
+    get_video_parameters,
+    protocol::{
+        display::{advanced_display::AdvancedParameterSetting, base_display::BaseParameterSetting},
+        video::video_parameters::{VideoChannelValue, VideoParameterSettings},
+    },
+};
+
+/// The version of the exported document, bumped whenever older documents can't be imported as they
+/// are
+pub const CAMERA_CONFIG_VERSION: u32 = 1;
+
+const VIDEO_CHANNELS: [VideoChannelValue; 3] = [
+    VideoChannelValue::MainStream,
+    VideoChannelValue::AuxiliaryStream,
+    VideoChannelValue::ThirdStream,
+];
+
+/// Every setting of a camera, to be restored to it or to another camera
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+pub struct CameraConfig {
+    pub version: u32,
+    /// When it was exported, in RFC 3339
+    pub exported_at: String,
+    /// The camera it was exported from
+    #[ts(as = "String")]
+    pub camera_uuid: Uuid,
+    pub model: Option<String>,
+    pub firmware: Option<String>,
+    pub image: BaseParameterSetting,
+    pub advanced_image: AdvancedParameterSetting,
+    /// One for each channel the camera has
+    pub video: Vec<VideoParameterSettings>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CameraConfigError {
+    UnsupportedVersion {
+        version: u32,
+    },
+    /// The document doesn't fit the camera it is imported to
+    Invalid {
+        reasons: Vec<String>,
+    },
+}
+
+impl std::fmt::Display for CameraConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            CameraConfigError::UnsupportedVersion { version } => write!(
+                f,
+                "Camera configuration version {version} is not supported, expected {CAMERA_CONFIG_VERSION}"
+            ),
+            CameraConfigError::Invalid { reasons } => {
+                write!(f, "Invalid camera configuration: {}", reasons.join("; "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for CameraConfigError {}
+
+#[instrument(level = "debug")]
+pub async fn export_camera_config(camera_uuid: &Uuid) -> Result<CameraConfig> {
+    let sys_config = get_sys_config(camera_uuid).await?;
+    let image = get_image_adjustment(camera_uuid).await?;
+    let advanced_image = get_image_adjustment_ex(camera_uuid).await?;
+
+    let mut video = Vec::new();
+    for channel in VIDEO_CHANNELS {
+        match get_video_parameters(camera_uuid, channel.clone()).await {
+            Ok(video_parameters) => video.push(video_parameters),
+            // Not every camera has all channels, but all of them have the main one
+            Err(error) if channel != VideoChannelValue::MainStream => {
+                debug!("Skipping video channel {channel:?}: {error:#}");
+            }
+            Err(error) => return Err(error),
+        }
+    }
+
+    Ok(CameraConfig {
+        version: CAMERA_CONFIG_VERSION,
+        exported_at: Utc::now().to_rfc3339(),
+        camera_uuid: *camera_uuid,
+        model: sys_config.model,
+        firmware: sys_config.firmware,
+        image,
+        advanced_image,
+        video,
+    })
+}
+
+/// Applies an exported document to the camera, answering its settings afterwards
+#[instrument(level = "debug", skip(camera_config))]
+pub async fn import_camera_config(
+    camera_uuid: &Uuid,
+    camera_config: &CameraConfig,
+) -> Result<CameraConfig> {
+    if camera_config.version != CAMERA_CONFIG_VERSION {
+        return Err(CameraConfigError::UnsupportedVersion {
+            version: camera_config.version,
+        }
+        .into());
+    }
+
+    let sys_config = get_sys_config(camera_uuid).await?;
+    if camera_config.model.is_some() && camera_config.model != sys_config.model {
+        warn!(
+            "Importing a configuration from a {:?} camera into a {:?} one",
+            camera_config.model, sys_config.model
+        );
+    }
+
+    let mut current_video = Vec::new();
+    for video_parameters in &camera_config.video {
+        let Some(channel) = &video_parameters.channel else {
+            current_video.push(None);
+            continue;
+        };
+
+        current_video.push(
+            get_video_parameters(camera_uuid, channel.clone())
+                .await
+                .ok(),
+        );
+    }
+
+    let reasons = validate_video(&camera_config.video, &current_video);
+    if !reasons.is_empty() {
+        return Err(CameraConfigError::Invalid { reasons }.into());
+    }
+
+    apply_settings(
+        camera_uuid,
+        &camera_config.video,
+        Some(&camera_config.image),
+        Some(&camera_config.advanced_image),
+    )
+    .await?;
+
+    info!(
+        "Camera {camera_uuid} configuration imported from camera {}",
+        camera_config.camera_uuid
+    );
+
+    export_camera_config(camera_uuid).await
+}
+
+/// Sends the settings to the camera. The encoders go first because changing them may restart the
+/// image pipeline, then the base image settings, which the advanced ones refine. The fields that
+/// trigger an action are left out
+#[instrument(level = "debug", skip(video, image, advanced_image))]
+pub async fn apply_settings(
+    camera_uuid: &Uuid,
+    video: &[VideoParameterSettings],
+    image: Option<&BaseParameterSetting>,
+    advanced_image: Option<&AdvancedParameterSetting>,
+) -> Result<()> {
+    let reasons = channel_reasons(video);
+    if !reasons.is_empty() {
+        return Err(CameraConfigError::Invalid { reasons }.into());
+    }
+
+    for video_parameters in video {
+        // The supported resolutions and frame rates can't be set
+        apply(
+            camera_uuid,
+            Action::SetVideoParameterSettings(VideoParameterSettings {
+                pixel_list: None,
+                max_framerate: None,
+                ..video_parameters.clone()
+            }),
+        )
+        .await
+        .with_context(|| {
+            format!(
+                "Failed applying video channel {:?}",
+                video_parameters.channel
+            )
+        })?;
+    }
+
+    if let Some(image) = image {
+        apply(
+            camera_uuid,
+            Action::SetImageAdjustment(image.clone().without_triggers()),
+        )
+        .await
+        .context("Failed applying the image settings")?;
+    }
+
+    if let Some(advanced_image) = advanced_image {
+        apply(
+            camera_uuid,
+            Action::SetImageAdjustmentEx(advanced_image.clone().without_triggers()),
+        )
+        .await
+        .context("Failed applying the advanced image settings")?;
+    }
+
+    Ok(())
+}
+
+async fn apply(camera_uuid: &Uuid, action: Action) -> Result<()> {
+    control_inner(Json(CameraControl {
+        camera_uuid: *camera_uuid,
+        action,
+    }))
+    .await?;
+
+    Ok(())
+}
+
+/// Each encoder setting must name a channel, and no channel may be set twice
+pub fn channel_reasons(video: &[VideoParameterSettings]) -> Vec<String> {
+    video
+        .iter()
+        .enumerate()
+        .filter_map(
+            |(index, video_parameters)| match &video_parameters.channel {
+                None => Some(format!("Video settings #{index} have no channel")),
+                Some(channel)
+                    if video[..index]
+                        .iter()
+                        .any(|other| other.channel.as_ref() == Some(channel)) =>
+                {
+                    Some(format!("Video channel {channel:?} is repeated"))
+                }
+                Some(_) => None,
+            },
+        )
+        .collect()
+}
+
+/// Each channel must exist in the camera once, with a resolution and frame rate it supports
+fn validate_video(
+    video: &[VideoParameterSettings],
+    current_video: &[Option<VideoParameterSettings>],
+) -> Vec<String> {
+    let mut reasons = channel_reasons(video);
+    if !reasons.is_empty() {
+        return reasons;
+    }
+
+    for (video_parameters, current) in video.iter().zip(current_video) {
+        let Some(channel) = &video_parameters.channel else {
+            continue;
+        };
+
+        let Some(current) = current else {
+            reasons.push(format!("Camera has no video channel {channel:?}"));
+            continue;
+        };
+
+        let channel = channel.clone() as u8;
+        reasons.extend(unsupported_reasons(
+            &radcam::encoder_settings(channel, video_parameters),
+            &radcam::encoder_settings(channel, current),
+        ));
+    }
+
+    reasons
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::protocol::video::video_parameters::VideoResolutionValue;
+
+    use super::*;
+
+    fn video_parameters(
+        channel: VideoChannelValue,
+        resolution: (u16, u16),
+        frame_rate: u16,
+    ) -> VideoParameterSettings {
+        VideoParameterSettings {
+            channel: Some(channel),
+            pic_width: Some(resolution.0),
+            pic_height: Some(resolution.1),
+            frame_rate: Some(frame_rate),
+            pixel_list: Some(vec![
+                VideoResolutionValue {
+                    width: 1920,
+                    height: 1080,
+                },
+                VideoResolutionValue {
+                    width: 1280,
+                    height: 720,
+                },
+            ]),
+            max_framerate: Some(30),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn validate_video_test() {
+        let current = [Some(video_parameters(
+            VideoChannelValue::MainStream,
+            (1920, 1080),
+            25,
+        ))];
+
+        let valid = [video_parameters(
+            VideoChannelValue::MainStream,
+            (1280, 720),
+            30,
+        )];
+        assert!(validate_video(&valid, &current).is_empty());
+
+        let unsupported = [video_parameters(
+            VideoChannelValue::MainStream,
+            (2560, 1440),
+            60,
+        )];
+        assert_eq!(validate_video(&unsupported, &current).len(), 2);
+
+        let missing_channel = [video_parameters(
+            VideoChannelValue::AuxiliaryStream,
+            (1280, 720),
+            30,
+        )];
+        assert_eq!(validate_video(&missing_channel, &[None]).len(), 1);
+
+        let repeated = [valid[0].clone(), valid[0].clone()];
+        assert_eq!(
+            validate_video(&repeated, &[current[0].clone(), current[0].clone()]).len(),
+            1
+        );
+    }
+}
@@ -0,0 +1,543 @@
+use std::{
+    collections::HashMap,
+    net::Ipv4Addr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result, anyhow};
+use mcm_client::{Camera, CameraKind, Credentials, get_camera};
+use once_cell::sync::Lazy;
+use reqwest::{Method, StatusCode, header};
+use tokio::sync::broadcast;
+use tracing::*;
+use url::Url;
+use uuid::Uuid;
+
+use crate::{CameraControl, camera_backend::BackendError, hash_password, snapshot::JPEG_SOI};
+
+const PORT: u16 = 80;
+const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+/// The action answering a JPEG still from the main stream
+const SNAPSHOT_ACTION: &str = "snap";
+/// The action receiving a firmware image, which the camera flashes before rebooting
+const FIRMWARE_UPGRADE_ACTION: &str = "upgrade";
+/// Uploading and checking a firmware image takes much longer than the other requests
+const FIRMWARE_UPGRADE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10 * 60);
+/// The return code the firmware answers `cgi_action` with when it rejects the user or password
+const AUTH_FAILURE_CODE: i64 = -2;
+/// How long a camera without `cgi_action` is sent through the unauthenticated path, before
+/// `cgi_action` is tried again
+const UNAUTHENTICATED_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// Cameras whose firmware doesn't know `cgi_action`, so they are sent through the unauthenticated
+/// path instead
+static UNAUTHENTICATED_CAMERAS: Lazy<Mutex<UnauthenticatedCameras>> = Lazy::new(|| {
+    Mutex::new(UnauthenticatedCameras {
+        since: HashMap::new(),
+        appearances: mcm_client::subscribe_appearances(),
+    })
+});
+
+/// A firmware upgrade may add `cgi_action`, so the cameras are tried again after a while, or as
+/// soon as they reappear
+struct UnauthenticatedCameras {
+    since: HashMap<Uuid, Instant>,
+    appearances: broadcast::Receiver<Uuid>,
+}
+
+impl UnauthenticatedCameras {
+    fn contains(&mut self, camera_uuid: &Uuid) -> bool {
+        self.contains_at(camera_uuid, Instant::now())
+    }
+
+    fn contains_at(&mut self, camera_uuid: &Uuid, now: Instant) -> bool {
+        loop {
+            use broadcast::error::TryRecvError;
+
+            match self.appearances.try_recv() {
+                Ok(camera_uuid) => {
+                    self.since.remove(&camera_uuid);
+                }
+                Err(TryRecvError::Lagged(_)) => self.since.clear(),
+                Err(TryRecvError::Empty | TryRecvError::Closed) => break,
+            }
+        }
+
+        self.since
+            .retain(|_, since| now.duration_since(*since) < UNAUTHENTICATED_TIMEOUT);
+
+        self.since.contains_key(camera_uuid)
+    }
+
+    fn insert(&mut self, camera_uuid: Uuid) {
+        self.since.insert(camera_uuid, Instant::now());
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CameraError {
+    /// The camera rejected the credentials it has in the Mavlink Camera Manager
+    Unauthorized { username: String },
+    /// The camera answered with a non-success return code
+    ReturnCode { code: i32, log: String },
+}
+
+impl std::fmt::Display for CameraError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            CameraError::Unauthorized { username } => {
+                write!(f, "Camera rejected the credentials for user {username:?}")
+            }
+            CameraError::ReturnCode { code, log } => {
+                write!(f, "Camera answered with return code {code}: {log:?}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CameraError {}
+
+#[derive(Debug)]
+enum Response {
+    Ok(serde_json::Value),
+    Unauthorized,
+    /// The firmware doesn't know the requested path, like `cgi_action` on older firmwares
+    NotImplemented(String),
+    /// The camera failed answering this request
+    Failed(String),
+}
+
+/// Sends the action to the camera, authenticating through `cgi_action` when it has credentials
+#[instrument(level = "debug", skip(payload))]
+pub(crate) async fn send_camera_request(
+    camera_control: &CameraControl,
+    payload: String,
+) -> Result<serde_json::Value> {
+    let camera = get_camera(&camera_control.camera_uuid)
+        .await
+        .context("Camera not found")?;
+
+    send_camera_request_to(&camera, camera_control, payload).await
+}
+
+/// Like `send_camera_request`, but to a camera that isn't (yet) where the registry says it is
+#[instrument(level = "debug", skip(payload))]
+pub(crate) async fn send_camera_request_to(
+    camera: &Camera,
+    camera_control: &CameraControl,
+    payload: String,
+) -> Result<serde_json::Value> {
+    radcam_only(camera)?;
+
+    let camera_uuid = camera_control.camera_uuid;
+    let action = action_name(camera_control)?;
+
+    let Camera {
+        hostname,
+        credentials,
+        ..
+    } = camera.clone();
+
+    let Some(Credentials { username, password }) = credentials else {
+        return send_unauthenticated(&hostname, &action, payload).await;
+    };
+
+    if UNAUTHENTICATED_CAMERAS
+        .lock()
+        .unwrap()
+        .contains(&camera_uuid)
+    {
+        return send_unauthenticated(&hostname, &action, payload).await;
+    }
+
+    let url = cgi_action_url(&hostname, &action, &username, &hash_password(&password))?;
+
+    match send(method(&action, &payload), url, payload.clone()).await? {
+        Response::Ok(value) => Ok(value),
+        Response::Unauthorized => Err(CameraError::Unauthorized { username }.into()),
+        Response::NotImplemented(reason) => {
+            warn!(
+                "Camera {camera_uuid} doesn't support cgi_action ({reason}), falling back to the unauthenticated path"
+            );
+
+            UNAUTHENTICATED_CAMERAS.lock().unwrap().insert(camera_uuid);
+
+            send_unauthenticated(&hostname, &action, payload).await
+        }
+        Response::Failed(reason) => Err(anyhow!("Camera failed answering {action:?}: {reason}")),
+    }
+}
+
+/// Fetches a JPEG still from the camera, authenticating through `cgi_action` when it has
+/// credentials
+#[instrument(level = "debug")]
+pub(crate) async fn fetch_snapshot(camera_uuid: &Uuid) -> Result<Vec<u8>> {
+    let camera = get_camera(camera_uuid).await.context("Camera not found")?;
+    radcam_only(&camera)?;
+
+    let Camera {
+        hostname,
+        credentials,
+        ..
+    } = camera;
+
+    let (url, username) = match credentials {
+        Some(Credentials { username, password }) => (
+            cgi_action_url(
+                &hostname,
+                SNAPSHOT_ACTION,
+                &username,
+                &hash_password(&password),
+            )?,
+            Some(username),
+        ),
+        None => (action_url(&hostname, SNAPSHOT_ACTION)?, None),
+    };
+
+    let response = reqwest::Client::new()
+        .get(url)
+        .timeout(TIMEOUT)
+        .header(header::ACCEPT, "image/jpeg")
+        .send()
+        .await
+        .inspect_err(|error| {
+            warn!("Error from fetch_snapshot(): {error:#?}");
+        })?;
+
+    let status = response.status();
+    let content = response.bytes().await?;
+
+    if status.is_success() && content.starts_with(&JPEG_SOI) {
+        return Ok(content.to_vec());
+    }
+
+    match (
+        classify_response(status, &String::from_utf8_lossy(&content)),
+        username,
+    ) {
+        (Response::Unauthorized, Some(username)) => {
+            Err(CameraError::Unauthorized { username }.into())
+        }
+        (Response::Unauthorized, None) => Err(anyhow!(
+            "Camera requires authentication, but it has no credentials"
+        )),
+        (Response::NotImplemented(reason) | Response::Failed(reason), _) => {
+            Err(anyhow!("Camera failed answering a snapshot: {reason}"))
+        }
+        (Response::Ok(_), _) => Err(anyhow!("Camera answered a snapshot that is not a JPEG")),
+    }
+}
+
+/// Streams a firmware image to the camera, authenticating through `cgi_action` when it has
+/// credentials. The camera reboots by itself once it is flashed
+#[instrument(level = "debug", skip(firmware))]
+pub(crate) async fn upload_firmware(
+    camera_uuid: &Uuid,
+    firmware: reqwest::Body,
+    size: u64,
+) -> Result<serde_json::Value> {
+    let camera = get_camera(camera_uuid).await.context("Camera not found")?;
+    radcam_only(&camera)?;
+
+    let Camera {
+        hostname,
+        credentials,
+        ..
+    } = camera;
+
+    let (url, username) = match credentials {
+        Some(Credentials { username, password }) => (
+            cgi_action_url(
+                &hostname,
+                FIRMWARE_UPGRADE_ACTION,
+                &username,
+                &hash_password(&password),
+            )?,
+            Some(username),
+        ),
+        None => (action_url(&hostname, FIRMWARE_UPGRADE_ACTION)?, None),
+    };
+
+    let response = reqwest::Client::new()
+        .post(url)
+        .timeout(FIRMWARE_UPGRADE_TIMEOUT)
+        .header(header::CONTENT_TYPE, "application/octet-stream")
+        .header(header::CONTENT_LENGTH, size)
+        .header(header::ACCEPT, "application/json")
+        .body(firmware)
+        .send()
+        .await
+        .inspect_err(|error| {
+            warn!("Error from upload_firmware(): {error:#?}");
+        })?;
+
+    let status = response.status();
+    let content = response.text().await?;
+
+    match (classify_response(status, &content), username) {
+        (Response::Ok(value), _) => Ok(value),
+        (Response::Unauthorized, Some(username)) => {
+            Err(CameraError::Unauthorized { username }.into())
+        }
+        (Response::Unauthorized, None) => Err(anyhow!(
+            "Camera requires authentication, but it has no credentials"
+        )),
+        (Response::NotImplemented(reason) | Response::Failed(reason), _) => {
+            Err(anyhow!("Camera failed receiving the firmware: {reason}"))
+        }
+    }
+}
+
+async fn send_unauthenticated(
+    hostname: &Ipv4Addr,
+    action: &str,
+    payload: String,
+) -> Result<serde_json::Value> {
+    match send(
+        method(action, &payload),
+        action_url(hostname, action)?,
+        payload,
+    )
+    .await?
+    {
+        Response::Ok(value) => Ok(value),
+        Response::Unauthorized => Err(anyhow!(
+            "Camera requires authentication, but it has no credentials"
+        )),
+        Response::NotImplemented(reason) | Response::Failed(reason) => {
+            Err(anyhow!("Camera failed answering {action:?}: {reason}"))
+        }
+    }
+}
+
+/// The getters without payload are sent as GET, everything else carries a body or changes the
+/// camera, so it is POSTed
+fn method(action: &str, payload: &str) -> Method {
+    if action.starts_with("get") && payload.is_empty() {
+        Method::GET
+    } else {
+        Method::POST
+    }
+}
+
+#[instrument(level = "debug", skip(payload))]
+async fn send(method: Method, url: Url, payload: String) -> Result<Response> {
+    let response = reqwest::Client::new()
+        .request(method, url)
+        .timeout(TIMEOUT)
+        .body(payload)
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(header::ACCEPT, "application/json")
+        .send()
+        .await
+        .inspect_err(|error| {
+            warn!("Error from send(): {error:#?}");
+        })?;
+
+    let status = response.status();
+    let content = response.text().await?;
+
+    Ok(classify_response(status, &content))
+}
+
+fn classify_response(status: StatusCode, content: &str) -> Response {
+    match status {
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => return Response::Unauthorized,
+        StatusCode::NOT_FOUND | StatusCode::METHOD_NOT_ALLOWED | StatusCode::NOT_IMPLEMENTED => {
+            return Response::NotImplemented(format!("HTTP {status}"));
+        }
+        status if !status.is_success() => {
+            return Response::Failed(format!("HTTP {status}: {content}"));
+        }
+        _ => (),
+    }
+
+    match serde_json::from_str::<serde_json::Value>(content) {
+        Ok(value) if is_auth_failure(&value) => Response::Unauthorized,
+        Ok(value) if is_cgi_action_unsupported(&value) => {
+            Response::NotImplemented(value.to_string())
+        }
+        Ok(value) => Response::Ok(value),
+        Err(error) => Response::Failed(format!("Invalid JSON answer: {error}")),
+    }
+}
+
+/// The firmware answers failed logins with HTTP 200 and its authentication return code
+fn is_auth_failure(value: &serde_json::Value) -> bool {
+    value.get("code").and_then(serde_json::Value::as_i64) == Some(AUTH_FAILURE_CODE)
+}
+
+/// Firmwares without `cgi_action` answer it with HTTP 200 and a message saying so
+fn is_cgi_action_unsupported(value: &serde_json::Value) -> bool {
+    error_messages(value).any(|message| {
+        message.contains("cgi_action")
+            && ["not support", "unsupported", "unknown"]
+                .iter()
+                .any(|word| message.contains(word))
+    })
+}
+
+fn error_messages(value: &serde_json::Value) -> impl Iterator<Item = String> + '_ {
+    ["msg", "message", "error"]
+        .into_iter()
+        .filter_map(|key| value.get(key)?.as_str())
+        .map(str::to_lowercase)
+}
+
+/// The CGI actions are RadCam only, the other cameras go through their camera backend
+fn radcam_only(camera: &Camera) -> Result<()> {
+    match camera.kind {
+        CameraKind::RadCam => Ok(()),
+        kind => Err(BackendError::Unsupported {
+            kind,
+            what: "the RadCam actions".to_string(),
+        }
+        .into()),
+    }
+}
+
+fn action_name(camera_control: &CameraControl) -> Result<String> {
+    serde_json::to_value(&camera_control.action)?
+        .get("action")
+        .and_then(|action| action.as_str())
+        .map(str::to_string)
+        .context("Action without a name")
+}
+
+fn action_url(hostname: &Ipv4Addr, action: &str) -> Result<Url> {
+    format!("http://{hostname}:{PORT}/action/{action}")
+        .parse()
+        .context("Invalid URl")
+}
+
+fn cgi_action_url(
+    hostname: &Ipv4Addr,
+    action: &str,
+    username: &str,
+    password_hash: &str,
+) -> Result<Url> {
+    Url::parse_with_params(
+        &format!("http://{hostname}:{PORT}/action/cgi_action"),
+        [
+            ("user", username),
+            ("pwd", password_hash),
+            ("action", action),
+        ],
+    )
+    .context("Invalid URl")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_cgi_action_url() {
+        let url = cgi_action_url(
+            &Ipv4Addr::new(192, 168, 2, 10),
+            "getImageAdjustment",
+            "admin",
+            &hash_password("admin"),
+        )
+        .unwrap();
+
+        assert_eq!(
+            url.as_str(),
+            "http://192.168.2.10/action/cgi_action?user=admin&pwd=21232f297a57a5a743894a0e4a801fc3&action=getImageAdjustment"
+        );
+    }
+
+    #[test]
+    fn test_classify_response() {
+        assert!(matches!(
+            classify_response(StatusCode::OK, r#"{"brightness": 50}"#),
+            Response::Ok(_)
+        ));
+        assert!(matches!(
+            classify_response(StatusCode::UNAUTHORIZED, ""),
+            Response::Unauthorized
+        ));
+        assert!(matches!(
+            classify_response(
+                StatusCode::OK,
+                r#"{"code": -2, "msg": "Invalid user or password"}"#
+            ),
+            Response::Unauthorized
+        ));
+        assert!(matches!(
+            classify_response(
+                StatusCode::OK,
+                r#"{"code": -3, "msg": "Invalid password format"}"#
+            ),
+            Response::Ok(_)
+        ));
+        assert!(matches!(
+            classify_response(StatusCode::OK, "<html>Please login</html>"),
+            Response::Failed(_)
+        ));
+        assert!(matches!(
+            classify_response(StatusCode::NOT_FOUND, ""),
+            Response::NotImplemented(_)
+        ));
+        assert!(matches!(
+            classify_response(
+                StatusCode::OK,
+                r#"{"code": -1, "msg": "cgi_action not supported"}"#
+            ),
+            Response::NotImplemented(_)
+        ));
+        assert!(matches!(
+            classify_response(StatusCode::INTERNAL_SERVER_ERROR, ""),
+            Response::Failed(_)
+        ));
+        assert!(matches!(
+            classify_response(StatusCode::OK, "<html></html>"),
+            Response::Failed(_)
+        ));
+    }
+
+    #[test]
+    fn test_method() {
+        assert_eq!(method("getSysConfig", ""), Method::GET);
+        assert_eq!(method("getVencConf", r#"{"channel":0}"#), Method::POST);
+        assert_eq!(
+            method("setImageAdjustment", r#"{"brightness":50}"#),
+            Method::POST
+        );
+        assert_eq!(method("restart", ""), Method::POST);
+    }
+
+    #[test]
+    fn test_unauthenticated_cameras() {
+        let (sender, appearances) = broadcast::channel(4);
+        let mut cameras = UnauthenticatedCameras {
+            since: HashMap::new(),
+            appearances,
+        };
+        let camera_uuid = Uuid::new_v5(&Uuid::NAMESPACE_DNS, b"camera.example.com");
+
+        cameras.insert(camera_uuid);
+        assert!(cameras.contains(&camera_uuid));
+
+        sender.send(camera_uuid).unwrap();
+        assert!(!cameras.contains(&camera_uuid));
+
+        cameras.insert(camera_uuid);
+        assert!(!cameras.contains_at(&camera_uuid, Instant::now() + UNAUTHENTICATED_TIMEOUT));
+    }
+
+    #[test]
+    fn test_is_auth_failure() {
+        assert!(is_auth_failure(
+            &json!({"code": -2, "msg": "Authentication failed"})
+        ));
+        assert!(!is_auth_failure(
+            &json!({"code": -3, "msg": "Login page moved"})
+        ));
+        assert!(!is_auth_failure(&json!({"code": 0, "msg": "success"})));
+        assert!(!is_auth_failure(&json!({"brightness": 50})));
+    }
+}
@@ -0,0 +1,86 @@
+use anyhow::{Context, Result};
+use tracing::*;
+use uuid::Uuid;
+
+use crate::{
+    Action, CameraControl,
+    camera_backend::{self, radcam},
+    client, get_video_parameters,
+    protocol::{
+        parse_response,
+        video::video_parameters::{VideoChannelValue, VideoParameterSettings},
+    },
+    schema,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EncoderError {
+    /// The camera doesn't support the resolution or frame rate
+    Unsupported { reasons: Vec<String> },
+}
+
+impl std::fmt::Display for EncoderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            EncoderError::Unsupported { reasons } => {
+                write!(f, "Unsupported video settings: {}", reasons.join("; "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for EncoderError {}
+
+/// Changes the encoder of a channel, answering its new settings. The settings are checked against
+/// what the channel supports first, and the MCM stream built from the channel follows the change
+#[instrument(level = "debug")]
+pub(crate) async fn set_video_parameters(
+    camera_uuid: &Uuid,
+    video_parameters: &VideoParameterSettings,
+) -> Result<VideoParameterSettings> {
+    let action = Action::SetVideoParameterSettings(video_parameters.clone());
+    schema::validate(&action)?;
+
+    // The camera applies the settings without a channel to the main one
+    let channel = video_parameters
+        .channel
+        .clone()
+        .unwrap_or(VideoChannelValue::MainStream);
+    let channel_number = channel.clone() as u8;
+
+    let camera = mcm_client::get_camera(camera_uuid)
+        .await
+        .context("Camera not found")?;
+
+    let previous = get_video_parameters(camera_uuid, channel.clone()).await?;
+
+    let reasons = camera_backend::unsupported_reasons(
+        &radcam::encoder_settings(channel_number, video_parameters),
+        &radcam::encoder_settings(channel_number, &previous),
+    );
+    if !reasons.is_empty() {
+        return Err(EncoderError::Unsupported { reasons }.into());
+    }
+
+    let payload = serde_json::to_string(video_parameters)?;
+    let res = client::send_camera_request(
+        &CameraControl {
+            camera_uuid: *camera_uuid,
+            action,
+        },
+        payload,
+    )
+    .await?;
+    parse_response::<serde_json::Value>(res)?;
+
+    let current = get_video_parameters(camera_uuid, channel).await?;
+
+    camera_backend::follow_in_stream(
+        &camera,
+        &radcam::encoder_settings(channel_number, &previous),
+        &radcam::encoder_settings(channel_number, &current),
+    )
+    .await;
+
+    Ok(current)
+}
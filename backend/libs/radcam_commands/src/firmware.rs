@@ -0,0 +1,546 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result, anyhow};
+use axum::{
+    body::Body,
+    extract::{Path, Query},
+    http::{HeaderMap, StatusCode, header},
+    response::IntoResponse,
+};
+use chrono::Utc;
+use futures::StreamExt;
+use indexmap::IndexMap;
+use mcm_client::{Camera, CameraKind};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Mutex, oneshot};
+use tracing::*;
+use ts_rs::TS;
+use utils::jobs::{self, JobHandle, JobId};
+use uuid::Uuid;
+
+use crate::{
+    CameraError, client, get_sys_config,
+    labels::camera_labels,
+    protocol::system::SysConfig,
+    status::{camera_status, update_status},
+    wait_for_reboot,
+};
+
+/// How long the versions read from a camera are trusted
+const VERSIONS_MAX_AGE: Duration = Duration::from_secs(5 * 60);
+/// Unreachable cameras shouldn't hold the whole list
+const VERSIONS_TIMEOUT: Duration = Duration::from_secs(5);
+/// The upload progress is published at most this often, as the image comes in small chunks
+const UPLOAD_PROGRESS_PERIOD: Duration = Duration::from_millis(500);
+/// Flashing takes a while before the camera reboots, so it is polled for longer than a restart
+const UPGRADE_REBOOT_TRIES: usize = 180;
+
+/// When the versions were last read from each camera, even if it failed
+static LAST_READS: Lazy<Mutex<IndexMap<Uuid, tokio::time::Instant>>> =
+    Lazy::new(|| Mutex::new(IndexMap::new()));
+
+/// A camera known by the Mavlink Camera Manager, with its versions as last read from it
+#[derive(Debug, Clone, Serialize, TS)]
+pub struct CameraEntry {
+    #[serde(flatten)]
+    #[ts(flatten)]
+    pub camera: Camera,
+    pub versions: Option<CameraVersions>,
+    /// Names to select the camera by in batch operations
+    pub labels: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+pub struct CameraVersions {
+    pub model: Option<String>,
+    pub firmware: Option<String>,
+    pub hardware: Option<String>,
+    /// When they were read from the camera, in RFC 3339
+    pub read_at: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+pub struct FirmwareUpgradeStatus {
+    pub state: FirmwareUpgradeState,
+    /// How much of the image was sent to the camera, in bytes
+    pub bytes_sent: u64,
+    /// The image size, in bytes
+    pub total_bytes: u64,
+    pub previous_version: Option<String>,
+    /// Only present once the camera answers after rebooting
+    pub new_version: Option<String>,
+    /// When the upgrade started, in RFC 3339
+    pub started_at: String,
+    /// Only present when the upgrade failed
+    pub error: Option<String>,
+    /// The job running the upgrade, once it started
+    pub job_id: Option<JobId>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+pub enum FirmwareUpgradeState {
+    Uploading,
+    /// The camera is flashing the image and rebooting
+    Rebooting,
+    /// Checking the version the camera answers after rebooting
+    Verifying,
+    Done,
+    Failed,
+}
+
+impl FirmwareUpgradeState {
+    fn is_running(&self) -> bool {
+        matches!(
+            self,
+            FirmwareUpgradeState::Uploading
+                | FirmwareUpgradeState::Rebooting
+                | FirmwareUpgradeState::Verifying
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FirmwareError {
+    EmptyImage,
+    /// The image is streamed to the camera, which needs its size upfront
+    UnknownSize,
+    /// The camera is already being upgraded
+    UpgradeInProgress {
+        camera_uuid: Uuid,
+    },
+}
+
+impl std::fmt::Display for FirmwareError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            FirmwareError::EmptyImage => write!(f, "Firmware image is empty"),
+            FirmwareError::UnknownSize => write!(f, "Firmware image size is unknown"),
+            FirmwareError::UpgradeInProgress { camera_uuid } => {
+                write!(f, "Camera {camera_uuid} is already being upgraded")
+            }
+        }
+    }
+}
+
+impl std::error::Error for FirmwareError {}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct UpgradeQuery {
+    /// The firmware version the image carries. When missing, the upgrade is verified by the
+    /// version changing
+    pub version: Option<String>,
+}
+
+impl CameraVersions {
+    fn new(sys_config: SysConfig) -> Self {
+        Self {
+            model: sys_config.model,
+            firmware: sys_config.firmware,
+            hardware: sys_config.hardware,
+            read_at: Utc::now().to_rfc3339(),
+        }
+    }
+}
+
+/// All cameras known by the Mavlink Camera Manager, with their versions. Cameras whose versions are
+/// missing or old are read again
+#[instrument(level = "debug")]
+pub async fn inventory() -> IndexMap<Uuid, CameraEntry> {
+    let cameras = mcm_client::cameras().await;
+
+    let outdated_cameras = {
+        let mut last_reads = LAST_READS.lock().await;
+        last_reads.retain(|camera_uuid, _| cameras.contains_key(camera_uuid));
+
+        // Only RadCams answer their versions
+        let outdated_cameras = cameras
+            .values()
+            .filter(|camera| camera.kind == CameraKind::RadCam)
+            .map(|camera| &camera.uuid)
+            .filter(|camera_uuid| {
+                last_reads
+                    .get(*camera_uuid)
+                    .is_none_or(|last_read| last_read.elapsed() > VERSIONS_MAX_AGE)
+            })
+            .copied()
+            .collect::<Vec<Uuid>>();
+
+        for camera_uuid in &outdated_cameras {
+            last_reads.insert(*camera_uuid, tokio::time::Instant::now());
+        }
+
+        outdated_cameras
+    };
+
+    futures::future::join_all(outdated_cameras.iter().map(|camera_uuid| async move {
+        if let Err(error) = read_versions(camera_uuid).await {
+            debug!("Failed reading camera {camera_uuid} versions: {error:#}");
+        }
+    }))
+    .await;
+
+    let mut labels = camera_labels().await.unwrap_or_else(|error| {
+        debug!("Failed reading the camera labels: {error:#}");
+        IndexMap::new()
+    });
+
+    let mut inventory = IndexMap::new();
+    for (camera_uuid, camera) in cameras {
+        let versions = camera_status(&camera_uuid).await.versions;
+        let labels = labels.shift_remove(&camera_uuid).unwrap_or_default();
+
+        inventory.insert(
+            camera_uuid,
+            CameraEntry {
+                camera,
+                versions,
+                labels,
+            },
+        );
+    }
+
+    inventory
+}
+
+#[instrument(level = "debug")]
+async fn read_versions(camera_uuid: &Uuid) -> Result<CameraVersions> {
+    let sys_config = tokio::time::timeout(VERSIONS_TIMEOUT, get_sys_config(camera_uuid))
+        .await
+        .context("Timed out")??;
+
+    let versions = CameraVersions::new(sys_config);
+    update_status(camera_uuid, |status| {
+        status.versions = Some(versions.clone())
+    })
+    .await;
+
+    Ok(versions)
+}
+
+/// Upgrades the camera firmware, answering once the camera got the whole image. Flashing, rebooting
+/// and verifying go on in the background, with the progress in the camera status
+#[instrument(level = "debug", skip(firmware))]
+pub async fn start_upgrade(
+    camera_uuid: &Uuid,
+    firmware: Body,
+    total_bytes: u64,
+    version: Option<String>,
+) -> Result<FirmwareUpgradeStatus> {
+    if total_bytes == 0 {
+        return Err(FirmwareError::EmptyImage.into());
+    }
+
+    let upgrade = FirmwareUpgradeStatus {
+        state: FirmwareUpgradeState::Uploading,
+        bytes_sent: 0,
+        total_bytes,
+        previous_version: None,
+        new_version: None,
+        started_at: Utc::now().to_rfc3339(),
+        error: None,
+        job_id: None,
+    };
+
+    update_status(camera_uuid, |status| {
+        if status
+            .firmware_upgrade
+            .as_ref()
+            .is_some_and(|upgrade| upgrade.state.is_running())
+        {
+            return Err(FirmwareError::UpgradeInProgress {
+                camera_uuid: *camera_uuid,
+            });
+        }
+
+        status.firmware_upgrade = Some(upgrade.clone());
+
+        Ok(())
+    })
+    .await?;
+
+    let (previous_version, uptime_before) = match get_sys_config(camera_uuid).await {
+        Ok(sys_config) => (sys_config.firmware, sys_config.uptime),
+        Err(error) => {
+            let error = error.context("Failed reading the current firmware version");
+            finish_upgrade(camera_uuid, Err(anyhow!("{error:#}"))).await;
+
+            return Err(error);
+        }
+    };
+
+    update_upgrade(camera_uuid, |upgrade| {
+        upgrade.previous_version = previous_version.clone()
+    })
+    .await;
+
+    info!(
+        "Upgrading camera {camera_uuid} firmware from {previous_version:?}, sending {total_bytes} bytes"
+    );
+
+    let (uploaded_sender, uploaded) = oneshot::channel();
+
+    // Interrupting it could leave the camera without a working firmware
+    let job = jobs::spawn("firmwareUpgrade", Some(*camera_uuid), false, {
+        let camera_uuid = *camera_uuid;
+        let previous_version = previous_version.clone();
+
+        move |job| async move {
+            let result = upgrade_firmware(
+                &camera_uuid,
+                job,
+                firmware,
+                total_bytes,
+                uploaded_sender,
+                uptime_before,
+                previous_version,
+                version,
+            )
+            .await;
+
+            finish_upgrade(&camera_uuid, result).await;
+
+            match camera_status(&camera_uuid).await.firmware_upgrade {
+                Some(upgrade) if upgrade.state == FirmwareUpgradeState::Done => {
+                    Ok(serde_json::to_value(upgrade)?)
+                }
+                Some(upgrade) => Err(anyhow!(upgrade.error.unwrap_or_default())),
+                None => Err(anyhow!("Firmware upgrade status is gone")),
+            }
+        }
+    })
+    .await;
+
+    update_upgrade(camera_uuid, |upgrade| upgrade.job_id = Some(job.id)).await;
+
+    // The image comes from the request, so it has to stay open until the camera has it all
+    uploaded
+        .await
+        .context("Firmware upgrade stopped before sending the image")??;
+
+    Ok(camera_status(camera_uuid)
+        .await
+        .firmware_upgrade
+        .unwrap_or(FirmwareUpgradeStatus {
+            previous_version,
+            job_id: Some(job.id),
+            ..upgrade
+        }))
+}
+
+/// Sends the image, waits for the camera to reboot, and answers its new firmware version. How
+/// sending the image went is told through `uploaded` as soon as it's known
+#[allow(clippy::too_many_arguments)]
+async fn upgrade_firmware(
+    camera_uuid: &Uuid,
+    job: JobHandle,
+    firmware: Body,
+    total_bytes: u64,
+    uploaded: oneshot::Sender<Result<()>>,
+    uptime_before: Option<u64>,
+    previous_version: Option<String>,
+    expected_version: Option<String>,
+) -> Result<Option<String>> {
+    // The image goes through as it arrives, reporting how much of it was sent from time to time
+    let chunks = firmware.into_data_stream().scan(
+        (0u64, None::<tokio::time::Instant>),
+        move |(bytes_sent, last_report), chunk| {
+            if let Ok(chunk) = &chunk {
+                *bytes_sent += chunk.len() as u64;
+            }
+
+            let report = *bytes_sent >= total_bytes
+                || last_report
+                    .is_none_or(|last_report| last_report.elapsed() >= UPLOAD_PROGRESS_PERIOD);
+            if report {
+                *last_report = Some(tokio::time::Instant::now());
+            }
+
+            futures::future::ready(Some((*bytes_sent, report, chunk)))
+        },
+    );
+    let chunks = chunks.then({
+        let camera_uuid = *camera_uuid;
+
+        move |(bytes_sent, report, chunk)| async move {
+            if report {
+                update_upgrade(&camera_uuid, |upgrade| upgrade.bytes_sent = bytes_sent).await;
+                job.progress(
+                    Some(bytes_sent as f32 / total_bytes as f32),
+                    format!("Uploading, {bytes_sent} of {total_bytes} bytes sent"),
+                )
+                .await;
+            }
+
+            chunk
+        }
+    });
+
+    let upload = async {
+        let res =
+            client::upload_firmware(camera_uuid, reqwest::Body::wrap_stream(chunks), total_bytes)
+                .await
+                .context("Failed sending the firmware")?;
+
+        // A camera that accepted the image may still refuse flashing it
+        crate::protocol::parse_response::<serde_json::Value>(res).map(|_| ())
+    }
+    .await;
+
+    // The error goes to the request, as it is the one that can tell what went wrong
+    if let Err(error) = upload {
+        let message = format!("{error:#}");
+        let _ = uploaded.send(Err(error));
+
+        return Err(anyhow!(message));
+    }
+    let _ = uploaded.send(Ok(()));
+
+    update_upgrade(camera_uuid, |upgrade| {
+        upgrade.state = FirmwareUpgradeState::Rebooting
+    })
+    .await;
+    job.progress(None, "Rebooting").await;
+
+    let sys_config = wait_for_reboot(camera_uuid, uptime_before, UPGRADE_REBOOT_TRIES).await?;
+
+    update_upgrade(camera_uuid, |upgrade| {
+        upgrade.state = FirmwareUpgradeState::Verifying
+    })
+    .await;
+    job.progress(None, "Verifying").await;
+
+    let versions = CameraVersions::new(sys_config);
+    update_status(camera_uuid, |status| {
+        status.versions = Some(versions.clone())
+    })
+    .await;
+
+    verify_version(
+        versions.firmware.as_deref(),
+        previous_version.as_deref(),
+        expected_version.as_deref(),
+    )?;
+
+    Ok(versions.firmware)
+}
+
+fn verify_version(
+    new_version: Option<&str>,
+    previous_version: Option<&str>,
+    expected_version: Option<&str>,
+) -> Result<()> {
+    let new_version = new_version.context("Camera didn't answer its firmware version")?;
+
+    match expected_version {
+        Some(expected_version) if new_version != expected_version => Err(anyhow!(
+            "Camera is running firmware {new_version:?} instead of {expected_version:?}"
+        )),
+        None if previous_version == Some(new_version) => {
+            Err(anyhow!("Camera is still running firmware {new_version:?}"))
+        }
+        _ => Ok(()),
+    }
+}
+
+async fn update_upgrade(camera_uuid: &Uuid, update: impl FnOnce(&mut FirmwareUpgradeStatus)) {
+    update_status(camera_uuid, |status| {
+        if let Some(upgrade) = status.firmware_upgrade.as_mut() {
+            update(upgrade);
+        }
+    })
+    .await;
+}
+
+async fn finish_upgrade(camera_uuid: &Uuid, result: Result<Option<String>>) {
+    match &result {
+        Ok(new_version) => info!("Camera {camera_uuid} upgraded to firmware {new_version:?}"),
+        Err(error) => warn!("Camera {camera_uuid} firmware upgrade failed: {error:#}"),
+    }
+
+    update_upgrade(camera_uuid, |upgrade| match result {
+        Ok(new_version) => {
+            upgrade.state = FirmwareUpgradeState::Done;
+            upgrade.new_version = new_version;
+        }
+        Err(error) => {
+            upgrade.state = FirmwareUpgradeState::Failed;
+            upgrade.error = Some(format!("{error:#}"));
+        }
+    })
+    .await;
+}
+
+#[instrument(level = "debug", skip(firmware))]
+pub async fn upload(
+    Path(camera_uuid): Path<Uuid>,
+    Query(query): Query<UpgradeQuery>,
+    headers: HeaderMap,
+    firmware: Body,
+) -> impl IntoResponse {
+    let total_bytes = headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok());
+
+    let result = match total_bytes {
+        Some(total_bytes) => {
+            start_upgrade(&camera_uuid, firmware, total_bytes, query.version).await
+        }
+        None => Err(FirmwareError::UnknownSize.into()),
+    };
+
+    match result {
+        Ok(upgrade) => (
+            StatusCode::ACCEPTED,
+            serde_json::to_string(&upgrade).unwrap(),
+        )
+            .into_response(),
+        Err(error) => {
+            warn!("Firmware upgrade error: {error:#?}");
+
+            let status = match (
+                error.downcast_ref::<FirmwareError>(),
+                error.downcast_ref::<CameraError>(),
+            ) {
+                (Some(FirmwareError::EmptyImage), _) => StatusCode::BAD_REQUEST,
+                (Some(FirmwareError::UnknownSize), _) => StatusCode::LENGTH_REQUIRED,
+                (Some(FirmwareError::UpgradeInProgress { .. }), _) => StatusCode::CONFLICT,
+                (None, Some(CameraError::Unauthorized { .. })) => StatusCode::UNAUTHORIZED,
+                (None, Some(CameraError::ReturnCode { .. })) => StatusCode::BAD_GATEWAY,
+                (None, None) => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+
+            (status, format!("{error:?}")).into_response()
+        }
+    }
+}
+
+#[instrument(level = "debug")]
+pub async fn upgrade_status(Path(camera_uuid): Path<Uuid>) -> impl IntoResponse {
+    match camera_status(&camera_uuid).await.firmware_upgrade {
+        Some(upgrade) => (StatusCode::OK, serde_json::to_string(&upgrade).unwrap()).into_response(),
+        None => (
+            StatusCode::NOT_FOUND,
+            format!("Camera {camera_uuid} was never upgraded"),
+        )
+            .into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verify_version_test() {
+        assert!(verify_version(Some("V1.3.0"), Some("V1.2.3"), None).is_ok());
+        assert!(verify_version(Some("V1.3.0"), Some("V1.2.3"), Some("V1.3.0")).is_ok());
+        // Reflashing the same version is fine when it's the expected one
+        assert!(verify_version(Some("V1.2.3"), Some("V1.2.3"), Some("V1.2.3")).is_ok());
+
+        assert!(verify_version(Some("V1.2.3"), Some("V1.2.3"), None).is_err());
+        assert!(verify_version(Some("V1.3.0"), Some("V1.2.3"), Some("V1.4.0")).is_err());
+        assert!(verify_version(None, Some("V1.2.3"), None).is_err());
+    }
+}
@@ -0,0 +1,44 @@
+use axum::{Json, http::StatusCode, response::IntoResponse};
+use tracing::*;
+use utils::jobs::{Job, action_name, spawn};
+
+use crate::{Action, CameraControl, control_inner};
+
+/// Runs the camera action as a job
+#[instrument(level = "debug")]
+pub async fn spawn_camera_action(camera_control: CameraControl) -> Job {
+    let name = action_name(&camera_control.action);
+    let camera_uuid = camera_control.camera_uuid;
+    // The camera must not be left between two addresses
+    let cancellable = !matches!(camera_control.action, Action::SetNetParameterSettings(_));
+
+    spawn(
+        &name.clone(),
+        Some(camera_uuid),
+        cancellable,
+        |job| async move {
+            job.progress(None, format!("Running {name}")).await;
+
+            control_inner(Json(camera_control)).await
+        },
+    )
+    .await
+}
+
+#[instrument(level = "debug")]
+pub async fn start(Json(camera_control): Json<CameraControl>) -> impl IntoResponse {
+    let job = spawn_camera_action(camera_control).await;
+
+    (StatusCode::ACCEPTED, serde_json::to_string(&job).unwrap()).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn action_name_test() {
+        assert_eq!(action_name(&Action::Restart), "restart");
+        assert_eq!(action_name(&Action::TakeSnapshot), "takeSnapshot");
+    }
+}
@@ -0,0 +1,108 @@
+use anyhow::{Context, Result};
+use axum::{Json, extract::Path, http::StatusCode, response::IntoResponse};
+use indexmap::IndexMap;
+use settings::MANAGER as SETTINGS_MANAGER;
+use tracing::*;
+use uuid::Uuid;
+
+/// The labels of every camera that has some
+#[instrument(level = "debug")]
+pub async fn camera_labels() -> Result<IndexMap<Uuid, Vec<String>>> {
+    let labels = SETTINGS_MANAGER
+        .get()
+        .context("Not available")?
+        .read()
+        .await
+        .settings
+        .get_labels()
+        .clone();
+
+    Ok(labels)
+}
+
+/// Replaces the camera labels, answering them as they were stored
+#[instrument(level = "debug")]
+pub async fn set_camera_labels(camera_uuid: &Uuid, labels: Vec<String>) -> Result<Vec<String>> {
+    let labels = normalize(labels);
+
+    let settings = &mut SETTINGS_MANAGER
+        .get()
+        .context("Not available")?
+        .write()
+        .await
+        .settings;
+
+    if labels.is_empty() {
+        settings.get_labels_mut().shift_remove(camera_uuid);
+    } else {
+        settings
+            .get_labels_mut()
+            .insert(*camera_uuid, labels.clone());
+    }
+
+    settings.save().await?;
+
+    Ok(labels)
+}
+
+/// Labels are matched as they are written, so they are only trimmed, and the empty and repeated
+/// ones dropped
+fn normalize(labels: Vec<String>) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+
+    for label in labels {
+        let label = label.trim();
+
+        if label.is_empty() || normalized.iter().any(|other| other == label) {
+            continue;
+        }
+
+        normalized.push(label.to_string());
+    }
+
+    normalized
+}
+
+#[instrument(level = "debug")]
+pub async fn list() -> impl IntoResponse {
+    match camera_labels().await {
+        Ok(labels) => (StatusCode::OK, serde_json::to_string(&labels).unwrap()).into_response(),
+        Err(error) => (StatusCode::INTERNAL_SERVER_ERROR, format!("{error:?}")).into_response(),
+    }
+}
+
+#[instrument(level = "debug")]
+pub async fn update(
+    Path(camera_uuid): Path<Uuid>,
+    Json(labels): Json<Vec<String>>,
+) -> impl IntoResponse {
+    match set_camera_labels(&camera_uuid, labels).await {
+        Ok(labels) => (StatusCode::OK, serde_json::to_string(&labels).unwrap()).into_response(),
+        Err(error) => (StatusCode::INTERNAL_SERVER_ERROR, format!("{error:?}")).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_test() {
+        let labels = vec![
+            " port ".to_string(),
+            "forward".to_string(),
+            "".to_string(),
+            "port".to_string(),
+            "Port".to_string(),
+        ];
+
+        assert_eq!(
+            normalize(labels),
+            vec![
+                "port".to_string(),
+                "forward".to_string(),
+                "Port".to_string()
+            ]
+        );
+    }
+}
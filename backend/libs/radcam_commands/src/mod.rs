@@ -1,20 +1,45 @@
 use std::{future::Future, pin::Pin};
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, anyhow};
 use axum::{Json, http::StatusCode, response::IntoResponse};
-use mcm_client::{Camera, get_camera};
+use camera_backend::BackendError;
+use camera_config::{CameraConfig, CameraConfigError};
+use encoder::EncoderError;
+use mcm_client::CameraKind;
 use protocol::{
     display::{advanced_display::AdvancedParameterSetting, base_display::BaseParameterSetting},
-    video::video_parameters::VideoParameterSettings,
+    network::net_parameters::NetParameterSettings,
+    osd::osd_parameters::OsdParameterSettings,
+    parse_response,
+    system::{SysConfig, SystemTime},
+    video::video_parameters::{VideoChannelValue, VideoParameterSettings},
 };
+use schema::SchemaError;
 use serde::{Deserialize, Serialize};
 use tracing::*;
 use ts_rs::TS;
-use url::Url;
 use uuid::Uuid;
-use web_client::send_request;
 
+pub mod batch;
+pub mod camera_backend;
+pub mod camera_config;
+mod client;
+pub mod encoder;
+pub mod firmware;
+pub mod jobs;
+pub mod labels;
+mod network;
 pub mod protocol;
+pub mod schema;
+pub mod snapshot;
+pub mod status;
+pub mod time_sync;
+
+pub use client::CameraError;
+pub use snapshot::SnapshotError;
+
+/// How many times a restarting camera is polled, one second apart, going down and coming back
+const RESTART_TRIES: usize = 40;
 
 #[derive(Debug, Clone, Serialize, Deserialize, TS)]
 // #[tsync] // FIXME: Disabled for now, see https://github.com/Wulf/tsync/issues/58
@@ -31,6 +56,10 @@ pub struct CameraControl {
 pub enum Action {
     #[serde(rename = "getSysConfig")]
     GetSysConfig,
+    #[serde(rename = "getSysTime")]
+    GetSystemTime,
+    #[serde(rename = "setSysTime")]
+    SetSystemTime(SystemTime),
     #[serde(rename = "getImageAdjustment")]
     GetImageAdjustment,
     #[serde(rename = "getImageAdjustmentEx")]
@@ -43,8 +72,22 @@ pub enum Action {
     SetImageAdjustmentEx(AdvancedParameterSetting),
     #[serde(rename = "setVencConf")]
     SetVideoParameterSettings(VideoParameterSettings),
+    #[serde(rename = "getOsdConf")]
+    GetOsdParameterSettings,
+    #[serde(rename = "setOsdConf")]
+    SetOsdParameterSettings(OsdParameterSettings),
+    #[serde(rename = "getNetConfig")]
+    GetNetParameterSettings,
+    #[serde(rename = "setNetConfig")]
+    SetNetParameterSettings(NetParameterSettings),
     #[serde(rename = "restart")]
     Restart,
+    #[serde(rename = "takeSnapshot")]
+    TakeSnapshot,
+    #[serde(rename = "exportCameraConfig")]
+    ExportCameraConfig,
+    #[serde(rename = "importCameraConfig")]
+    ImportCameraConfig(CameraConfig),
 }
 
 impl std::fmt::Display for Action {
@@ -54,12 +97,64 @@ impl std::fmt::Display for Action {
 }
 
 #[instrument(level = "debug")]
-fn control_inner(
+pub fn control_inner(
     camera_control: Json<CameraControl>,
 ) -> Pin<Box<dyn Future<Output = Result<serde_json::Value>> + Send>> {
     Box::pin(async move {
         debug!("Got control query: {camera_control:#?}");
 
+        // The other cameras don't speak the RadCam actions, but have the shared ones
+        let camera = mcm_client::get_camera(&camera_control.camera_uuid)
+            .await
+            .context("Camera not found")?;
+        if camera.kind != CameraKind::RadCam {
+            return camera_backend::control_shared_action(&camera, &camera_control.action).await;
+        }
+
+        // Snapshots are not JSON, so they don't go through the camera request
+        if let Action::TakeSnapshot = &camera_control.action {
+            let snapshot = snapshot::take_snapshot(&camera_control.camera_uuid).await?;
+
+            return Ok(serde_json::to_value(snapshot)?);
+        }
+
+        // The whole configuration takes several camera requests
+        match &camera_control.action {
+            Action::ExportCameraConfig => {
+                let camera_config =
+                    camera_config::export_camera_config(&camera_control.camera_uuid).await?;
+
+                return Ok(serde_json::to_value(camera_config)?);
+            }
+            Action::ImportCameraConfig(camera_config) => {
+                let camera_config =
+                    camera_config::import_camera_config(&camera_control.camera_uuid, camera_config)
+                        .await?;
+
+                return Ok(serde_json::to_value(camera_config)?);
+            }
+            _ => (),
+        }
+
+        // The camera may move to another address, so it needs a guarded switchover
+        if let Action::SetNetParameterSettings(net_config) = &camera_control.action {
+            let net_config =
+                network::set_net_config(&camera_control.camera_uuid, net_config).await?;
+
+            return Ok(serde_json::to_value(net_config)?);
+        }
+
+        // The MCM stream has to follow the encoder
+        if let Action::SetVideoParameterSettings(video_parameters) = &camera_control.action {
+            let video_parameters =
+                encoder::set_video_parameters(&camera_control.camera_uuid, video_parameters)
+                    .await?;
+
+            return Ok(serde_json::to_value(video_parameters)?);
+        }
+
+        schema::validate(&camera_control.action)?;
+
         let action_value = serde_json::to_value(&camera_control.action).unwrap();
         let action_map = action_value.as_object().unwrap();
         let payload = action_map
@@ -67,15 +162,43 @@ fn control_inner(
             .map(|value| value.to_string())
             .unwrap_or_default();
 
-        let url = get_camera_api_url(&camera_control.0).await?;
+        debug!("json: {payload}");
 
-        debug!("URL: {url}, json: {payload}");
+        // Tells a reboot apart from a camera that is slow to go down
+        let uptime_before = match &camera_control.action {
+            Action::Restart => get_sys_config(&camera_control.camera_uuid)
+                .await
+                .ok()
+                .and_then(|sys_config| sys_config.uptime),
+            _ => None,
+        };
 
-        let res: serde_json::Value =
-            send_request(reqwest::Client::new().post(url), payload).await?;
+        let res = client::send_camera_request(&camera_control.0, payload).await?;
 
         debug!("Answer from the camera: {res:#?}");
 
+        // Validates the answer against the expected type, stripping the return-code envelope
+        let res = match &camera_control.action {
+            Action::GetSysConfig => serde_json::to_value(parse_response::<SysConfig>(res)?)?,
+            Action::GetSystemTime => serde_json::to_value(parse_response::<SystemTime>(res)?)?,
+            Action::GetImageAdjustment => {
+                serde_json::to_value(parse_response::<BaseParameterSetting>(res)?)?
+            }
+            Action::GetImageAdjustmentEx => {
+                serde_json::to_value(parse_response::<AdvancedParameterSetting>(res)?)?
+            }
+            Action::GetVideoParameterSettings(_) => {
+                serde_json::to_value(parse_response::<VideoParameterSettings>(res)?)?
+            }
+            Action::GetOsdParameterSettings => {
+                serde_json::to_value(parse_response::<OsdParameterSettings>(res)?)?
+            }
+            Action::GetNetParameterSettings => {
+                serde_json::to_value(parse_response::<NetParameterSettings>(res)?)?
+            }
+            _ => parse_response::<serde_json::Value>(res)?,
+        };
+
         let res = match &camera_control.action {
             Action::SetImageAdjustment(_) => {
                 let mut camera_control = camera_control.0.clone();
@@ -89,32 +212,17 @@ fn control_inner(
 
                 control_inner(Json(camera_control)).await
             }
-            Action::SetVideoParameterSettings(video_parameters_settings) => {
+            Action::SetOsdParameterSettings(_) => {
                 let mut camera_control = camera_control.0.clone();
-                camera_control.action = Action::GetVideoParameterSettings(VideoParameterSettings {
-                    channel: video_parameters_settings.channel.clone(),
-                    ..Default::default()
-                });
+                camera_control.action = Action::GetOsdParameterSettings;
 
                 control_inner(Json(camera_control)).await
             }
             Action::Restart => {
-                let mut camera_control = camera_control.0.clone();
-                camera_control.action = Action::GetSysConfig;
-                let value = Json(camera_control);
-
-                tokio::time::sleep(tokio::time::Duration::from_secs(15)).await;
-
-                let mut tries = 20;
-                while tries > 0 {
-                    debug!("Waiting for camera to restart...");
-
-                    tries -= 1;
-                    tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
-
-                    if control_inner(value.clone()).await.is_ok() {
-                        break;
-                    }
+                if let Err(error) =
+                    wait_for_reboot(&camera_control.camera_uuid, uptime_before, RESTART_TRIES).await
+                {
+                    warn!("{error:#}");
                 }
 
                 Ok(res)
@@ -130,20 +238,178 @@ fn control_inner(
 
 #[instrument(level = "debug")]
 pub async fn control(camera_control: Json<CameraControl>) -> impl IntoResponse {
+    if runs_as_job(&camera_control.action) {
+        return jobs::start(camera_control).await.into_response();
+    }
+
     let res = match control_inner(camera_control).await {
         Ok(res) => res,
         Err(error) => {
             warn!("res from send_request: {error:#?}");
-            return (StatusCode::INTERNAL_SERVER_ERROR, format!("{error:?}")).into_response();
+
+            let status = error_status(&error);
+
+            return (status, format!("{error:?}")).into_response();
         }
     };
 
     (StatusCode::OK, res.to_string()).into_response()
 }
 
+/// Whether the action takes the camera away for too long to be answered in its own request
+fn runs_as_job(action: &Action) -> bool {
+    matches!(action, Action::Restart | Action::ImportCameraConfig(_))
+}
+
+/// The status code answering the error of a camera control
+pub(crate) fn error_status(error: &anyhow::Error) -> StatusCode {
+    match error.downcast_ref::<CameraError>() {
+        Some(CameraError::Unauthorized { .. }) => StatusCode::UNAUTHORIZED,
+        Some(CameraError::ReturnCode { .. }) => StatusCode::BAD_GATEWAY,
+        None => error
+            .downcast_ref::<SnapshotError>()
+            .map(snapshot::status_code)
+            .or_else(|| {
+                error
+                    .downcast_ref::<CameraConfigError>()
+                    .map(|_| StatusCode::BAD_REQUEST)
+            })
+            .or_else(|| {
+                error
+                    .downcast_ref::<SchemaError>()
+                    .map(|_| StatusCode::BAD_REQUEST)
+            })
+            .or_else(|| {
+                error
+                    .downcast_ref::<EncoderError>()
+                    .map(|_| StatusCode::BAD_REQUEST)
+            })
+            .or_else(|| {
+                error
+                    .downcast_ref::<BackendError>()
+                    .map(|_| StatusCode::BAD_REQUEST)
+            })
+            .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+#[instrument(level = "debug")]
+pub async fn get_sys_config(camera_uuid: &Uuid) -> Result<SysConfig> {
+    get(camera_uuid, Action::GetSysConfig).await
+}
+
+#[instrument(level = "debug")]
+pub async fn get_system_time(camera_uuid: &Uuid) -> Result<SystemTime> {
+    get(camera_uuid, Action::GetSystemTime).await
+}
+
+#[instrument(level = "debug")]
+pub async fn get_image_adjustment(camera_uuid: &Uuid) -> Result<BaseParameterSetting> {
+    get(camera_uuid, Action::GetImageAdjustment).await
+}
+
+#[instrument(level = "debug")]
+pub async fn get_image_adjustment_ex(camera_uuid: &Uuid) -> Result<AdvancedParameterSetting> {
+    get(camera_uuid, Action::GetImageAdjustmentEx).await
+}
+
+#[instrument(level = "debug")]
+pub async fn get_video_parameters(
+    camera_uuid: &Uuid,
+    channel: VideoChannelValue,
+) -> Result<VideoParameterSettings> {
+    let action = Action::GetVideoParameterSettings(VideoParameterSettings {
+        channel: Some(channel),
+        ..Default::default()
+    });
+
+    get(camera_uuid, action).await
+}
+
+/// Sets the overlays without reading them back, as they are pushed at the telemetry rate
+#[instrument(level = "debug", skip(osd_parameters))]
+pub async fn set_osd_parameters(
+    camera_uuid: &Uuid,
+    osd_parameters: &OsdParameterSettings,
+) -> Result<()> {
+    let camera_control = CameraControl {
+        camera_uuid: *camera_uuid,
+        action: Action::SetOsdParameterSettings(osd_parameters.clone()),
+    };
+
+    schema::validate(&camera_control.action)?;
+
+    let payload = serde_json::to_string(osd_parameters)?;
+    let res = client::send_camera_request(&camera_control, payload).await?;
+    parse_response::<serde_json::Value>(res)?;
+
+    Ok(())
+}
+
+/// Waits for the camera to go down, or for its uptime to go backwards, and then to come back,
+/// answering its system information. `tries` bounds both waits, one second apart
+#[instrument(level = "debug")]
+pub(crate) async fn wait_for_reboot(
+    camera_uuid: &Uuid,
+    uptime_before: Option<u64>,
+    tries: usize,
+) -> Result<SysConfig> {
+    if uptime_before.is_none() {
+        debug!("Camera {camera_uuid} uptime is unknown, only waiting for it to go down");
+    }
+
+    let mut tries = tries;
+    loop {
+        debug!("Waiting for camera to go down...");
+
+        tries = tries.saturating_sub(1);
+        tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+
+        match get_sys_config(camera_uuid).await {
+            Err(_) => break,
+            // It rebooted between two polls
+            Ok(sys_config)
+                if sys_config
+                    .uptime
+                    .zip(uptime_before)
+                    .is_some_and(|(uptime, uptime_before)| uptime < uptime_before) =>
+            {
+                return Ok(sys_config);
+            }
+            Ok(_) if tries == 0 => return Err(anyhow!("Camera didn't reboot")),
+            Ok(_) => (),
+        }
+    }
+
+    loop {
+        debug!("Waiting for camera to come back...");
+
+        tries = tries.saturating_sub(1);
+        tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+
+        match get_sys_config(camera_uuid).await {
+            Ok(sys_config) => return Ok(sys_config),
+            Err(error) if tries == 0 => {
+                return Err(error).context("Camera didn't come back after rebooting");
+            }
+            Err(_) => (),
+        }
+    }
+}
+
+async fn get<T: serde::de::DeserializeOwned>(camera_uuid: &Uuid, action: Action) -> Result<T> {
+    let res = control_inner(Json(CameraControl {
+        camera_uuid: *camera_uuid,
+        action,
+    }))
+    .await?;
+
+    Ok(serde_json::from_value(res)?)
+}
+
 #[instrument(level = "debug")]
 pub async fn list() -> impl IntoResponse {
-    let cameras = mcm_client::cameras().await;
+    let cameras = firmware::inventory().await;
 
     let json = match serde_json::to_string(&cameras) {
         Ok(json) => json,
@@ -155,37 +421,6 @@ pub async fn list() -> impl IntoResponse {
     json.into_response()
 }
 
-#[instrument(level = "debug")]
-async fn get_camera_api_url(camera_control: &CameraControl) -> Result<Url> {
-    let camera_uuid = camera_control.camera_uuid;
-    let action_value = serde_json::to_value(&camera_control.action).unwrap();
-    let action_map = action_value.as_object().unwrap();
-    let action = action_map.get("action").unwrap().as_str().unwrap();
-
-    let Camera {
-        hostname,
-        credentials,
-        ..
-    } = get_camera(&camera_uuid).await.context("Camera not found")?;
-
-    let port = 80;
-
-    match credentials {
-        // NOTE: This part of the protocol is not working: only the get requests using cgi_action parameter are working.
-        // I'm leaving it here in case they fix it:
-        // Some(Credentials { username, password }) => {
-        //     format!(
-        //         "http://{hostname}:{port}/action/cgi_action?user={username}&pwd={password}&action={action}", password=hash_password(&password)
-        //     )
-        // }
-        // None => format!("http://{hostname}:{port}/action/{action}"),
-        // So for now we are just using the protocol without the authentication:
-        _ => format!("http://{hostname}:{port}/action/{action}"),
-    }
-    .parse()
-    .context("Invalid URl")
-}
-
 #[instrument(level = "debug")]
 pub fn hash_password(password: &str) -> String {
     use md5::{Digest, Md5};
@@ -202,7 +437,7 @@ mod tests {
 
     use utils::deserialize;
 
-    use super::CameraControl;
+    use super::{Action, CameraControl, runs_as_job};
 
     #[test]
     fn action_serde_test() {
@@ -229,4 +464,11 @@ mod tests {
 
         assert_eq!(expected_action, serialized_action);
     }
+
+    #[test]
+    fn runs_as_job_test() {
+        assert!(runs_as_job(&Action::Restart));
+        assert!(!runs_as_job(&Action::GetSysConfig));
+        assert!(!runs_as_job(&Action::TakeSnapshot));
+    }
 }
@@ -0,0 +1,320 @@
+use std::net::{IpAddr, Ipv4Addr, UdpSocket};
+
+use anyhow::{Context, Result, anyhow};
+use mcm_client::{Camera, get_camera};
+use tracing::*;
+use uuid::Uuid;
+
+use crate::{
+    Action, CameraControl, CameraError, client,
+    protocol::{
+        network::net_parameters::{NetDhcpValue, NetParameterSettings},
+        parse_response,
+    },
+};
+
+/// How long the camera has to come up at its new address
+const SWITCHOVER_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+const SWITCHOVER_POLL_PERIOD: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// The new IPv4 settings of a camera with a static address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct StaticAddress {
+    ip_address: Ipv4Addr,
+    netmask: Ipv4Addr,
+    gateway: Option<Ipv4Addr>,
+}
+
+/// Changes the camera network settings, following it to its new address. The registry keeps the
+/// old address until the camera answers at the new one
+#[instrument(level = "debug")]
+pub(crate) async fn set_net_config(
+    camera_uuid: &Uuid,
+    net_config: &NetParameterSettings,
+) -> Result<NetParameterSettings> {
+    let camera = get_camera(camera_uuid).await.context("Camera not found")?;
+
+    let current_config = get_net_config(&camera).await?;
+
+    // Missing fields are kept as they are
+    let new_config = NetParameterSettings {
+        dhcp: net_config.dhcp.or(current_config.dhcp),
+        ip_address: net_config
+            .ip_address
+            .clone()
+            .or(current_config.ip_address.clone()),
+        netmask: net_config
+            .netmask
+            .clone()
+            .or(current_config.netmask.clone()),
+        gateway: net_config
+            .gateway
+            .clone()
+            .or(current_config.gateway.clone()),
+        dns1: net_config.dns1.clone().or(current_config.dns1.clone()),
+        dns2: net_config.dns2.clone().or(current_config.dns2.clone()),
+        mac: None,
+    };
+
+    if new_config.dhcp == Some(NetDhcpValue::Open) {
+        warn!(
+            "Camera {camera_uuid} switching to DHCP, it will be followed once the MCM discovers its new address"
+        );
+
+        send_net_config(&camera, &new_config).await?;
+
+        return Ok(new_config);
+    }
+
+    let address = parse_static_address(&new_config)?;
+
+    let companion_address = local_address_to(&camera.hostname)?;
+    let other_cameras = mcm_client::cameras()
+        .await
+        .into_values()
+        .filter(|other_camera| other_camera.uuid != *camera_uuid)
+        .map(|other_camera| other_camera.hostname)
+        .collect::<Vec<Ipv4Addr>>();
+    validate_static_address(&address, &companion_address, &other_cameras)?;
+
+    send_net_config(&camera, &new_config).await?;
+
+    if address.ip_address == camera.hostname {
+        return get_net_config(&camera).await;
+    }
+
+    info!(
+        "Camera {camera_uuid} moving from {} to {}",
+        camera.hostname, address.ip_address
+    );
+
+    let moved_camera = Camera {
+        hostname: address.ip_address,
+        ..camera.clone()
+    };
+    let net_config = wait_for_camera(&moved_camera).await.with_context(|| {
+        format!(
+            "Camera didn't come up at {}, it may still be at {}",
+            address.ip_address, camera.hostname
+        )
+    })?;
+
+    mcm_client::move_camera(camera_uuid, address.ip_address).await?;
+
+    Ok(net_config)
+}
+
+async fn get_net_config(camera: &Camera) -> Result<NetParameterSettings> {
+    let res = client::send_camera_request_to(
+        camera,
+        &CameraControl {
+            camera_uuid: camera.uuid,
+            action: Action::GetNetParameterSettings,
+        },
+        String::new(),
+    )
+    .await?;
+
+    parse_response(res)
+}
+
+/// The camera may drop the connection while switching, so only its explicit rejections are errors
+async fn send_net_config(camera: &Camera, net_config: &NetParameterSettings) -> Result<()> {
+    let payload = serde_json::to_string(net_config)?;
+
+    let res = client::send_camera_request_to(
+        camera,
+        &CameraControl {
+            camera_uuid: camera.uuid,
+            action: Action::SetNetParameterSettings(net_config.clone()),
+        },
+        payload,
+    )
+    .await
+    .and_then(parse_response::<serde_json::Value>);
+
+    match res {
+        Ok(_) => Ok(()),
+        Err(error) if error.downcast_ref::<CameraError>().is_some() => Err(error),
+        Err(error) => {
+            debug!("No answer while switching the camera network settings: {error:?}");
+            Ok(())
+        }
+    }
+}
+
+async fn wait_for_camera(camera: &Camera) -> Result<NetParameterSettings> {
+    let start = std::time::Instant::now();
+
+    loop {
+        tokio::time::sleep(SWITCHOVER_POLL_PERIOD).await;
+
+        match get_net_config(camera).await {
+            Ok(net_config) => return Ok(net_config),
+            Err(error) if start.elapsed() > SWITCHOVER_TIMEOUT => return Err(error),
+            Err(error) => debug!("Waiting for the camera at {}: {error}", camera.hostname),
+        }
+    }
+}
+
+/// The address of our interface facing the camera. No packet is sent
+fn local_address_to(hostname: &Ipv4Addr) -> Result<Ipv4Addr> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+    socket.connect((*hostname, 80))?;
+
+    match socket.local_addr()?.ip() {
+        IpAddr::V4(address) => Ok(address),
+        IpAddr::V6(address) => Err(anyhow!("Unexpected IPv6 local address {address}")),
+    }
+}
+
+fn parse_static_address(net_config: &NetParameterSettings) -> Result<StaticAddress> {
+    let parse = |name: &str, value: &Option<String>| -> Result<Option<Ipv4Addr>> {
+        value
+            .as_deref()
+            .filter(|value| !value.is_empty())
+            .map(|value| {
+                value
+                    .parse::<Ipv4Addr>()
+                    .with_context(|| format!("Invalid {name} {value:?}"))
+            })
+            .transpose()
+    };
+
+    Ok(StaticAddress {
+        ip_address: parse("IP address", &net_config.ip_address)?
+            .context("Missing the IP address")?,
+        netmask: parse("netmask", &net_config.netmask)?.context("Missing the netmask")?,
+        gateway: parse("gateway", &net_config.gateway)?.filter(|gateway| !gateway.is_unspecified()),
+    })
+}
+
+/// The camera should stay reachable by the companion, without taking anyone's address
+fn validate_static_address(
+    address: &StaticAddress,
+    companion_address: &Ipv4Addr,
+    other_cameras: &[Ipv4Addr],
+) -> Result<()> {
+    let StaticAddress {
+        ip_address,
+        netmask,
+        gateway,
+    } = address;
+
+    let mask = u32::from(*netmask);
+    let prefix = mask.leading_ones();
+    if mask.count_ones() != prefix || prefix == 0 || prefix > 30 {
+        return Err(anyhow!("Invalid netmask {netmask}"));
+    }
+
+    let network = u32::from(*ip_address) & mask;
+    let is_host = |address: &Ipv4Addr| {
+        let address = u32::from(*address);
+        address & mask == network && address != network && address != network | !mask
+    };
+
+    if ip_address.is_loopback() || ip_address.is_multicast() || !is_host(ip_address) {
+        return Err(anyhow!(
+            "{ip_address} is not a valid host address in {ip_address}/{prefix}"
+        ));
+    }
+
+    if ip_address == companion_address {
+        return Err(anyhow!(
+            "{ip_address} conflicts with the companion computer address"
+        ));
+    }
+
+    if !is_host(companion_address) {
+        return Err(anyhow!(
+            "{ip_address}/{prefix} would leave the camera outside the companion computer subnet ({companion_address})"
+        ));
+    }
+
+    if other_cameras.contains(ip_address) {
+        return Err(anyhow!("{ip_address} is already used by another camera"));
+    }
+
+    if let Some(gateway) = gateway.filter(|gateway| gateway == ip_address || !is_host(gateway)) {
+        return Err(anyhow!(
+            "Gateway {gateway} is not reachable from {ip_address}/{prefix}"
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn address(ip_address: &str, netmask: &str, gateway: Option<&str>) -> StaticAddress {
+        StaticAddress {
+            ip_address: ip_address.parse().unwrap(),
+            netmask: netmask.parse().unwrap(),
+            gateway: gateway.map(|gateway| gateway.parse().unwrap()),
+        }
+    }
+
+    #[test]
+    fn validate_static_address_test() {
+        let companion = Ipv4Addr::new(192, 168, 2, 2);
+        let other_cameras = [Ipv4Addr::new(192, 168, 2, 11)];
+
+        let validate =
+            |address: &StaticAddress| validate_static_address(address, &companion, &other_cameras);
+
+        assert!(
+            validate(&address(
+                "192.168.2.10",
+                "255.255.255.0",
+                Some("192.168.2.1")
+            ))
+            .is_ok()
+        );
+        assert!(validate(&address("192.168.2.10", "255.255.255.0", None)).is_ok());
+        // Invalid netmask
+        assert!(validate(&address("192.168.2.10", "255.0.255.0", None)).is_err());
+        // Network and broadcast addresses
+        assert!(validate(&address("192.168.2.0", "255.255.255.0", None)).is_err());
+        assert!(validate(&address("192.168.2.255", "255.255.255.0", None)).is_err());
+        // Companion conflict
+        assert!(validate(&address("192.168.2.2", "255.255.255.0", None)).is_err());
+        // Outside the companion subnet
+        assert!(validate(&address("192.168.3.10", "255.255.255.0", None)).is_err());
+        assert!(validate(&address("192.168.2.130", "255.255.255.128", None)).is_err());
+        // Another camera's address
+        assert!(validate(&address("192.168.2.11", "255.255.255.0", None)).is_err());
+        // Unreachable gateway
+        assert!(validate(&address("192.168.2.10", "255.255.255.0", Some("10.0.0.1"))).is_err());
+        assert!(
+            validate(&address(
+                "192.168.2.10",
+                "255.255.255.0",
+                Some("192.168.2.10")
+            ))
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn parse_static_address_test() {
+        let net_config = NetParameterSettings {
+            ip_address: Some("192.168.2.10".to_string()),
+            netmask: Some("255.255.255.0".to_string()),
+            gateway: Some("0.0.0.0".to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            parse_static_address(&net_config).unwrap(),
+            address("192.168.2.10", "255.255.255.0", None)
+        );
+
+        let net_config = NetParameterSettings {
+            ip_address: Some("192.168.2.300".to_string()),
+            ..net_config
+        };
+        assert!(parse_static_address(&net_config).is_err());
+    }
+}
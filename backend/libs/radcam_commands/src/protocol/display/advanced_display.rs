@@ -273,6 +273,19 @@ pub struct AdvancedParameterSetting {
     pub set_default: Option<u8>,
 }
 
+impl AdvancedParameterSetting {
+    /// The settings without the fields that trigger an action, which would fire again each time
+    /// the settings are sent
+    pub fn without_triggers(self) -> Self {
+        Self {
+            iris_level: None,
+            once_awb: None,
+            set_default: None,
+            ..self
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
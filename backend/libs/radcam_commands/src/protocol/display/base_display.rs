@@ -96,6 +96,17 @@ pub struct BaseParameterSetting {
     pub rotate: Option<BaseRotateValue>,
 }
 
+impl BaseParameterSetting {
+    /// The settings without the fields that trigger an action, which would fire again each time
+    /// the settings are sent
+    pub fn without_triggers(self) -> Self {
+        Self {
+            set_default: None,
+            ..self
+        }
+    }
+}
+
 #[derive(Default, Debug, Clone, PartialEq, Serialize_repr, Deserialize_repr, TS)]
 #[tsync]
 #[repr(u32)]
@@ -1,14 +1,64 @@
-use serde::Deserialize;
+use anyhow::{Context, Result};
+use serde::{Deserialize, de::DeserializeOwned};
+
+use crate::CameraError;
 
 pub mod display;
+pub mod network;
+pub mod osd;
+pub mod system;
 pub mod video;
 
+/// The return-code envelope the camera answers with, around the actual data
 #[derive(Debug, Clone, PartialEq, Deserialize)]
-pub struct Response {
+pub struct Response<T> {
+    /// Zero on success. Missing when the camera answers only with the data
+    #[serde(default)]
     pub code: i32,
-    pub device_mac: String,
-    pub device_id: String,
-    pub log: String,
-    pub device_ip: String,
-    pub sign_tby: String,
+    #[serde(default)]
+    pub log: Option<String>,
+    #[serde(flatten)]
+    pub data: T,
+}
+
+/// Unwraps the data from the camera answer, turning non-success return codes into errors
+pub fn parse_response<T: DeserializeOwned>(value: serde_json::Value) -> Result<T> {
+    let response: Response<T> =
+        serde_json::from_value(value).context("Unexpected answer from the camera")?;
+
+    if response.code != 0 {
+        return Err(CameraError::ReturnCode {
+            code: response.code,
+            log: response.log.unwrap_or_default(),
+        }
+        .into());
+    }
+
+    Ok(response.data)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn parse_response_test() {
+        let value = parse_response::<serde_json::Value>(json!({"code": 0, "brightness": 50}));
+        assert_eq!(value.unwrap(), json!({"brightness": 50}));
+
+        let value = parse_response::<serde_json::Value>(json!({"brightness": 50}));
+        assert_eq!(value.unwrap(), json!({"brightness": 50}));
+
+        let error = parse_response::<serde_json::Value>(json!({"code": -3, "log": "bad param"}))
+            .unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<CameraError>(),
+            Some(&CameraError::ReturnCode {
+                code: -3,
+                log: "bad param".to_string(),
+            })
+        );
+    }
 }
@@ -0,0 +1,13 @@
+use serde::{Deserialize, Serialize};
+use serde_repr::*;
+use ts_rs::TS;
+
+pub mod net_parameters;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum NetworkCommand {
+    /// Get Network Parameter Setting
+    Get(net_parameters::NetParameterSettings),
+    /// Set Network Parameter Setting
+    Set(net_parameters::NetParameterSettings),
+}
@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+use tsync::tsync;
+
+use super::*;
+
+#[skip_serializing_none]
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[tsync]
+pub struct NetParameterSettings {
+    /// Whether the address is assigned by DHCP.
+    pub dhcp: Option<NetDhcpValue>,
+    /// IPv4 address.
+    pub ip_address: Option<String>,
+    /// IPv4 netmask.
+    pub netmask: Option<String>,
+    /// IPv4 default gateway.
+    pub gateway: Option<String>,
+    /// Primary DNS server.
+    pub dns1: Option<String>,
+    /// Secondary DNS server.
+    pub dns2: Option<String>,
+    /// MAC address. Read-only.
+    pub mac: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Serialize_repr, Deserialize_repr, TS)]
+#[tsync]
+#[repr(u8)]
+pub enum NetDhcpValue {
+    #[default]
+    Close = 0,
+    Open = 1,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use utils::deserialize;
+
+    use super::*;
+
+    #[test]
+    fn deserialize_test() {
+        let json = json!({
+            "dhcp": 0,
+            "ip_address": "192.168.2.10",
+            "netmask": "255.255.255.0",
+            "gateway": "192.168.2.1",
+            "dns1": "8.8.8.8",
+            "mac": "bc:07:18:01:c5:0f"
+        })
+        .to_string();
+
+        let params = deserialize::<NetParameterSettings>(&json).expect("Failed deserializing");
+
+        let expected_params = NetParameterSettings {
+            dhcp: Some(NetDhcpValue::Close),
+            ip_address: Some("192.168.2.10".to_string()),
+            netmask: Some("255.255.255.0".to_string()),
+            gateway: Some("192.168.2.1".to_string()),
+            dns1: Some("8.8.8.8".to_string()),
+            dns2: None,
+            mac: Some("bc:07:18:01:c5:0f".to_string()),
+        };
+
+        assert_eq!(params, expected_params);
+    }
+}
@@ -0,0 +1 @@
+pub mod osd_parameters;
@@ -0,0 +1,77 @@
+use serde::{Deserialize, Serialize};
+use serde_repr::*;
+use serde_with::skip_serializing_none;
+use ts_rs::TS;
+use tsync::tsync;
+
+#[skip_serializing_none]
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[tsync]
+pub struct OsdParameterSettings {
+    /// Custom text overlays.
+    pub text_list: Option<Vec<OsdTextValue>>,
+}
+
+#[skip_serializing_none]
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[tsync]
+pub struct OsdTextValue {
+    /// Overlay index. Range: [0..=7]
+    pub index: u8,
+    /// Whether the overlay is displayed.
+    pub enable: Option<OsdEnableValue>,
+    /// Overlay text.
+    pub text: Option<String>,
+    /// Horizontal position, in percent of the image width. Range: [0..=100]
+    pub pos_x: Option<u8>,
+    /// Vertical position, in percent of the image height. Range: [0..=100]
+    pub pos_y: Option<u8>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize_repr, Deserialize_repr, TS)]
+#[tsync]
+#[repr(u8)]
+pub enum OsdEnableValue {
+    #[default]
+    Close = 0,
+    Open = 1,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use utils::deserialize;
+
+    use super::*;
+
+    #[test]
+    fn deserialize_test() {
+        let json = json!({
+            "text_list": [
+                {
+                    "index": 0,
+                    "enable": 1,
+                    "text": "Depth: 12.3 m",
+                    "pos_x": 2,
+                    "pos_y": 90
+                }
+            ]
+        })
+        .to_string();
+
+        let params = deserialize::<OsdParameterSettings>(&json).expect("Failed deserializing");
+
+        let expected_params = OsdParameterSettings {
+            text_list: Some(vec![OsdTextValue {
+                index: 0,
+                enable: Some(OsdEnableValue::Open),
+                text: Some("Depth: 12.3 m".to_string()),
+                pos_x: Some(2),
+                pos_y: Some(90),
+            }]),
+        };
+
+        assert_eq!(params, expected_params);
+    }
+}
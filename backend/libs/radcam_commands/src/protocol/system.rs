@@ -0,0 +1,103 @@
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+use ts_rs::TS;
+use tsync::tsync;
+
+/// The camera's system information from `getSysConfig`, under any of the names firmwares use
+#[skip_serializing_none]
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[tsync]
+pub struct SysConfig {
+    /// Device model.
+    #[serde(alias = "device_model", alias = "deviceModel")]
+    pub model: Option<String>,
+    /// Firmware version.
+    #[serde(
+        alias = "soft_version",
+        alias = "softVersion",
+        alias = "firmware_version"
+    )]
+    pub firmware: Option<String>,
+    /// Hardware version.
+    #[serde(
+        alias = "hard_version",
+        alias = "hardVersion",
+        alias = "hardware_version"
+    )]
+    pub hardware: Option<String>,
+    /// MAC address.
+    #[serde(alias = "device_mac")]
+    pub mac: Option<String>,
+    /// Time since the camera booted, in seconds.
+    #[serde(alias = "run_time", alias = "runTime")]
+    pub uptime: Option<u64>,
+}
+
+/// The camera's clock, from `getSysTime` and to `setSysTime`
+#[skip_serializing_none]
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[tsync]
+pub struct SystemTime {
+    /// UTC time, as `YYYY-MM-DD HH:MM:SS`.
+    pub time: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::protocol::parse_response;
+
+    use super::*;
+
+    #[test]
+    fn deserialize_test() {
+        let json = json!({
+            "code": 0,
+            "device_id": "1",
+            "device_ip": "192.168.2.10",
+            "device_mac": "bc:07:18:01:c5:0f",
+            "model": "RadCam",
+            "firmware": "V1.2.3",
+            "uptime": 3600,
+        });
+
+        let sys_config = parse_response::<SysConfig>(json).unwrap();
+
+        assert_eq!(
+            sys_config,
+            SysConfig {
+                model: Some("RadCam".to_string()),
+                firmware: Some("V1.2.3".to_string()),
+                hardware: None,
+                mac: Some("bc:07:18:01:c5:0f".to_string()),
+                uptime: Some(3600),
+            }
+        );
+    }
+
+    #[test]
+    fn deserialize_aliases_test() {
+        let json = json!({
+            "code": 0,
+            "device_mac": "bc-07-18-01-c5-0f",
+            "device_model": "RadCam",
+            "soft_version": "V1.2.3",
+            "hard_version": "V2",
+            "run_time": 60,
+        });
+
+        let sys_config = parse_response::<SysConfig>(json).unwrap();
+
+        assert_eq!(
+            sys_config,
+            SysConfig {
+                model: Some("RadCam".to_string()),
+                firmware: Some("V1.2.3".to_string()),
+                hardware: Some("V2".to_string()),
+                mac: Some("bc-07-18-01-c5-0f".to_string()),
+                uptime: Some(60),
+            }
+        );
+    }
+}
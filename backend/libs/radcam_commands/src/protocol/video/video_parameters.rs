@@ -36,8 +36,8 @@ pub struct VideoParameterSettings {
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
 #[tsync]
 pub struct VideoResolutionValue {
-    width: u16,
-    height: u16,
+    pub width: u16,
+    pub height: u16,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize_repr, Deserialize_repr, TS)]
@@ -0,0 +1,826 @@
+use anyhow::Result;
+use axum::{extract::Path, http::StatusCode, response::IntoResponse};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::*;
+use ts_rs::TS;
+
+use crate::{
+    Action,
+    protocol::{
+        display::{advanced_display::*, base_display::*},
+        video::video_parameters::*,
+    },
+};
+
+/// The settings each camera action takes, so clients don't need to hardcode them
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+pub struct ActionSchema {
+    /// The action name, as in `CameraControl`
+    pub action: String,
+    pub fields: Vec<FieldSchema>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+pub struct FieldSchema {
+    /// The field name in the action json
+    pub name: String,
+    pub label: String,
+    pub kind: FieldKind,
+    pub unit: Option<String>,
+    /// Only answered by the camera, it ignores it when set
+    pub read_only: bool,
+    /// The field only applies while all of these hold
+    pub depends_on: Vec<FieldDependency>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[serde(tag = "type")]
+pub enum FieldKind {
+    Integer {
+        min: i64,
+        max: i64,
+    },
+    Enum {
+        options: Vec<EnumOption>,
+    },
+    /// Does something once when set to `1`, like restoring the defaults
+    Trigger,
+    /// A list of `{ width, height }`
+    Resolutions,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+pub struct EnumOption {
+    pub value: i64,
+    pub label: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+pub struct FieldDependency {
+    pub field: String,
+    /// The values of `field` that make the dependent field apply
+    pub values: Vec<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaError {
+    pub action: String,
+    pub reasons: Vec<String>,
+}
+
+impl std::fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Invalid {}: {}", self.action, self.reasons.join("; "))
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+/// The options of a fieldless enum, with the labels shown to the user
+macro_rules! options {
+    ($enum:ident { $($variant:ident => $label:literal),+ $(,)? }) => {
+        FieldKind::Enum {
+            options: vec![$(EnumOption {
+                value: $enum::$variant as i64,
+                label: $label.to_string(),
+            }),+],
+        }
+    };
+}
+
+impl FieldSchema {
+    fn new(name: &str, label: &str, kind: FieldKind) -> Self {
+        Self {
+            name: name.to_string(),
+            label: label.to_string(),
+            kind,
+            unit: None,
+            read_only: false,
+            depends_on: vec![],
+        }
+    }
+
+    fn unit(mut self, unit: &str) -> Self {
+        self.unit = Some(unit.to_string());
+        self
+    }
+
+    fn read_only(mut self) -> Self {
+        self.read_only = true;
+        self
+    }
+
+    fn depends_on(mut self, field: &str, values: &[i64]) -> Self {
+        self.depends_on.push(FieldDependency {
+            field: field.to_string(),
+            values: values.to_vec(),
+        });
+        self
+    }
+}
+
+fn byte(name: &str, label: &str) -> FieldSchema {
+    FieldSchema::new(name, label, FieldKind::Integer { min: 0, max: 255 })
+}
+
+fn integer(name: &str, label: &str, min: i64, max: i64) -> FieldSchema {
+    FieldSchema::new(name, label, FieldKind::Integer { min, max })
+}
+
+fn enumeration(name: &str, label: &str, kind: FieldKind) -> FieldSchema {
+    FieldSchema::new(name, label, kind)
+}
+
+fn trigger(name: &str, label: &str) -> FieldSchema {
+    FieldSchema::new(name, label, FieldKind::Trigger)
+}
+
+/// The schemas of all the actions that change camera settings
+pub fn schemas() -> Vec<ActionSchema> {
+    vec![
+        base_display_schema(),
+        advanced_display_schema(),
+        video_parameters_schema(),
+    ]
+}
+
+pub fn action_schema(action: &str) -> Option<ActionSchema> {
+    schemas().into_iter().find(|schema| schema.action == action)
+}
+
+fn base_display_schema() -> ActionSchema {
+    use BaseAutoGainModeValue as AutoGain;
+    use BaseAutoWhiteBalanceModeValue as WhiteBalance;
+    use BaseExposureModeValue as Exposure;
+    use BaseManualAGainEnableValue as ManualAGain;
+    use BaseManualDGainEnableValue as ManualDGain;
+
+    let manual_awb = &[WhiteBalance::Manual as i64];
+
+    ActionSchema {
+        action: "setImageAdjustment".to_string(),
+        fields: vec![
+            byte("hue", "Tonal"),
+            byte("brightness", "Brightness"),
+            byte("sharpness", "Sharpness"),
+            byte("contrast", "Contrast"),
+            byte("saturation", "Saturation"),
+            byte("gamma", "Gamma"),
+            byte("blc_level", "Backlight Compensation"),
+            enumeration(
+                "max_exposure",
+                "Exposure Time",
+                options!(BaseMaxExposureValue {
+                    _12 => "1/12",
+                    _25 => "1/25",
+                    _30 => "1/30",
+                    _50 => "1/50",
+                    _60 => "1/60",
+                    _100 => "1/100",
+                    _200 => "1/200",
+                    _400 => "1/400",
+                    _800 => "1/800",
+                    _1000 => "1/1000",
+                    _2000 => "1/2000",
+                    _4000 => "1/4000",
+                    _8000 => "1/8000",
+                }),
+            ),
+            trigger("set_default", "Restore Defaults"),
+            enumeration(
+                "antiFog",
+                "Dehaze",
+                options!(BaseAntiFogValue { Close => "Off", Open => "On" }),
+            ),
+            enumeration(
+                "frameTurbo_pro",
+                "Frame Turbo",
+                options!(BaseFrameTurboValue {
+                    Close => "Off",
+                    HighFrameRates => "High Frame Rate",
+                    UltraHighFrameRates => "Ultra High Frame Rate",
+                }),
+            ),
+            enumeration(
+                "sceneMode",
+                "Scene Mode (not working, use the advanced one)",
+                options!(BaseSceneModeValue {
+                    FaceCapture => "Face Capture",
+                    LicensePlateCapture => "License Plate Capture",
+                }),
+            ),
+            enumeration(
+                "AE_strategy_mode",
+                "Exposure Strategy Mode",
+                options!(BaseAutoExposureStrategyModeValue {
+                    HighLightPriority => "Highlight Priority",
+                    LowLightPriority => "Low Light Priority",
+                }),
+            ),
+            enumeration(
+                "auto_exposureEx",
+                "Exposure Mode",
+                options!(BaseExposureModeValue { Auto => "Auto", Manual => "Manual" }),
+            ),
+            enumeration(
+                "exposure_time",
+                "Manual Exposure Time",
+                options!(BaseExposureTimeValue {
+                    _12 => "1/12",
+                    _25 => "1/25",
+                    _30 => "1/30",
+                    _50 => "1/50",
+                    _60 => "1/60",
+                    _100 => "1/100",
+                    _200 => "1/200",
+                    _400 => "1/400",
+                    _800 => "1/800",
+                    _1000 => "1/1000",
+                    _2000 => "1/2000",
+                    _4000 => "1/4000",
+                    _8000 => "1/8000",
+                    _10000 => "1/10000",
+                    _34464 => "1/34464",
+                }),
+            )
+            .depends_on("auto_exposureEx", &[Exposure::Manual as i64]),
+            enumeration(
+                "auto_awb",
+                "White Balance Mode",
+                options!(BaseAutoWhiteBalanceModeValue { Auto => "Auto", Manual => "Manual" }),
+            ),
+            byte("awb_red", "Manual White Balance Red").depends_on("auto_awb", manual_awb),
+            byte("awb_green", "Manual White Balance Green").depends_on("auto_awb", manual_awb),
+            byte("awb_blue", "Manual White Balance Blue").depends_on("auto_awb", manual_awb),
+            enumeration(
+                "awb_auto_mode",
+                "White Balance Scene",
+                options!(BaseAutoWhiteBalanceSceneValue {
+                    Scene1 => "Scene 1",
+                    Scene2 => "Scene 2",
+                }),
+            )
+            .depends_on("auto_awb", &[WhiteBalance::Auto as i64]),
+            byte("awb_style_red", "Manual White Balance Style Red"),
+            byte("awb_style_green", "Manual White Balance Style Green"),
+            byte("awb_style_blue", "Manual White Balance Style Blue"),
+            enumeration(
+                "auto_gain_mode",
+                "Automatic Gain Mode",
+                options!(BaseAutoGainModeValue { Auto => "Auto", Manual => "Manual" }),
+            ),
+            byte("auto_DGain_max", "Automatic Maximum D Gain")
+                .depends_on("auto_gain_mode", &[AutoGain::Auto as i64]),
+            byte("auto_AGain_max", "Automatic Maximum A Gain")
+                .depends_on("auto_gain_mode", &[AutoGain::Auto as i64]),
+            byte("max_sys_gain", "Max Sys Gain"),
+            enumeration(
+                "manual_AGain_enable",
+                "Enable Manual A Gain",
+                options!(BaseManualAGainEnableValue { Close => "Off", Open => "On" }),
+            ),
+            byte("manual_AGain", "Manual A Gain")
+                .depends_on("manual_AGain_enable", &[ManualAGain::Open as i64]),
+            enumeration(
+                "manual_DGain_enable",
+                "Enable Manual D Gain",
+                options!(BaseManualDGainEnableValue { Close => "Off", Open => "On" }),
+            ),
+            byte("manual_DGain", "Manual D Gain")
+                .depends_on("manual_DGain_enable", &[ManualDGain::Open as i64]),
+            enumeration(
+                "rotate",
+                "Image Rotation",
+                options!(BaseRotateValue { _0 => "0", _90 => "90", _180 => "180", _270 => "270" }),
+            )
+            .unit("°"),
+        ],
+    }
+}
+
+fn advanced_display_schema() -> ActionSchema {
+    use AdvancedDisplayAutoIrisValue as AutoIris;
+    use AdvancedDisplayColorBlackValue as ColorBlack;
+    use AdvancedDisplayInfrDetectModeValue as DetectMode;
+    use AdvancedDisplayLampTypeValue as LampType;
+    use AdvancedDisplayLightControlModeValue as LightMode;
+    use AdvancedDisplayWDRSensorValue as WdrSensor;
+
+    let video_detection = &[DetectMode::VideoDetection as i64];
+    let time_control = &[DetectMode::TimeControl as i64];
+
+    ActionSchema {
+        action: "setImageAdjustmentEx".to_string(),
+        fields: vec![
+            enumeration(
+                "mirror",
+                "Mirror",
+                options!(AdvancedDisplayMirrorValue { Open => "On", Close => "Off" }),
+            ),
+            enumeration(
+                "flip",
+                "Flip",
+                options!(AdvancedDisplayFlipValue { Open => "On", Close => "Off" }),
+            ),
+            enumeration(
+                "power_freq",
+                "Video Format",
+                options!(AdvancedDisplayPowerFreqValue {
+                    NTSC => "NTSC (60 Hz)",
+                    PAL => "PAL (50 Hz)",
+                }),
+            ),
+            enumeration(
+                "color_black",
+                "Color Turned Black",
+                options!(AdvancedDisplayColorBlackValue {
+                    Color => "Always Color",
+                    Auto => "Auto",
+                }),
+            ),
+            enumeration(
+                "infr_detect_mode",
+                "Video Detection Mode",
+                options!(AdvancedDisplayInfrDetectModeValue {
+                    VideoDetection => "Video Detection",
+                    TimeControl => "Schedule",
+                    PhotosensitiveDetection => "Photosensitive Detection",
+                }),
+            )
+            .depends_on("color_black", &[ColorBlack::Auto as i64]),
+            byte("sens_day_to_night", "Color To Black Sensitivity")
+                .depends_on("infr_detect_mode", video_detection),
+            byte("sens_night_to_day", "Black To Color Sensitivity")
+                .depends_on("infr_detect_mode", video_detection),
+            integer("infr_day_h", "Color Turned Start Time Hour", 0, 23)
+                .unit("h")
+                .depends_on("infr_detect_mode", time_control),
+            integer("infr_day_m", "Color Turned Start Time Minute", 0, 59)
+                .unit("min")
+                .depends_on("infr_detect_mode", time_control),
+            integer("infr_night_h", "Color Turned End Time Hour", 0, 23)
+                .unit("h")
+                .depends_on("infr_detect_mode", time_control),
+            integer("infr_night_m", "Color Turned End Time Minute", 0, 59)
+                .unit("min")
+                .depends_on("infr_detect_mode", time_control),
+            enumeration(
+                "lens_correction",
+                "Lens Correction",
+                options!(AdvancedDisplayLensCorrectionValue { Open => "On", Close => "Off" }),
+            ),
+            byte("wdr_level", "Wide Dynamic Strength"),
+            enumeration(
+                "ircut_level",
+                "IRCUT Level",
+                options!(AdvancedDisplayIRCUTLevelValue { LowLevel => "Low", HighLevel => "High" }),
+            ),
+            enumeration(
+                "ldr_level",
+                "Photosensitive Level",
+                options!(AdvancedDisplayLDRLevelValue { LowLevel => "Low", HighLevel => "High" }),
+            ),
+            enumeration(
+                "led_control_mode",
+                "Light Pattern",
+                options!(AdvancedDisplayLightControlModeValue {
+                    ElectricalLevel => "Electrical Level",
+                    PWM => "PWM",
+                }),
+            ),
+            enumeration(
+                "lamp_type",
+                "Light Type",
+                options!(AdvancedDisplayLampTypeValue {
+                    InfraredLamp => "Infrared Lamp",
+                    WhiteLight => "White Light",
+                    Auto => "Auto",
+                }),
+            ),
+            enumeration(
+                "led_control_avail",
+                "Light Enable Level",
+                options!(AdvancedDisplayLedControlAvailValue {
+                    LowLevel => "Low",
+                    HighLevel => "High",
+                }),
+            )
+            .depends_on("led_control_mode", &[LightMode::ElectricalLevel as i64]),
+            byte("ir_level", "Infrared Lamp Brightness")
+                .depends_on("led_control_mode", &[LightMode::PWM as i64]),
+            byte("led_level", "White Light Brightness")
+                .depends_on("lamp_type", &[LampType::WhiteLight as i64])
+                .depends_on("led_control_mode", &[LightMode::PWM as i64]),
+            enumeration(
+                "led_control",
+                "IR Control",
+                options!(AdvancedDisplayLedControlValue {
+                    Auto => "Auto",
+                    Open => "On",
+                    Close => "Off",
+                }),
+            ),
+            enumeration(
+                "auto_iris",
+                "Aperture Mode",
+                options!(AdvancedDisplayAutoIrisValue {
+                    Open => "Open",
+                    Close => "Closed",
+                    Manual => "Manual",
+                }),
+            ),
+            byte("irisLevel", "Aperture PWM Duty Cycle")
+                .read_only()
+                .depends_on("auto_iris", &[AutoIris::Manual as i64]),
+            enumeration(
+                "noiseReduction",
+                "3D Noise Reduction",
+                options!(AdvancedDisplayNoiseReductionValue {
+                    Close => "Off",
+                    Low => "Low",
+                    Middle => "Medium",
+                    High => "High",
+                }),
+            ),
+            enumeration(
+                "wdr_sensor",
+                "WDR Enable",
+                options!(AdvancedDisplayWDRSensorValue { Close => "Off", Open => "On" }),
+            ),
+            byte("wdr_level_sensor", "WDR Strength")
+                .depends_on("wdr_sensor", &[WdrSensor::Open as i64]),
+            enumeration(
+                "hlc_enable",
+                "HLC",
+                options!(AdvancedDisplayHlcEnableValue { Close => "Off", Open => "On" }),
+            ),
+            enumeration(
+                "low_farme_rate",
+                "Slow Shutter",
+                options!(AdvancedDisplayLowFramerateValue { Close => "Off", Open => "On" }),
+            ),
+            enumeration(
+                "_2DNR_level",
+                "2D NR",
+                options!(AdvancedDisplay2dNrLevelValue {
+                    Low => "Low",
+                    Middle => "Medium",
+                    High => "High",
+                }),
+            ),
+            enumeration(
+                "anti_flicker",
+                "Anti Flicker",
+                options!(AdvancedDisplayAntiflickerValue {
+                    Close => "Off",
+                    Auto => "Auto",
+                    _50HZ => "50 Hz",
+                    _60HZ => "60 Hz",
+                }),
+            ),
+            enumeration(
+                "scene_mode",
+                "Scene Mode",
+                options!(AdvancedDisplaySceneModeValue {
+                    IPC => "Surveillance",
+                    FaceCapture => "Face Capture",
+                    LicensePlateCapture => "License Plate Capture",
+                }),
+            ),
+            trigger("onceAWB", "Automatic White Balance Once"),
+            trigger("set_default", "Restore Defaults"),
+        ],
+    }
+}
+
+fn video_parameters_schema() -> ActionSchema {
+    let max = u16::MAX as i64;
+
+    ActionSchema {
+        action: "setVencConf".to_string(),
+        fields: vec![
+            enumeration(
+                "channel",
+                "Video Stream Channel",
+                options!(VideoChannelValue {
+                    MainStream => "Main Stream",
+                    AuxiliaryStream => "Auxiliary Stream",
+                    ThirdStream => "Third Stream",
+                }),
+            ),
+            enumeration(
+                "encode_profile",
+                "Encoding Scheme",
+                options!(VideoEncodingProfileValue {
+                    Baseline => "Baseline",
+                    MainProfile => "Main",
+                    HighProfile => "High",
+                }),
+            ),
+            enumeration(
+                "encode_type",
+                "Video Coding",
+                options!(VideoEncodeTypeValue { H264 => "H.264", H265 => "H.265" }),
+            ),
+            FieldSchema::new(
+                "pixel_list",
+                "Supported Resolutions",
+                FieldKind::Resolutions,
+            )
+            .read_only(),
+            integer("pic_width", "Width, from the supported resolutions", 1, max).unit("px"),
+            integer(
+                "pic_height",
+                "Height, from the supported resolutions",
+                1,
+                max,
+            )
+            .unit("px"),
+            enumeration(
+                "rc_mode",
+                "Bitrate Type",
+                options!(VideoRcModeValue {
+                    VariableBitRate => "Variable Bitrate",
+                    ConstantBitRate => "Constant Bitrate",
+                }),
+            ),
+            integer("bitrate", "Bitrate", 1, max).unit("kbps"),
+            integer("max_framerate", "Maximum Frame Rate", 1, max)
+                .unit("fps")
+                .read_only(),
+            integer("frame_rate", "Frame Rate, up to the maximum", 1, max).unit("fps"),
+            integer("gop", "I-Frame Interval", 1, max).unit("frames"),
+        ],
+    }
+}
+
+/// Checks the action settings against its schema, if it has one. Fields that don't apply because
+/// of the other fields in the same request are left for the camera to ignore, so the settings as
+/// read from the camera can be sent back as they are
+pub fn validate(action: &Action) -> Result<()> {
+    let value = serde_json::to_value(action)?;
+
+    let Some(schema) = value
+        .get("action")
+        .and_then(Value::as_str)
+        .and_then(action_schema)
+    else {
+        return Ok(());
+    };
+
+    let payload = value.get("json").cloned().unwrap_or_default();
+
+    let reasons = check(&schema, &payload);
+    if !reasons.is_empty() {
+        return Err(SchemaError {
+            action: schema.action,
+            reasons,
+        }
+        .into());
+    }
+
+    Ok(())
+}
+
+fn check(schema: &ActionSchema, payload: &Value) -> Vec<String> {
+    let mut reasons = Vec::new();
+
+    for field in &schema.fields {
+        let Some(value) = payload.get(&field.name).filter(|value| !value.is_null()) else {
+            continue;
+        };
+
+        if let Some(reason) = check_kind(field, value) {
+            reasons.push(reason);
+        }
+
+        if let Some(dependency) = field.depends_on.iter().find(|dependency| {
+            payload
+                .get(&dependency.field)
+                .and_then(Value::as_i64)
+                .is_some_and(|value| !dependency.values.contains(&value))
+        }) {
+            debug!(
+                "{} is ignored by the camera unless {} is one of {:?}",
+                field.name, dependency.field, dependency.values
+            );
+        }
+    }
+
+    reasons
+}
+
+fn check_kind(field: &FieldSchema, value: &Value) -> Option<String> {
+    let name = &field.name;
+
+    match &field.kind {
+        FieldKind::Integer { min, max } => match value.as_i64() {
+            Some(integer) if (*min..=*max).contains(&integer) => None,
+            _ => Some(format!(
+                "{name} should be between {min} and {max}, not {value}"
+            )),
+        },
+        FieldKind::Enum { options } => match value.as_i64() {
+            Some(integer) if options.iter().any(|option| option.value == integer) => None,
+            _ => Some(format!("{name} doesn't take {value}")),
+        },
+        FieldKind::Trigger => match value.as_i64() {
+            Some(0 | 1) => None,
+            _ => Some(format!("{name} should be 0 or 1, not {value}")),
+        },
+        FieldKind::Resolutions => None,
+    }
+}
+
+#[instrument(level = "debug")]
+pub async fn list() -> impl IntoResponse {
+    (StatusCode::OK, serde_json::to_string(&schemas()).unwrap()).into_response()
+}
+
+#[instrument(level = "debug")]
+pub async fn describe(Path(action): Path<String>) -> impl IntoResponse {
+    match action_schema(&action) {
+        Some(schema) => (StatusCode::OK, serde_json::to_string(&schema).unwrap()).into_response(),
+        None => (
+            StatusCode::NOT_FOUND,
+            format!("Action {action:?} has no schema"),
+        )
+            .into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn options_test() {
+        let rotate = base_display_schema()
+            .fields
+            .into_iter()
+            .find(|field| field.name == "rotate")
+            .unwrap();
+
+        let FieldKind::Enum { options } = rotate.kind else {
+            panic!("Expected an enum");
+        };
+        assert_eq!(
+            options[1],
+            EnumOption {
+                value: 1,
+                label: "90".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn check_test() {
+        let schema = advanced_display_schema();
+
+        assert!(check(&schema, &json!({ "infr_day_h": 7, "onceAWB": 1 })).is_empty());
+        // Values that don't apply are left for the camera
+        assert!(check(&schema, &json!({ "infr_detect_mode": 0, "infr_day_h": 7 })).is_empty());
+
+        assert_eq!(
+            check(
+                &schema,
+                &json!({ "infr_day_h": 24, "infr_day_m": 60, "onceAWB": 2, "mirror": 5 })
+            )
+            .len(),
+            4
+        );
+    }
+
+    #[test]
+    fn validate_test() {
+        let action = Action::SetImageAdjustmentEx(AdvancedParameterSetting {
+            infr_night_m: Some(75),
+            ..Default::default()
+        });
+
+        let error = validate(&action).unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<SchemaError>().unwrap().action,
+            "setImageAdjustmentEx"
+        );
+
+        assert!(validate(&Action::GetImageAdjustment).is_ok());
+    }
+
+    /// The json keys of a value, to compare against the schema field names
+    fn keys(value: impl Serialize) -> Vec<String> {
+        let mut keys = serde_json::to_value(value)
+            .unwrap()
+            .as_object()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect::<Vec<String>>();
+        keys.sort();
+        keys
+    }
+
+    fn field_names(schema: ActionSchema) -> Vec<String> {
+        let mut names = schema
+            .fields
+            .into_iter()
+            .map(|field| field.name)
+            .collect::<Vec<String>>();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn schema_fields_match_the_protocol_test() {
+        let base = BaseParameterSetting {
+            hue: Some(1),
+            brightness: Some(1),
+            sharpness: Some(1),
+            contrast: Some(1),
+            saturation: Some(1),
+            gamma: Some(1),
+            blc_level: Some(1),
+            max_exposure: Some(Default::default()),
+            set_default: Some(1),
+            anti_fog: Some(Default::default()),
+            frame_turbo_pro: Some(Default::default()),
+            scene_mode: Some(Default::default()),
+            auto_exposure_strategy_mode: Some(Default::default()),
+            auto_exposure_ex: Some(Default::default()),
+            exposure_time: Some(Default::default()),
+            auto_awb: Some(Default::default()),
+            awb_red: Some(1),
+            awb_green: Some(1),
+            awb_blue: Some(1),
+            awb_auto_mode: Some(Default::default()),
+            awb_style_red: Some(1),
+            awb_style_green: Some(1),
+            awb_style_blue: Some(1),
+            auto_gain_mode: Some(Default::default()),
+            auto_d_gain_max: Some(1),
+            auto_a_gain_max: Some(1),
+            max_sys_gain: Some(1),
+            manual_a_gain_enable: Some(Default::default()),
+            manual_a_gain: Some(1),
+            manual_d_gain_enable: Some(Default::default()),
+            manual_d_gain: Some(1),
+            rotate: Some(Default::default()),
+        };
+        assert_eq!(keys(base), field_names(base_display_schema()));
+
+        let advanced = AdvancedParameterSetting {
+            mirror: Some(Default::default()),
+            flip: Some(Default::default()),
+            power_freq: Some(Default::default()),
+            color_black: Some(Default::default()),
+            infr_detect_mode: Some(Default::default()),
+            sens_day_to_night: Some(1),
+            sens_night_to_day: Some(1),
+            infr_day_h: Some(1),
+            infr_day_m: Some(1),
+            infr_night_h: Some(1),
+            infr_night_m: Some(1),
+            lens_correction: Some(Default::default()),
+            wdr_level: Some(1),
+            ircut_level: Some(Default::default()),
+            ldr_level: Some(Default::default()),
+            led_control_mode: Some(Default::default()),
+            lamp_type: Some(Default::default()),
+            led_control_avail: Some(Default::default()),
+            ir_level: Some(1),
+            led_level: Some(1),
+            led_control: Some(Default::default()),
+            auto_iris: Some(Default::default()),
+            iris_level: Some(1),
+            noise_reduction: Some(Default::default()),
+            wdr_sensor: Some(Default::default()),
+            wdr_level_sensor: Some(1),
+            hlc_enable: Some(Default::default()),
+            low_farme_rate: Some(Default::default()),
+            _2d_nr_level: Some(Default::default()),
+            anti_flicker: Some(Default::default()),
+            scene_mode: Some(Default::default()),
+            once_awb: Some(1),
+            set_default: Some(1),
+        };
+        assert_eq!(keys(advanced), field_names(advanced_display_schema()));
+
+        let video = VideoParameterSettings {
+            channel: Some(Default::default()),
+            encode_profile: Some(Default::default()),
+            encode_type: Some(Default::default()),
+            pixel_list: Some(vec![VideoResolutionValue::default()]),
+            pic_width: Some(1),
+            pic_height: Some(1),
+            rc_mode: Some(Default::default()),
+            bitrate: Some(1),
+            max_framerate: Some(1),
+            frame_rate: Some(1),
+            gop: Some(1),
+        };
+        assert_eq!(keys(video), field_names(video_parameters_schema()));
+    }
+}
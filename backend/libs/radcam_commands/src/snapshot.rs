@@ -0,0 +1,306 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use axum::{
+    extract::Path,
+    http::{StatusCode, header},
+    response::IntoResponse,
+};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use tracing::*;
+use ts_rs::TS;
+use uuid::Uuid;
+
+use crate::camera_backend;
+
+static STORAGE: OnceCell<Storage> = OnceCell::new();
+
+const EXTENSION: &str = "jpg";
+/// Every JPEG starts with the Start Of Image marker
+pub(crate) const JPEG_SOI: [u8; 2] = [0xFF, 0xD8];
+const TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%S%3fZ";
+
+#[derive(Debug)]
+struct Storage {
+    path: PathBuf,
+    /// Maximum size of all snapshots together, in bytes
+    quota: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+pub struct Snapshot {
+    /// The file name, which identifies the snapshot
+    pub name: String,
+    #[ts(as = "String")]
+    pub camera_uuid: Uuid,
+    /// When it was taken, in RFC 3339
+    pub timestamp: String,
+    /// File size, in bytes
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotError {
+    NotFound {
+        name: String,
+    },
+    /// Storing the snapshot would exceed the quota
+    QuotaExceeded {
+        used: u64,
+        size: u64,
+        quota: u64,
+    },
+}
+
+impl std::fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SnapshotError::NotFound { name } => write!(f, "Snapshot {name:?} not found"),
+            SnapshotError::QuotaExceeded { used, size, quota } => write!(
+                f,
+                "Snapshot of {size} bytes doesn't fit the quota, {used} of {quota} bytes are used"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+/// Sets where snapshots are stored, Should be done inside main
+#[instrument(level = "debug")]
+pub fn init(path: String, quota: u64) {
+    STORAGE.get_or_init(|| Storage {
+        path: PathBuf::from(path),
+        quota,
+    });
+}
+
+/// Fetches a still from the camera, through its backend, and stores it
+#[instrument(level = "debug")]
+pub async fn take_snapshot(camera_uuid: &Uuid) -> Result<Snapshot> {
+    let storage = storage()?;
+
+    let content = camera_backend::fetch_snapshot(camera_uuid).await?;
+    let size = content.len() as u64;
+
+    let used = list_snapshots()
+        .await?
+        .iter()
+        .map(|snapshot| snapshot.size)
+        .sum::<u64>();
+    if used + size > storage.quota {
+        return Err(SnapshotError::QuotaExceeded {
+            used,
+            size,
+            quota: storage.quota,
+        }
+        .into());
+    }
+
+    let timestamp = Utc::now();
+    let name = snapshot_name(camera_uuid, &timestamp);
+
+    tokio::fs::create_dir_all(&storage.path)
+        .await
+        .context("Failed creating the snapshots directory")?;
+    tokio::fs::write(storage.path.join(&name), content)
+        .await
+        .context("Failed writing snapshot")?;
+
+    info!("Snapshot {name:?} taken from camera {camera_uuid}");
+
+    Ok(Snapshot {
+        name,
+        camera_uuid: *camera_uuid,
+        timestamp: timestamp.to_rfc3339(),
+        size,
+    })
+}
+
+/// All stored snapshots, oldest first
+#[instrument(level = "debug")]
+pub async fn list_snapshots() -> Result<Vec<Snapshot>> {
+    let storage = storage()?;
+
+    let mut entries = match tokio::fs::read_dir(&storage.path).await {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(error) => return Err(error).context("Failed reading the snapshots directory"),
+    };
+
+    let mut snapshots = Vec::new();
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name().to_string_lossy().to_string();
+
+        let Some((camera_uuid, timestamp)) = parse_snapshot_name(&name) else {
+            trace!("Skipping unknown file {name:?}");
+            continue;
+        };
+
+        snapshots.push(Snapshot {
+            name,
+            camera_uuid,
+            timestamp: timestamp.to_rfc3339(),
+            size: entry.metadata().await?.len(),
+        });
+    }
+
+    snapshots.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(snapshots)
+}
+
+#[instrument(level = "debug")]
+pub async fn read_snapshot(name: &str) -> Result<Vec<u8>> {
+    let path = snapshot_path(name)?;
+
+    tokio::fs::read(path)
+        .await
+        .map_err(|error| match error.kind() {
+            std::io::ErrorKind::NotFound => SnapshotError::NotFound {
+                name: name.to_string(),
+            }
+            .into(),
+            _ => anyhow::Error::new(error).context("Failed reading snapshot"),
+        })
+}
+
+#[instrument(level = "debug")]
+pub async fn delete_snapshot(name: &str) -> Result<()> {
+    let path = snapshot_path(name)?;
+
+    tokio::fs::remove_file(path)
+        .await
+        .map_err(|error| match error.kind() {
+            std::io::ErrorKind::NotFound => SnapshotError::NotFound {
+                name: name.to_string(),
+            }
+            .into(),
+            _ => anyhow::Error::new(error).context("Failed deleting snapshot"),
+        })
+}
+
+#[instrument(level = "debug")]
+pub async fn list() -> impl IntoResponse {
+    match list_snapshots().await {
+        Ok(snapshots) => {
+            (StatusCode::OK, serde_json::to_string(&snapshots).unwrap()).into_response()
+        }
+        Err(error) => error_response(error),
+    }
+}
+
+#[instrument(level = "debug")]
+pub async fn download(Path(name): Path<String>) -> impl IntoResponse {
+    match read_snapshot(&name).await {
+        Ok(content) => (
+            StatusCode::OK,
+            [
+                (header::CONTENT_TYPE, "image/jpeg".to_string()),
+                (
+                    header::CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"{name}\""),
+                ),
+            ],
+            content,
+        )
+            .into_response(),
+        Err(error) => error_response(error),
+    }
+}
+
+#[instrument(level = "debug")]
+pub async fn delete(Path(name): Path<String>) -> impl IntoResponse {
+    match delete_snapshot(&name).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(error) => error_response(error),
+    }
+}
+
+pub(crate) fn status_code(error: &SnapshotError) -> StatusCode {
+    match error {
+        SnapshotError::NotFound { .. } => StatusCode::NOT_FOUND,
+        SnapshotError::QuotaExceeded { .. } => StatusCode::INSUFFICIENT_STORAGE,
+    }
+}
+
+fn error_response(error: anyhow::Error) -> axum::response::Response {
+    warn!("Snapshot error: {error:#?}");
+
+    let status = error
+        .downcast_ref::<SnapshotError>()
+        .map(status_code)
+        .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+
+    (status, format!("{error:?}")).into_response()
+}
+
+fn storage() -> Result<&'static Storage> {
+    STORAGE.get().context("Snapshots storage not initialized")
+}
+
+/// Only names we generate are accepted, so paths can't escape the snapshots directory
+fn snapshot_path(name: &str) -> Result<PathBuf> {
+    if parse_snapshot_name(name).is_none() {
+        return Err(SnapshotError::NotFound {
+            name: name.to_string(),
+        }
+        .into());
+    }
+
+    Ok(storage()?.path.join(name))
+}
+
+fn snapshot_name(camera_uuid: &Uuid, timestamp: &DateTime<Utc>) -> String {
+    format!(
+        "{camera_uuid}_{}.{EXTENSION}",
+        timestamp.format(TIMESTAMP_FORMAT)
+    )
+}
+
+fn parse_snapshot_name(name: &str) -> Option<(Uuid, DateTime<Utc>)> {
+    let (camera_uuid, timestamp) = name
+        .strip_suffix(&format!(".{EXTENSION}"))?
+        .split_once('_')?;
+
+    let camera_uuid = Uuid::parse_str(camera_uuid).ok()?;
+    let timestamp = NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT)
+        .ok()?
+        .and_utc();
+
+    Some((camera_uuid, timestamp))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn snapshot_name_test() {
+        let camera_uuid = Uuid::parse_str("bc071801-c50f-8301-ac36-bc071801c50f").unwrap();
+        let timestamp = Utc.with_ymd_and_hms(2025, 3, 14, 15, 9, 26).unwrap()
+            + chrono::Duration::milliseconds(535);
+
+        let name = snapshot_name(&camera_uuid, &timestamp);
+        assert_eq!(
+            name,
+            "bc071801-c50f-8301-ac36-bc071801c50f_20250314T150926535Z.jpg"
+        );
+
+        assert_eq!(parse_snapshot_name(&name), Some((camera_uuid, timestamp)));
+    }
+
+    #[test]
+    fn parse_snapshot_name_test() {
+        assert_eq!(parse_snapshot_name("../settings.json"), None);
+        assert_eq!(parse_snapshot_name("../_20250314T150926535Z.jpg"), None);
+        assert_eq!(
+            parse_snapshot_name("bc071801-c50f-8301-ac36-bc071801c50f_yesterday.jpg"),
+            None
+        );
+    }
+}
@@ -0,0 +1,68 @@
+use axum::{http::StatusCode, response::IntoResponse};
+use indexmap::IndexMap;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use tracing::*;
+use ts_rs::TS;
+use uuid::Uuid;
+
+use crate::firmware::{CameraVersions, FirmwareUpgradeStatus};
+
+static STATUS: Lazy<RwLock<IndexMap<Uuid, CameraStatus>>> =
+    Lazy::new(|| RwLock::new(IndexMap::new()));
+
+/// What the manager knows about each camera, besides its settings
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize, TS)]
+pub struct CameraStatus {
+    pub time_sync: Option<TimeSyncStatus>,
+    pub versions: Option<CameraVersions>,
+    /// The last firmware upgrade, while it runs and after it ends
+    pub firmware_upgrade: Option<FirmwareUpgradeStatus>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+pub struct TimeSyncStatus {
+    pub source: TimeSource,
+    /// How far the camera clock was ahead of the source before the last sync, in milliseconds.
+    /// Missing if the camera couldn't tell its time
+    pub offset_ms: Option<i64>,
+    /// When the camera was last synchronised, in RFC 3339
+    pub synced_at: Option<String>,
+    /// Only present when the last sync failed
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+pub enum TimeSource {
+    /// The autopilot `SYSTEM_TIME`, usually from its GPS
+    Autopilot,
+    /// Our own clock, when the autopilot doesn't know the time
+    Host,
+}
+
+pub(crate) async fn update_status<R>(
+    camera_uuid: &Uuid,
+    update: impl FnOnce(&mut CameraStatus) -> R,
+) -> R {
+    update(STATUS.write().await.entry(*camera_uuid).or_default())
+}
+
+pub(crate) async fn camera_status(camera_uuid: &Uuid) -> CameraStatus {
+    STATUS
+        .read()
+        .await
+        .get(camera_uuid)
+        .cloned()
+        .unwrap_or_default()
+}
+
+#[instrument(level = "debug")]
+pub async fn status() -> impl IntoResponse {
+    let status = STATUS.read().await.clone();
+
+    match serde_json::to_string(&status) {
+        Ok(json) => json.into_response(),
+        Err(error) => (StatusCode::INTERNAL_SERVER_ERROR, format!("{error:?}")).into_response(),
+    }
+}
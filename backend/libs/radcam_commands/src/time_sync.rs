@@ -0,0 +1,164 @@
+use std::{future::Future, time::Duration};
+
+use anyhow::{Context, Result};
+use axum::Json;
+use chrono::{DateTime, NaiveDateTime, TimeDelta, Utc};
+use indexmap::IndexMap;
+use mcm_client::CameraKind;
+use tracing::*;
+use uuid::Uuid;
+
+use crate::{
+    Action, CameraControl, control_inner,
+    protocol::system::SystemTime,
+    status::{self, TimeSource, TimeSyncStatus},
+};
+
+/// How often new cameras are looked for
+const CHECK_PERIOD: Duration = Duration::from_secs(10);
+/// How often each camera is synchronised again
+const SYNC_PERIOD: Duration = Duration::from_secs(10 * 60);
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Keeps the cameras' clocks synchronised with the autopilot, or with our own clock when the
+/// autopilot doesn't know the time. Cameras are synchronised as soon as they are discovered, and
+/// then periodically
+#[instrument(level = "debug", skip(autopilot_time))]
+pub async fn time_sync_task<F, Fut>(autopilot_time: F)
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Option<DateTime<Utc>>>,
+{
+    let mut last_syncs: IndexMap<Uuid, tokio::time::Instant> = IndexMap::new();
+
+    loop {
+        // Also gives the MCM client the time to start
+        tokio::time::sleep(CHECK_PERIOD).await;
+
+        let cameras = mcm_client::cameras().await;
+        last_syncs.retain(|camera_uuid, _| cameras.contains_key(camera_uuid));
+
+        // The other cameras keep their clocks through their own means
+        let radcams = cameras
+            .values()
+            .filter(|camera| camera.kind == CameraKind::RadCam);
+
+        for camera_uuid in radcams.map(|camera| &camera.uuid) {
+            if last_syncs
+                .get(camera_uuid)
+                .is_some_and(|last_sync| last_sync.elapsed() < SYNC_PERIOD)
+            {
+                continue;
+            }
+
+            // Failures are retried in the next sync period, not to flood an unreachable camera
+            last_syncs.insert(*camera_uuid, tokio::time::Instant::now());
+
+            let (source, reference) = match autopilot_time().await {
+                Some(time) => (TimeSource::Autopilot, time),
+                None => (TimeSource::Host, Utc::now()),
+            };
+
+            match sync_camera(camera_uuid, reference - Utc::now()).await {
+                Ok(offset_ms) => {
+                    info!(
+                        "Camera {camera_uuid} clock synchronised from the {source:?} time, it was {offset_ms:?} ms off"
+                    );
+
+                    status::update_status(camera_uuid, |status| {
+                        status.time_sync = Some(TimeSyncStatus {
+                            source,
+                            offset_ms,
+                            synced_at: Some(Utc::now().to_rfc3339()),
+                            error: None,
+                        });
+                    })
+                    .await;
+                }
+                Err(error) => {
+                    warn!("Failed synchronising camera {camera_uuid} clock: {error:?}");
+
+                    // Keeps the last successful sync, so a transient failure doesn't hide it
+                    status::update_status(camera_uuid, |status| {
+                        status
+                            .time_sync
+                            .get_or_insert(TimeSyncStatus {
+                                source,
+                                offset_ms: None,
+                                synced_at: None,
+                                error: None,
+                            })
+                            .error = Some(format!("{error:#}"));
+                    })
+                    .await;
+                }
+            }
+        }
+    }
+}
+
+/// Sets the camera clock, returning how far ahead it was, in milliseconds. The reference is how
+/// far ahead the time source is from our clock
+#[instrument(level = "debug")]
+async fn sync_camera(camera_uuid: &Uuid, reference: TimeDelta) -> Result<Option<i64>> {
+    let now = || Utc::now() + reference;
+
+    // The camera answers only seconds, so the round trip is split to estimate when it was read
+    let before = now();
+    let offset_ms = match get_camera_time(camera_uuid).await {
+        Ok(camera_time) => {
+            let after = now();
+            let read_at = before + (after - before) / 2;
+
+            Some((camera_time - read_at).num_milliseconds())
+        }
+        Err(error) => {
+            debug!("Failed reading camera {camera_uuid} clock: {error:?}");
+            None
+        }
+    };
+
+    control_inner(Json(CameraControl {
+        camera_uuid: *camera_uuid,
+        action: Action::SetSystemTime(SystemTime {
+            time: Some(format_time(&now())),
+        }),
+    }))
+    .await
+    .context("Failed setting the camera clock")?;
+
+    Ok(offset_ms)
+}
+
+async fn get_camera_time(camera_uuid: &Uuid) -> Result<DateTime<Utc>> {
+    let time = crate::get_system_time(camera_uuid)
+        .await?
+        .time
+        .context("Camera answered without its time")?;
+
+    parse_time(&time)
+}
+
+fn format_time(time: &DateTime<Utc>) -> String {
+    time.format(TIME_FORMAT).to_string()
+}
+
+fn parse_time(time: &str) -> Result<DateTime<Utc>> {
+    Ok(NaiveDateTime::parse_from_str(time, TIME_FORMAT)
+        .with_context(|| format!("Invalid camera time {time:?}"))?
+        .and_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn time_format_test() {
+        let time = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+
+        assert_eq!(format_time(&time), "2023-11-14 22:13:20");
+        assert_eq!(parse_time("2023-11-14 22:13:20").unwrap(), time);
+        assert!(parse_time("yesterday").is_err());
+    }
+}
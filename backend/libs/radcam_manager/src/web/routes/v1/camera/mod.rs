@@ -1,16 +1,46 @@
 use axum::{
     Router,
-    routing::{get, post},
+    extract::DefaultBodyLimit,
+    routing::{get, post, put},
 };
 use radcam_commands;
 use tower_http::{cors::CorsLayer, trace::TraceLayer};
 use tracing::*;
 
+/// Firmware images are far bigger than the default request body limit
+const FIRMWARE_MAX_SIZE: usize = 256 * 1024 * 1024;
+
 #[instrument(level = "trace")]
 pub fn router() -> Router {
     Router::new()
         .route("/list", get(radcam_commands::list))
         .route("/control", post(radcam_commands::control))
+        .route("/backend", post(radcam_commands::camera_backend::control))
+        .route(
+            "/credentials/{camera_uuid}",
+            put(radcam_commands::camera_backend::credentials),
+        )
+        .route("/batch", post(radcam_commands::batch::batch))
+        .route("/jobs", post(radcam_commands::jobs::start))
+        .route("/labels", get(radcam_commands::labels::list))
+        .route(
+            "/labels/{camera_uuid}",
+            put(radcam_commands::labels::update),
+        )
+        .route("/status", get(radcam_commands::status::status))
+        .route("/schema", get(radcam_commands::schema::list))
+        .route("/schema/{action}", get(radcam_commands::schema::describe))
+        .route("/snapshots", get(radcam_commands::snapshot::list))
+        .route(
+            "/snapshots/{name}",
+            get(radcam_commands::snapshot::download).delete(radcam_commands::snapshot::delete),
+        )
+        .route(
+            "/firmware/{camera_uuid}",
+            get(radcam_commands::firmware::upgrade_status)
+                .post(radcam_commands::firmware::upload)
+                .layer(DefaultBodyLimit::max(FIRMWARE_MAX_SIZE)),
+        )
         .layer(TraceLayer::new_for_http())
         .layer(CorsLayer::permissive())
 }
@@ -1,10 +1,13 @@
+use autopilot::api::{ActuatorsControl, Preset, PresetName};
 use axum::response::IntoResponse;
-use mcm_client::Cameras;
+use indexmap::IndexMap;
+use mcm_client::{CameraKind, Cameras};
 use radcam_commands::{
     CameraControl, protocol::display::advanced_display::AdvancedParameterSetting,
 };
 use serde::Serialize;
 use serde_json::json;
+use uuid::Uuid;
 
 #[derive(Debug, Serialize)]
 pub struct CockpitExtras {
@@ -59,12 +62,13 @@ pub enum HttpRequestMethod {
 
 pub async fn cockpit_extras() -> impl IntoResponse {
     let cameras = mcm_client::cameras().await;
+    let presets = autopilot::presets().await;
 
     let cockpit_extras = CockpitExtras {
         target_system: "Cockpit".to_string(),
         target_cockpit_api_version: "1.0.0".to_string(),
         widgets: widgets(&cameras),
-        actions: actions(&cameras),
+        actions: actions(&cameras, &presets),
     };
 
     let json = serde_json::to_string_pretty(&cockpit_extras).unwrap();
@@ -80,7 +84,7 @@ fn widgets(cameras: &Cameras) -> Vec<CockpitWidget> {
     cameras
         .iter()
         .map(|(camera_uuid, camera)| CockpitWidget {
-            name: format!("RadCam ({})", camera.hostname),
+            name: format!("{} ({})", kind_name(camera.kind), camera.hostname),
             config_iframe_url: None,
             iframe_url: format!("/?uuid={camera_uuid}&cockpit_mode=true"),
             iframe_icon: "/assets/logo.svg".to_string(),
@@ -89,36 +93,83 @@ fn widgets(cameras: &Cameras) -> Vec<CockpitWidget> {
         .collect()
 }
 
-fn actions(cameras: &Cameras) -> Vec<CockpitAction> {
+fn actions(cameras: &Cameras, presets: &IndexMap<Uuid, Vec<Preset>>) -> Vec<CockpitAction> {
     cameras
         .iter()
         .flat_map(|(camera_uuid, camera)| {
-            let name: String = format!("RadCam White Balance ({})", camera.hostname);
-
-            vec![CockpitAction {
-                id: format!("radcam-white-balance-{camera_uuid}"),
-                name: name.clone(),
-                action_type: CockpitActionType::HttpRequest(HttpRequestAction {
-                    name,
-                    url: "http://{{ vehicle-address }}/extensionv2/radcammanager/v1/camera/control"
+            let preset_actions = presets
+                .get(camera_uuid)
+                .into_iter()
+                .flatten()
+                .map(move |preset| {
+                    let name = format!(
+                        "{} Preset {} ({})",
+                        kind_name(camera.kind),
+                        preset.name,
+                        camera.hostname
+                    );
+
+                    CockpitAction {
+                        id: format!("radcam-preset-{}-{camera_uuid}", preset.name),
+                        name: name.clone(),
+                        action_type: CockpitActionType::HttpRequest(HttpRequestAction {
+                            name,
+                            url: "http://{{ vehicle-address }}/extensionv2/radcammanager/v1/autopilot/control"
+                                .to_string(),
+                            method: HttpRequestMethod::POST,
+                            headers: json!({
+                                "Content-Type": "application/json",
+                            }),
+                            url_params: json!({}),
+                            body: json!(ActuatorsControl {
+                                camera_uuid: *camera_uuid,
+                                action: autopilot::api::Action::RecallPreset(PresetName {
+                                    name: preset.name.clone(),
+                                }),
+                            })
+                            .to_string(),
+                        }),
+                    }
+                });
+
+            // Only the RadCam backend has a white balance trigger
+            let white_balance_action = (camera.kind == CameraKind::RadCam).then(|| {
+                let name = format!("RadCam White Balance ({})", camera.hostname);
+
+                CockpitAction {
+                    id: format!("radcam-white-balance-{camera_uuid}"),
+                    name: name.clone(),
+                    action_type: CockpitActionType::HttpRequest(HttpRequestAction {
+                        name,
+                        url: "http://{{ vehicle-address }}/extensionv2/radcammanager/v1/camera/control"
+                            .to_string(),
+                        method: HttpRequestMethod::POST,
+                        headers: json!({
+                            "Content-Type": "application/json",
+                        }),
+                        url_params: json!({}),
+                        body: json!(CameraControl {
+                            camera_uuid: *camera_uuid,
+                            action: radcam_commands::Action::SetImageAdjustmentEx(
+                                AdvancedParameterSetting {
+                                    once_awb: Some(1),
+                                    ..Default::default()
+                                }
+                            ),
+                        })
                         .to_string(),
-                    method: HttpRequestMethod::POST,
-                    headers: json!({
-                        "Content-Type": "application/json",
                     }),
-                    url_params: json!({}),
-                    body: json!(CameraControl {
-                        camera_uuid: *camera_uuid,
-                        action: radcam_commands::Action::SetImageAdjustmentEx(
-                            AdvancedParameterSetting {
-                                once_awb: Some(1),
-                                ..Default::default()
-                            }
-                        ),
-                    })
-                    .to_string(),
-                }),
-            }]
+                }
+            });
+
+            white_balance_action.into_iter().chain(preset_actions)
         })
         .collect()
 }
+
+fn kind_name(kind: CameraKind) -> &'static str {
+    match kind {
+        CameraKind::RadCam => "RadCam",
+        CameraKind::Onvif => "Camera",
+    }
+}
@@ -0,0 +1,14 @@
+use axum::{Router, routing::get};
+use tower_http::{cors::CorsLayer, trace::TraceLayer};
+use tracing::*;
+use utils::jobs;
+
+#[instrument(level = "trace")]
+pub fn router() -> Router {
+    Router::new()
+        .route("/", get(jobs::list))
+        .route("/events", get(jobs::events_websocket_handler))
+        .route("/{id}", get(jobs::get).delete(jobs::delete))
+        .layer(TraceLayer::new_for_http())
+        .layer(CorsLayer::permissive())
+}
@@ -6,6 +6,7 @@ pub mod blueos;
 pub mod camera;
 pub mod cockpit;
 pub mod info;
+pub mod jobs;
 pub mod log;
 
 #[instrument(level = "trace")]
@@ -14,6 +15,7 @@ pub fn router() -> Router {
         .nest("/camera", camera::router())
         .nest("/log", log::router())
         .nest("/info", info::router())
+        .nest("/jobs", jobs::router())
         .nest("/autopilot", autopilot::router())
         .route("/register_service", get(blueos::server_metadata))
         .route("/cockpit_extras.json", get(cockpit::cockpit_extras))
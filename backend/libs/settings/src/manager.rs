@@ -8,7 +8,11 @@ use tokio_stream::{StreamExt, wrappers::ReadDirStream};
 use tracing::*;
 use uuid::Uuid;
 
-use crate::{CameraActuatorsSettings, RawSettingsData, SettingsDataImpl, v1::SettingsDataV1};
+use crate::{
+    CameraActuatorsSettings, CameraCredentials, CameraImageSettings, CameraPresets, CameraRules,
+    OsdSettings, RawSettingsData, SettingsDataImpl, v1::SettingsDataV1, v2::SettingsDataV2,
+    v3::SettingsDataV3,
+};
 
 pub static MANAGER: OnceCell<RwLock<Manager>> = OnceCell::new();
 
@@ -30,7 +34,15 @@ impl Settings {
     ) -> Result<Self> {
         let settings = Self {
             path,
-            inner: Box::new(SettingsDataV1 { actuators }),
+            inner: Box::new(SettingsDataV3 {
+                actuators,
+                presets: IndexMap::default(),
+                osd: IndexMap::default(),
+                image: IndexMap::default(),
+                rules: IndexMap::default(),
+                labels: IndexMap::default(),
+                credentials: IndexMap::default(),
+            }),
         };
 
         settings.save().await?;
@@ -48,10 +60,20 @@ impl Settings {
                 .with_context(|| format!("Failed to parse JSON from settings: {path:?}"))?;
 
             let inner = match raw {
-                RawSettingsData::V1(v1) => Box::new(v1),
+                RawSettingsData::V3(v3) => v3,
+                RawSettingsData::V2(v2) => {
+                    warn!("Migrating settings V2 to V3 from {path:?}");
+                    Box::new(SettingsDataV3::from(v2))
+                }
+                RawSettingsData::V1(v1) => {
+                    warn!("Migrating settings V1 to V3 from {path:?}");
+                    Box::new(SettingsDataV3::from(SettingsDataV2::from(v1)))
+                }
                 RawSettingsData::V0(v0) => {
-                    warn!("Migrating settings V0 to V1 from {path:?}");
-                    Box::new(SettingsDataV1::from(v0))
+                    warn!("Migrating settings V0 to V3 from {path:?}");
+                    Box::new(SettingsDataV3::from(SettingsDataV2::from(
+                        SettingsDataV1::from(v0),
+                    )))
                 }
             };
 
@@ -149,6 +171,54 @@ impl Settings {
         self.inner.get_actuators_mut()
     }
 
+    pub fn get_presets(&self) -> &IndexMap<Uuid, CameraPresets> {
+        self.inner.get_presets()
+    }
+
+    pub fn get_presets_mut(&mut self) -> &mut IndexMap<Uuid, CameraPresets> {
+        self.inner.get_presets_mut()
+    }
+
+    pub fn get_osd(&self) -> &IndexMap<Uuid, OsdSettings> {
+        self.inner.get_osd()
+    }
+
+    pub fn get_osd_mut(&mut self) -> &mut IndexMap<Uuid, OsdSettings> {
+        self.inner.get_osd_mut()
+    }
+
+    pub fn get_image(&self) -> &IndexMap<Uuid, CameraImageSettings> {
+        self.inner.get_image()
+    }
+
+    pub fn get_image_mut(&mut self) -> &mut IndexMap<Uuid, CameraImageSettings> {
+        self.inner.get_image_mut()
+    }
+
+    pub fn get_rules(&self) -> &IndexMap<Uuid, CameraRules> {
+        self.inner.get_rules()
+    }
+
+    pub fn get_rules_mut(&mut self) -> &mut IndexMap<Uuid, CameraRules> {
+        self.inner.get_rules_mut()
+    }
+
+    pub fn get_labels(&self) -> &IndexMap<Uuid, Vec<String>> {
+        self.inner.get_labels()
+    }
+
+    pub fn get_labels_mut(&mut self) -> &mut IndexMap<Uuid, Vec<String>> {
+        self.inner.get_labels_mut()
+    }
+
+    pub fn get_credentials(&self) -> &IndexMap<Uuid, CameraCredentials> {
+        self.inner.get_credentials()
+    }
+
+    pub fn get_credentials_mut(&mut self) -> &mut IndexMap<Uuid, CameraCredentials> {
+        self.inner.get_credentials_mut()
+    }
+
     pub fn to_raw(&self) -> RawSettingsData {
         self.inner.to_raw()
     }
@@ -1,15 +1,17 @@
 pub mod manager;
 mod v0;
 mod v1;
+mod v2;
+mod v3;
 
 use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 // Note: make only the current API version accessible:
-pub use v1::*;
+pub use v3::*;
 
-use crate::v0::SettingsDataV0;
+use crate::{v0::SettingsDataV0, v1::SettingsDataV1, v2::SettingsDataV2};
 
 pub use manager::{MANAGER, Manager, init};
 
@@ -18,11 +20,25 @@ pub use manager::{MANAGER, Manager, init};
 pub enum RawSettingsData {
     V0(SettingsDataV0),
     V1(SettingsDataV1),
+    V2(SettingsDataV2),
+    V3(Box<SettingsDataV3>),
 }
 
 pub trait SettingsDataImpl: std::fmt::Debug + Send + Sync {
     fn get_actuators(&self) -> &IndexMap<Uuid, CameraActuatorsSettings>;
     fn get_actuators_mut(&mut self) -> &mut IndexMap<Uuid, CameraActuatorsSettings>;
+    fn get_presets(&self) -> &IndexMap<Uuid, CameraPresets>;
+    fn get_presets_mut(&mut self) -> &mut IndexMap<Uuid, CameraPresets>;
+    fn get_osd(&self) -> &IndexMap<Uuid, OsdSettings>;
+    fn get_osd_mut(&mut self) -> &mut IndexMap<Uuid, OsdSettings>;
+    fn get_image(&self) -> &IndexMap<Uuid, CameraImageSettings>;
+    fn get_image_mut(&mut self) -> &mut IndexMap<Uuid, CameraImageSettings>;
+    fn get_rules(&self) -> &IndexMap<Uuid, CameraRules>;
+    fn get_rules_mut(&mut self) -> &mut IndexMap<Uuid, CameraRules>;
+    fn get_labels(&self) -> &IndexMap<Uuid, Vec<String>>;
+    fn get_labels_mut(&mut self) -> &mut IndexMap<Uuid, Vec<String>>;
+    fn get_credentials(&self) -> &IndexMap<Uuid, CameraCredentials>;
+    fn get_credentials_mut(&mut self) -> &mut IndexMap<Uuid, CameraCredentials>;
 
     fn to_raw(&self) -> RawSettingsData;
 }
@@ -2,12 +2,11 @@ use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::{RawSettingsData, SettingsDataImpl, v0::SettingsDataV0};
+use crate::v0::SettingsDataV0;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SettingsDataV1 {
     pub actuators: IndexMap<Uuid, CameraActuatorsSettings>,
-    // todo: pub image: IndexMap<Uuid, CameraImageSettings>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -15,12 +14,30 @@ pub struct CameraActuatorsSettings {
     pub parameters: ActuatorsParameters,
     pub calibration: Calibration,
     pub state: ActuatorsState,
+    #[serde(default = "default_script_buttons")]
+    pub script_buttons: Vec<ScriptButton>,
+}
+
+/// The buttons the Lua script forwards when the settings predate them
+fn default_script_buttons() -> Vec<ScriptButton> {
+    [
+        ScriptButtonAction::ZoomIn,
+        ScriptButtonAction::ZoomOut,
+        ScriptButtonAction::FocusNear,
+        ScriptButtonAction::FocusFar,
+    ]
+    .into_iter()
+    .zip(1..)
+    .map(|(action, button)| ScriptButton { button, action })
+    .collect()
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Calibration {
     pub closest_points: FocusZoomPoints,
     pub furthest_points: FocusZoomPoints,
+    #[serde(default)]
+    pub distance_points: DistanceFocusPoints,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -42,6 +59,12 @@ pub struct ActuatorsParameters {
     pub script_channel_trim: u16,
     pub script_channel_max: u16,
     pub enable_focus_and_zoom_correlation: bool,
+    #[serde(default)]
+    pub focus_interpolation: InterpolationMode,
+    #[serde(default)]
+    pub enable_rangefinder_focus: bool,
+    #[serde(default)]
+    pub rangefinder_orientation: RangefinderOrientation,
 
     // Zoom channel parameters
     pub zoom_channel: ServoChannel,
@@ -128,6 +151,26 @@ pub enum ScriptFunction {
     SCRIPT16 = 109,
 }
 
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InterpolationMode {
+    #[default]
+    Linear,
+    MonotoneCubic,
+    CatmullRom,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[repr(u8)]
+pub enum RangefinderOrientation {
+    #[default]
+    Forward = 0,
+    Right = 2,
+    Back = 4,
+    Left = 6,
+    Up = 24,
+    Down = 25,
+}
+
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[repr(u8)]
 pub enum MountType {
@@ -154,6 +197,29 @@ pub struct FocusZoomPoint {
     pub zoom: u32,
 }
 
+pub type DistanceFocusPoints = Vec<DistanceFocusPoint>;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct DistanceFocusPoint {
+    pub distance: u32,
+    pub position: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ScriptButton {
+    pub button: u8,
+    pub action: ScriptButtonAction,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum ScriptButtonAction {
+    ZoomIn,
+    ZoomOut,
+    FocusNear,
+    FocusFar,
+    RecallPreset(String),
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
 pub struct ActuatorsState {
     pub focus: Option<f32>,
@@ -168,17 +234,3 @@ impl From<SettingsDataV0> for SettingsDataV1 {
         }
     }
 }
-
-impl SettingsDataImpl for SettingsDataV1 {
-    fn get_actuators(&self) -> &IndexMap<Uuid, CameraActuatorsSettings> {
-        &self.actuators
-    }
-
-    fn get_actuators_mut(&mut self) -> &mut IndexMap<Uuid, CameraActuatorsSettings> {
-        &mut self.actuators
-    }
-
-    fn to_raw(&self) -> RawSettingsData {
-        RawSettingsData::V1(self.clone())
-    }
-}
@@ -0,0 +1,147 @@
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+// V2 only adds the presets and the OSD, the actuators types are kept from V1:
+use crate::v1::SettingsDataV1;
+pub use crate::v1::{
+    ActuatorsParameters, ActuatorsState, Calibration, CameraActuatorsSettings, CameraID,
+    DistanceFocusPoint, DistanceFocusPoints, FocusZoomPoint, FocusZoomPoints, InterpolationMode,
+    MountType, RangefinderOrientation, ScriptButton, ScriptButtonAction, ScriptFunction,
+    ServoChannel, TiltChannelFunction,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingsDataV2 {
+    pub actuators: IndexMap<Uuid, CameraActuatorsSettings>,
+    pub presets: IndexMap<Uuid, CameraPresets>,
+    #[serde(default)]
+    pub osd: IndexMap<Uuid, OsdSettings>,
+}
+
+/// The presets of a camera, by name
+pub type CameraPresets = IndexMap<String, Preset>;
+
+/// The image adjustments are kept in the camera protocol format, which is versioned with the camera
+/// firmware rather than with these settings: json the protocol doesn't accept anymore is ignored,
+/// with a warning, when the presets are loaded
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Preset {
+    pub state: ActuatorsState,
+    /// Raw `setImageAdjustment` json, as sent to the camera
+    #[serde(default)]
+    pub image_adjustment: Option<serde_json::Value>,
+    /// Raw `setImageAdjustmentEx` json, as sent to the camera
+    #[serde(default)]
+    pub image_adjustment_ex: Option<serde_json::Value>,
+}
+
+/// The telemetry overlaid on the camera image
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OsdSettings {
+    pub enabled: bool,
+    /// How often the overlays are updated, in milliseconds
+    pub update_interval_ms: u64,
+    pub fields: Vec<OsdField>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct OsdField {
+    pub item: OsdItem,
+    /// Horizontal position, in percent of the image width
+    pub x: u8,
+    /// Vertical position, in percent of the image height
+    pub y: u8,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum OsdItem {
+    Depth,
+    Heading,
+    Time,
+}
+
+impl From<SettingsDataV1> for SettingsDataV2 {
+    fn from(v1: SettingsDataV1) -> Self {
+        SettingsDataV2 {
+            actuators: v1.actuators,
+            presets: IndexMap::default(),
+            osd: IndexMap::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::RawSettingsData;
+
+    use super::*;
+
+    #[test]
+    fn migrate_v1_to_v2() {
+        let uuid = Uuid::new_v5(&Uuid::NAMESPACE_DNS, b"uuid.example.com");
+
+        let raw = json!({
+            "version": "V1",
+            "actuators": {
+                uuid.to_string(): {
+                    "parameters": {
+                        "camera_id": "CAM1",
+                        "focus_channel": "SERVO1",
+                        "focus_channel_min": 1100,
+                        "focus_channel_trim": 1500,
+                        "focus_channel_max": 1900,
+                        "focus_margin_gain": 1.0,
+                        "script_function": "SCRIPT1",
+                        "script_channel": "SERVO2",
+                        "script_channel_min": 1100,
+                        "script_channel_trim": 1500,
+                        "script_channel_max": 1900,
+                        "enable_focus_and_zoom_correlation": true,
+                        "zoom_channel": "SERVO3",
+                        "zoom_channel_min": 1100,
+                        "zoom_channel_trim": 1500,
+                        "zoom_channel_max": 1900,
+                        "tilt_channel": "SERVO4",
+                        "tilt_channel_min": 1100,
+                        "tilt_channel_trim": 1500,
+                        "tilt_channel_max": 1900,
+                        "tilt_channel_reversed": false,
+                        "tilt_mnt_type": "BrushlessPWM",
+                        "tilt_mnt_pitch_min": -90,
+                        "tilt_mnt_pitch_max": 90
+                    },
+                    "calibration": {
+                        "closest_points": [],
+                        "furthest_points": []
+                    },
+                    "state": {
+                        "focus": 1.0,
+                        "zoom": 2.0,
+                        "tilt": null
+                    }
+                }
+            }
+        });
+
+        let RawSettingsData::V1(v1) = serde_json::from_value(raw).unwrap() else {
+            panic!("Expected V1 settings");
+        };
+
+        let v2 = SettingsDataV2::from(v1);
+
+        assert!(v2.presets.is_empty());
+        assert!(v2.osd.is_empty());
+        assert_eq!(v2.actuators[&uuid].state.zoom, Some(2.0));
+        assert_eq!(
+            v2.actuators[&uuid]
+                .script_buttons
+                .iter()
+                .map(|script_button| script_button.button)
+                .collect::<Vec<_>>(),
+            vec![1, 2, 3, 4]
+        );
+    }
+}
@@ -0,0 +1,242 @@
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+// V3 only adds the image profiles, the rules, the labels and the credentials, the other types are
+// kept from V2:
+pub use crate::v2::{
+    ActuatorsParameters, ActuatorsState, Calibration, CameraActuatorsSettings, CameraID,
+    CameraPresets, DistanceFocusPoint, DistanceFocusPoints, FocusZoomPoint, FocusZoomPoints,
+    InterpolationMode, MountType, OsdField, OsdItem, OsdSettings, Preset, RangefinderOrientation,
+    ScriptButton, ScriptButtonAction, ScriptFunction, ServoChannel, TiltChannelFunction,
+};
+use crate::{RawSettingsData, SettingsDataImpl, v2::SettingsDataV2};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingsDataV3 {
+    pub actuators: IndexMap<Uuid, CameraActuatorsSettings>,
+    pub presets: IndexMap<Uuid, CameraPresets>,
+    pub osd: IndexMap<Uuid, OsdSettings>,
+    #[serde(default)]
+    pub image: IndexMap<Uuid, CameraImageSettings>,
+    #[serde(default)]
+    pub rules: IndexMap<Uuid, CameraRules>,
+    /// Names to select the cameras by, like "port" or "forward"
+    #[serde(default)]
+    pub labels: IndexMap<Uuid, Vec<String>>,
+    /// For the cameras whose default credentials are unknown, or were changed
+    #[serde(default)]
+    pub credentials: IndexMap<Uuid, CameraCredentials>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct CameraImageSettings {
+    /// The image profiles of a camera, by name
+    pub profiles: IndexMap<String, ImageProfile>,
+    /// The last profile applied to the camera
+    #[serde(default)]
+    pub active_profile: Option<String>,
+    /// What to do when the camera comes back with settings different from the active profile
+    #[serde(default)]
+    pub reapply_policy: ReapplyPolicy,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ReapplyPolicy {
+    Auto,
+    #[default]
+    Notify,
+    Ignore,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ImageProfile {
+    /// Raw `setImageAdjustment` json, as sent to the camera
+    #[serde(default)]
+    pub image_adjustment: Option<serde_json::Value>,
+    /// Raw `setImageAdjustmentEx` json, as sent to the camera
+    #[serde(default)]
+    pub image_adjustment_ex: Option<serde_json::Value>,
+    /// Raw `setVencConf` json, as sent to the camera, one for each channel
+    #[serde(default)]
+    pub video_parameters: Vec<serde_json::Value>,
+}
+
+pub type CameraRules = IndexMap<String, Rule>;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Rule {
+    pub enabled: bool,
+    pub condition: RuleCondition,
+    pub action: RuleAction,
+    #[serde(default)]
+    pub exit_action: Option<RuleAction>,
+    pub dwell_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum RuleCondition {
+    Depth {
+        comparison: Comparison,
+        threshold: f32,
+        hysteresis: f32,
+    },
+    LightsPwm {
+        channel: ServoChannel,
+        comparison: Comparison,
+        threshold: u16,
+        hysteresis: u16,
+    },
+    TimeOfDay {
+        start_minute: u16,
+        end_minute: u16,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Comparison {
+    Greater,
+    Less,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum RuleAction {
+    ApplyProfile(String),
+    /// Raw camera control json, as sent to the camera
+    CameraAction(serde_json::Value),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CameraCredentials {
+    pub username: String,
+    pub password: String,
+}
+
+impl From<SettingsDataV2> for SettingsDataV3 {
+    fn from(v2: SettingsDataV2) -> Self {
+        SettingsDataV3 {
+            actuators: v2.actuators,
+            presets: v2.presets,
+            osd: v2.osd,
+            image: IndexMap::default(),
+            rules: IndexMap::default(),
+            labels: IndexMap::default(),
+            credentials: IndexMap::default(),
+        }
+    }
+}
+
+impl SettingsDataImpl for SettingsDataV3 {
+    fn get_actuators(&self) -> &IndexMap<Uuid, CameraActuatorsSettings> {
+        &self.actuators
+    }
+
+    fn get_actuators_mut(&mut self) -> &mut IndexMap<Uuid, CameraActuatorsSettings> {
+        &mut self.actuators
+    }
+
+    fn get_presets(&self) -> &IndexMap<Uuid, CameraPresets> {
+        &self.presets
+    }
+
+    fn get_presets_mut(&mut self) -> &mut IndexMap<Uuid, CameraPresets> {
+        &mut self.presets
+    }
+
+    fn get_osd(&self) -> &IndexMap<Uuid, OsdSettings> {
+        &self.osd
+    }
+
+    fn get_osd_mut(&mut self) -> &mut IndexMap<Uuid, OsdSettings> {
+        &mut self.osd
+    }
+
+    fn get_image(&self) -> &IndexMap<Uuid, CameraImageSettings> {
+        &self.image
+    }
+
+    fn get_image_mut(&mut self) -> &mut IndexMap<Uuid, CameraImageSettings> {
+        &mut self.image
+    }
+
+    fn get_rules(&self) -> &IndexMap<Uuid, CameraRules> {
+        &self.rules
+    }
+
+    fn get_rules_mut(&mut self) -> &mut IndexMap<Uuid, CameraRules> {
+        &mut self.rules
+    }
+
+    fn get_labels(&self) -> &IndexMap<Uuid, Vec<String>> {
+        &self.labels
+    }
+
+    fn get_labels_mut(&mut self) -> &mut IndexMap<Uuid, Vec<String>> {
+        &mut self.labels
+    }
+
+    fn get_credentials(&self) -> &IndexMap<Uuid, CameraCredentials> {
+        &self.credentials
+    }
+
+    fn get_credentials_mut(&mut self) -> &mut IndexMap<Uuid, CameraCredentials> {
+        &mut self.credentials
+    }
+
+    fn to_raw(&self) -> RawSettingsData {
+        RawSettingsData::V3(Box::new(self.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn migrate_v2_to_v3() {
+        let uuid = Uuid::new_v5(&Uuid::NAMESPACE_DNS, b"uuid.example.com");
+
+        let raw = json!({
+            "version": "V2",
+            "actuators": {},
+            "presets": {
+                uuid.to_string(): {
+                    "wide": {
+                        "state": {
+                            "focus": 1.0,
+                            "zoom": 0.0,
+                            "tilt": null
+                        },
+                        "image_adjustment": { "brightness": 60 }
+                    }
+                }
+            },
+            "osd": {
+                uuid.to_string(): {
+                    "enabled": true,
+                    "update_interval_ms": 1000,
+                    "fields": [{ "item": "Depth", "x": 2, "y": 92 }]
+                }
+            }
+        });
+
+        let RawSettingsData::V2(v2) = serde_json::from_value(raw).unwrap() else {
+            panic!("Expected V2 settings");
+        };
+
+        let v3 = SettingsDataV3::from(v2);
+
+        assert!(v3.image.is_empty());
+        assert!(v3.rules.is_empty());
+        assert!(v3.labels.is_empty());
+        assert!(v3.credentials.is_empty());
+        assert_eq!(
+            v3.presets[&uuid]["wide"].image_adjustment,
+            Some(json!({ "brightness": 60 }))
+        );
+        assert!(v3.osd[&uuid].enabled);
+        assert!(matches!(v3.to_raw(), RawSettingsData::V3(_)));
+    }
+}
@@ -0,0 +1,418 @@
+use std::{
+    future::Future,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use axum::{
+    extract::{Path, WebSocketUpgrade, ws::WebSocket},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use chrono::Utc;
+use indexmap::IndexMap;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    sync::{RwLock, broadcast},
+    task::AbortHandle,
+};
+use tracing::*;
+use ts_rs::TS;
+use uuid::Uuid;
+
+/// How long the finished jobs are kept for auditing
+const FINISHED_JOBS_RETENTION: Duration = Duration::from_secs(60 * 60);
+/// The oldest finished jobs are dropped before their time when there are more than these
+const MAX_FINISHED_JOBS: usize = 100;
+const CHANNEL_CAPACITY: usize = 256;
+
+static JOBS: Lazy<RwLock<Jobs>> = Lazy::new(|| RwLock::new(Jobs::default()));
+
+static EVENTS: Lazy<broadcast::Sender<Job>> = Lazy::new(|| broadcast::channel(CHANNEL_CAPACITY).0);
+
+pub type JobId = u64;
+
+/// An operation that takes too long to be answered in its own request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+pub struct Job {
+    pub id: JobId,
+    /// What the job does, like the camera action it runs
+    pub name: String,
+    /// The camera it works on, if any
+    #[ts(as = "Option<String>")]
+    pub camera_uuid: Option<Uuid>,
+    pub state: JobState,
+    /// Whether it can still be cancelled
+    pub cancellable: bool,
+    /// From 0 to 1, when it can be told
+    pub progress: Option<f32>,
+    /// What it is doing now
+    pub message: Option<String>,
+    /// When it started, in RFC 3339
+    pub started_at: String,
+    /// When it finished, in RFC 3339
+    pub finished_at: Option<String>,
+    /// Only present when it succeeded
+    #[ts(type = "unknown")]
+    pub result: Option<serde_json::Value>,
+    /// Only present when it failed
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+pub enum JobState {
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+impl JobState {
+    fn is_running(&self) -> bool {
+        matches!(self, JobState::Running)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobError {
+    NotFound { id: JobId },
+    NotCancellable { id: JobId },
+}
+
+impl std::fmt::Display for JobError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            JobError::NotFound { id } => write!(f, "Job {id} not found"),
+            JobError::NotCancellable { id } => write!(f, "Job {id} can't be cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for JobError {}
+
+#[derive(Debug, Default)]
+struct Jobs {
+    next_id: JobId,
+    jobs: IndexMap<JobId, JobEntry>,
+}
+
+#[derive(Debug)]
+struct JobEntry {
+    job: Job,
+    finished: Option<Instant>,
+    abort_handle: Option<AbortHandle>,
+}
+
+/// Lets a running job report how it is going
+#[derive(Debug, Clone, Copy)]
+pub struct JobHandle {
+    id: JobId,
+}
+
+impl JobHandle {
+    pub async fn progress(&self, progress: Option<f32>, message: impl Into<String>) {
+        let message = message.into();
+
+        update_job(self.id, |entry| {
+            entry.job.progress = progress.map(|progress| progress.clamp(0., 1.));
+            entry.job.message = Some(message);
+        })
+        .await;
+    }
+
+    async fn finish(&self, result: Result<serde_json::Value>) {
+        match &result {
+            Ok(_) => info!("Job {} succeeded", self.id),
+            Err(error) => warn!("Job {} failed: {error:#}", self.id),
+        }
+
+        update_job(self.id, |entry| {
+            if !entry.job.state.is_running() {
+                return;
+            }
+
+            match result {
+                Ok(res) => {
+                    entry.job.state = JobState::Succeeded;
+                    entry.job.progress = Some(1.);
+                    entry.job.result = Some(res);
+                }
+                Err(error) => {
+                    entry.job.state = JobState::Failed;
+                    entry.job.error = Some(format!("{error:#}"));
+                }
+            }
+
+            finish(entry);
+        })
+        .await;
+    }
+}
+
+/// Runs the operation in the background, answering its job right away
+#[instrument(level = "debug", skip(run))]
+pub async fn spawn<F, Fut>(name: &str, camera_uuid: Option<Uuid>, cancellable: bool, run: F) -> Job
+where
+    F: FnOnce(JobHandle) -> Fut,
+    Fut: Future<Output = Result<serde_json::Value>> + Send + 'static,
+{
+    // The task is spawned with the jobs locked, so it can't finish before its abort handle is
+    // stored
+    let mut jobs = JOBS.write().await;
+    prune(&mut jobs.jobs, Instant::now());
+
+    let id = jobs.next_id;
+    jobs.next_id += 1;
+
+    let job = Job {
+        id,
+        name: name.to_string(),
+        camera_uuid,
+        state: JobState::Running,
+        cancellable,
+        progress: None,
+        message: None,
+        started_at: Utc::now().to_rfc3339(),
+        finished_at: None,
+        result: None,
+        error: None,
+    };
+
+    match camera_uuid {
+        Some(camera_uuid) => info!("Job {id} started: {name} on camera {camera_uuid}"),
+        None => info!("Job {id} started: {name}"),
+    }
+    publish(job.clone());
+
+    let handle = JobHandle { id };
+    let future = run(handle);
+    let task = tokio::spawn(async move {
+        let result = future.await;
+
+        handle.finish(result).await;
+    });
+
+    jobs.jobs.insert(
+        id,
+        JobEntry {
+            job: job.clone(),
+            finished: None,
+            abort_handle: cancellable.then(|| task.abort_handle()),
+        },
+    );
+
+    job
+}
+
+#[instrument(level = "debug")]
+pub async fn get_job(id: JobId) -> Option<Job> {
+    JOBS.read()
+        .await
+        .jobs
+        .get(&id)
+        .map(|entry| entry.job.clone())
+}
+
+#[instrument(level = "debug")]
+pub async fn jobs() -> Vec<Job> {
+    let mut jobs = JOBS.write().await;
+    prune(&mut jobs.jobs, Instant::now());
+
+    jobs.jobs.values().map(|entry| entry.job.clone()).collect()
+}
+
+/// Stops a running job. What the job already did to the camera is not undone
+#[instrument(level = "debug")]
+pub async fn cancel(id: JobId) -> Result<Job> {
+    let job = {
+        let mut jobs = JOBS.write().await;
+
+        let entry = jobs.jobs.get_mut(&id).ok_or(JobError::NotFound { id })?;
+
+        if !entry.job.state.is_running() {
+            return Ok(entry.job.clone());
+        }
+
+        let Some(abort_handle) = entry.abort_handle.take() else {
+            return Err(JobError::NotCancellable { id }.into());
+        };
+
+        abort_handle.abort();
+
+        entry.job.state = JobState::Cancelled;
+        finish(entry);
+
+        entry.job.clone()
+    };
+
+    info!("Job {id} cancelled");
+    publish(job.clone());
+
+    Ok(job)
+}
+
+pub fn subscribe() -> broadcast::Receiver<Job> {
+    EVENTS.subscribe()
+}
+
+/// The name the API gives to the action, as in its `action` tag
+pub fn action_name(action: &(impl Serialize + std::fmt::Debug)) -> String {
+    serde_json::to_value(action)
+        .ok()
+        .and_then(|value| value.get("action")?.as_str().map(str::to_string))
+        .unwrap_or_else(|| format!("{action:?}"))
+}
+
+fn finish(entry: &mut JobEntry) {
+    entry.job.cancellable = false;
+    entry.job.finished_at = Some(Utc::now().to_rfc3339());
+    entry.finished = Some(Instant::now());
+    entry.abort_handle = None;
+}
+
+async fn update_job(id: JobId, update: impl FnOnce(&mut JobEntry)) {
+    let job = {
+        let mut jobs = JOBS.write().await;
+
+        let Some(entry) = jobs.jobs.get_mut(&id) else {
+            return;
+        };
+
+        update(entry);
+
+        entry.job.clone()
+    };
+
+    publish(job);
+}
+
+fn publish(job: Job) {
+    crate::broadcast(&EVENTS, job);
+}
+
+/// Drops the finished jobs that are too old, and the oldest ones when there are too many
+fn prune(jobs: &mut IndexMap<JobId, JobEntry>, now: Instant) {
+    jobs.retain(|_, entry| {
+        entry
+            .finished
+            .is_none_or(|finished| now.duration_since(finished) < FINISHED_JOBS_RETENTION)
+    });
+
+    let finished_jobs = jobs
+        .values()
+        .filter(|entry| entry.finished.is_some())
+        .count();
+
+    let mut excess = finished_jobs.saturating_sub(MAX_FINISHED_JOBS);
+    jobs.retain(|_, entry| {
+        if excess == 0 || entry.finished.is_none() {
+            return true;
+        }
+
+        excess -= 1;
+        false
+    });
+}
+
+#[instrument(level = "debug")]
+pub async fn list() -> impl IntoResponse {
+    (
+        StatusCode::OK,
+        serde_json::to_string(&jobs().await).unwrap(),
+    )
+        .into_response()
+}
+
+#[instrument(level = "debug")]
+pub async fn get(Path(id): Path<JobId>) -> impl IntoResponse {
+    match get_job(id).await {
+        Some(job) => (StatusCode::OK, serde_json::to_string(&job).unwrap()).into_response(),
+        None => (StatusCode::NOT_FOUND, JobError::NotFound { id }.to_string()).into_response(),
+    }
+}
+
+#[instrument(level = "debug")]
+pub async fn delete(Path(id): Path<JobId>) -> impl IntoResponse {
+    match cancel(id).await {
+        Ok(job) => (StatusCode::OK, serde_json::to_string(&job).unwrap()).into_response(),
+        Err(error) => {
+            let status = match error.downcast_ref::<JobError>() {
+                Some(JobError::NotFound { .. }) => StatusCode::NOT_FOUND,
+                Some(JobError::NotCancellable { .. }) => StatusCode::CONFLICT,
+                None => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+
+            (status, format!("{error:?}")).into_response()
+        }
+    }
+}
+
+pub async fn events_websocket_handler(ws: WebSocketUpgrade) -> Response {
+    ws.on_upgrade(events_websocket_connection)
+}
+
+#[instrument(level = "debug", skip_all)]
+async fn events_websocket_connection(socket: WebSocket) {
+    let receiver = subscribe();
+
+    // Starts the client with the jobs it may have missed
+    let jobs = jobs().await;
+
+    crate::websocket::forward_broadcast(socket, "Jobs", jobs, receiver).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: JobId, finished: Option<Instant>) -> (JobId, JobEntry) {
+        let job = Job {
+            id,
+            name: "restart".to_string(),
+            camera_uuid: None,
+            state: JobState::Running,
+            cancellable: false,
+            progress: None,
+            message: None,
+            started_at: String::new(),
+            finished_at: None,
+            result: None,
+            error: None,
+        };
+
+        (
+            id,
+            JobEntry {
+                job,
+                finished,
+                abort_handle: None,
+            },
+        )
+    }
+
+    #[test]
+    fn prune_test() {
+        let now = Instant::now();
+        let expired = now - FINISHED_JOBS_RETENTION;
+        let recent = now - Duration::from_secs(1);
+
+        let mut jobs = IndexMap::from([
+            entry(0, Some(expired)),
+            entry(1, None),
+            entry(2, Some(recent)),
+        ]);
+        prune(&mut jobs, now);
+        assert_eq!(jobs.keys().copied().collect::<Vec<_>>(), vec![1, 2]);
+
+        // The oldest finished jobs go first, and the running ones stay
+        let mut jobs = (0..MAX_FINISHED_JOBS as JobId + 2)
+            .map(|id| entry(id, Some(recent)))
+            .chain([entry(1000, None)])
+            .collect::<IndexMap<_, _>>();
+        prune(&mut jobs, now);
+        assert_eq!(jobs.len(), MAX_FINISHED_JOBS + 1);
+        assert_eq!(jobs.first().map(|(id, _)| *id), Some(2));
+        assert!(jobs.contains_key(&1000));
+    }
+}
@@ -1,4 +1,9 @@
 use anyhow::Result;
+use tokio::sync::broadcast;
+use tracing::*;
+
+pub mod jobs;
+pub mod websocket;
 
 /// Helper function to deserialize a json string into a variable of type `T`. In general,
 /// this is a more robust approach than `serde_json::deserialize`.
@@ -11,3 +16,11 @@ where
         .and_then(serde_json::from_value::<T>)
         .map_err(anyhow::Error::msg)
 }
+
+/// Sends the value to all the subscribers of the channel. Sending only fails when nobody is
+/// subscribed, which is fine for events that nobody is waiting for
+pub fn broadcast<T>(sender: &broadcast::Sender<T>, value: T) {
+    if sender.send(value).is_err() {
+        trace!("No subscribers for {}", std::any::type_name::<T>());
+    }
+}
@@ -0,0 +1,61 @@
+use anyhow::Result;
+use axum::extract::ws::{Message, WebSocket};
+use futures::{SinkExt, StreamExt, stream::SplitSink};
+use serde::Serialize;
+use tokio::sync::broadcast;
+use tracing::*;
+
+/// Sends the initial messages to the WebSocket client, and then the broadcast ones as they come,
+/// until either side closes. The receiver should subscribe before the initial messages are read,
+/// so none is missed in between
+#[instrument(level = "debug", skip(socket, initial, receiver))]
+pub async fn forward_broadcast<T>(
+    socket: WebSocket,
+    name: &str,
+    initial: impl IntoIterator<Item = T>,
+    mut receiver: broadcast::Receiver<T>,
+) where
+    T: Serialize + Clone,
+{
+    let (mut websocket_sender, mut websocket_receiver) = socket.split();
+
+    for message in initial {
+        if send(&mut websocket_sender, &message).await.is_err() {
+            return;
+        }
+    }
+
+    loop {
+        let message = tokio::select! {
+            message = receiver.recv() => message,
+            message = websocket_receiver.next() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => continue,
+            },
+        };
+
+        let message = match message {
+            Ok(message) => message,
+            Err(broadcast::error::RecvError::Lagged(n)) => {
+                warn!("{name} WebSocket lagged by {n} messages");
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+
+        if send(&mut websocket_sender, &message).await.is_err() {
+            break;
+        }
+    }
+}
+
+async fn send(
+    websocket_sender: &mut SplitSink<WebSocket, Message>,
+    message: &impl Serialize,
+) -> Result<()> {
+    let message = serde_json::to_string(message)?;
+
+    websocket_sender.send(Message::Text(message.into())).await?;
+
+    Ok(())
+}
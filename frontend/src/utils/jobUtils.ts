@@ -0,0 +1,24 @@
+import axios from 'axios'
+
+import type { Job } from '@/bindings/radcam'
+
+const JOB_POLL_PERIOD_MS = 1000
+
+/**
+ * Waits for a job started by the backend to finish, polling it.
+ * Resolves with the job result, or rejects with its error when it failed or was cancelled.
+ */
+export async function waitForJob(backendApi: string, job: Job): Promise<unknown> {
+    while (job.state === 'Running') {
+        await new Promise((resolve) => setTimeout(resolve, JOB_POLL_PERIOD_MS))
+
+        const response = await axios.get(`${backendApi}/jobs/${job.id}`)
+        job = response.data as Job
+    }
+
+    if (job.state !== 'Succeeded') {
+        throw new Error(job.error ?? `Job ${job.name} ${job.state.toLowerCase()}`)
+    }
+
+    return job.result
+}